@@ -1,11 +1,14 @@
+use std::cell::RefCell;
 use std::marker::PhantomData;
+use std::thread;
 use std::time::{Duration, Instant};
 
 use crate::def::{Agent, AgentReport, Evaluator, Policy, State};
-use crate::spec::AgentSpec;
+use crate::spec::{AgentSpec, Selection};
 
-use super::report::MctsReport;
-use super::search::MctsSearch;
+use super::multiplayer::MultiplayerSearch;
+use super::report::{MctsReport, MoveStats};
+use super::search::{MctsSearch, Tree};
 
 pub struct MctsAgent<S: State, P: Policy<S>, E: Evaluator<S>> {
   _state: PhantomData<S>,
@@ -13,6 +16,16 @@ pub struct MctsAgent<S: State, P: Policy<S>, E: Evaluator<S>> {
   evaluator: E,
   max_samples: u64,
   time_limit: Option<Duration>,
+  // The tree from the previous move, if any.
+  tree: RefCell<Option<Tree<S>>>,
+  // The moves observed since the state in the root of the tree.
+  played: RefCell<Vec<S::Move>>,
+  selection: Selection,
+  exploration: f32,
+  fpu: Option<f32>,
+  threads: usize,
+  // Creates policies and evaluators for the helper threads.
+  new_searcher: Option<Box<Fn() -> (P, E) + Send + Sync>>,
 }
 
 impl<S: State, P: Policy<S>, E: Evaluator<S>> MctsAgent<S, P, E> {
@@ -28,22 +41,155 @@ impl<S: State, P: Policy<S>, E: Evaluator<S>> MctsAgent<S, P, E> {
       evaluator,
       max_samples: max_samples.unwrap_or(1000000000),
       time_limit,
+      tree: RefCell::new(None),
+      played: RefCell::new(Vec::new()),
+      selection: Selection::Puct,
+      exploration: 1.0,
+      fpu: None,
+      threads: 1,
+      new_searcher: None,
     }
   }
 
+  /// Sets the formula for selecting the child to sample, its exploration
+  /// constant and the first-play urgency. See `Selection`.
+  pub fn set_selection(
+    &mut self,
+    selection: Selection,
+    exploration: f32,
+    fpu: Option<f32>,
+  ) {
+    self.selection = selection;
+    self.exploration = exploration;
+    self.fpu = fpu;
+  }
+
+  /// Makes the agent search in `threads` threads (root parallelization).
+  /// Every helper thread grows its own tree with a policy and an evaluator
+  /// created by `new_searcher`, and the visit counts of the root moves are
+  /// merged at the end. The samples are split evenly between the threads.
+  pub fn set_threads<F>(&mut self, threads: usize, new_searcher: F)
+  where
+    F: 'static + Fn() -> (P, E) + Send + Sync,
+  {
+    assert!(threads > 0);
+    self.threads = threads;
+    self.new_searcher = Some(Box::new(new_searcher));
+  }
+
   pub fn select_move_with_report(
     &self,
     state: &S,
   ) -> Result<MctsReport<S::Move>, &'static str> {
-    let search = MctsSearch::new(
-      &self.policy,
-      &self.evaluator,
-      state.clone(),
-    );
-    search.search(
-      self.max_samples,
-      Instant::now() + self.time_limit.unwrap_or(Duration::from_secs(1000000))      
-    )
+    if state.is_terminal() {
+      return Err("the state is terminal");
+    }
+    if state.is_chance() {
+      return Err("the next move is made by chance");
+    }
+    let played = self.played.replace(Vec::new());
+    if state.num_players() > 2 {
+      return Ok(self.select_multiplayer(state));
+    }
+
+    // Follow the observed moves to the new root. If the agent wasn't told
+    // about the moves, look for the state in the tree by its hash.
+    let tree = self
+      .tree
+      .borrow_mut()
+      .take()
+      .and_then(|tree| {
+        if played.is_empty() {
+          tree.reroot(state)
+        } else {
+          tree.advance(&played, state)
+        }
+      })
+      .unwrap_or_else(|| Tree::new(state.clone()));
+    let deadline =
+      Instant::now() + self.time_limit.unwrap_or(Duration::from_secs(1000000));
+    // Every tree needs at least two samples to expand its root.
+    let threads = if self.new_searcher.is_some() {
+      self.threads.min((self.max_samples / 2).max(1) as usize)
+    } else {
+      1
+    };
+    let helper_samples = self.max_samples / threads as u64;
+    let main_samples = self.max_samples - helper_samples * (threads - 1) as u64;
+
+    let (selection, exploration, fpu) =
+      (self.selection, self.exploration, self.fpu);
+    let mut search = MctsSearch::with_tree(&self.policy, &self.evaluator, tree);
+    search.set_selection(selection, exploration, fpu);
+    let result = thread::scope(|scope| {
+      let mut helpers = Vec::new();
+      if let Some(ref new_searcher) = self.new_searcher {
+        for _ in 1..threads {
+          helpers.push(scope.spawn(move || {
+            let (policy, evaluator) = new_searcher();
+            let tree = Tree::new(state.clone());
+            let mut search = MctsSearch::with_tree(policy, evaluator, tree);
+            search.set_selection(selection, exploration, fpu);
+            search
+              .search(helper_samples, deadline)
+              .map(|_| search.into_tree().root_moves())
+          }));
+        }
+      }
+
+      let result = search.search(main_samples, deadline);
+      let helper_moves: Vec<Vec<MoveStats<S::Move>>> = helpers
+        .into_iter()
+        .filter_map(|helper| helper.join().unwrap().ok())
+        .collect();
+      result.map(|_| helper_moves)
+    });
+
+    let tree = search.into_tree();
+    let mut moves = tree.root_moves();
+    let report = result.map(|helper_moves| {
+      for other in helper_moves.iter() {
+        merge_moves(&mut moves, other);
+      }
+      let best = MctsReport::select_best(&moves, state.player());
+      let pv = tree.principal_line(moves[best].m);
+      MctsReport::new(moves, best, pv, state.player_index())
+    });
+    *self.tree.borrow_mut() = Some(tree);
+    report
+  }
+}
+
+impl<S: State, P: Policy<S>, E: Evaluator<S>> MctsAgent<S, P, E> {
+  // N-player UCT for the games with more than two players. The search runs in
+  // a single thread and doesn't reuse the tree.
+  fn select_multiplayer(&self, state: &S) -> MctsReport<S::Move> {
+    let deadline =
+      Instant::now() + self.time_limit.unwrap_or(Duration::from_secs(1000000));
+    let mut search =
+      MultiplayerSearch::new(&self.policy, &self.evaluator, state.clone());
+    search.set_selection(self.selection, self.exploration, self.fpu);
+    search.search(self.max_samples, deadline);
+    let moves = search.root_moves();
+    // The scores are for the player to move, and there are no proven moves.
+    let best = MctsReport::select_best(&moves, true);
+    let pv = search.principal_line(moves[best].m);
+    MctsReport::new(moves, best, pv, state.player_index())
+  }
+}
+
+// Adds the statistics of the moves from another tree with the same root.
+fn merge_moves<M: Copy + Eq>(moves: &mut [MoveStats<M>], other: &[MoveStats<M>]) {
+  for stats in moves.iter_mut() {
+    if let Some(o) = other.iter().find(|o| o.m == stats.m) {
+      let samples = stats.samples + o.samples;
+      if samples > 0 {
+        stats.score = (stats.score * stats.samples as f32
+          + o.score * o.samples as f32) / samples as f32;
+      }
+      stats.samples = samples;
+      stats.proven = stats.proven.or(o.proven);
+    }
   }
 }
 
@@ -57,6 +203,10 @@ impl<S: State, P: Policy<S>, E: Evaluator<S>> Agent<S> for MctsAgent<S, P, E> {
       .map(|report| Box::new(report.clone()) as Box<AgentReport<S::Move>>)
   }
 
+  fn observe_move(&self, m: S::Move) {
+    self.played.borrow_mut().push(m);
+  }
+
   fn spec(&self) -> AgentSpec {
     panic!("not implemented")
   }
@@ -66,7 +216,7 @@ impl<S: State, P: Policy<S>, E: Evaluator<S>> Agent<S> for MctsAgent<S, P, E> {
 mod test {
 
   use crate::def::Game;
-  use crate::games::Subtractor;
+  use crate::games::{Nim, Pig, Subtractor};
   use crate::equal_policy::EqualPolicy;
   use crate::evaluators::TerminalEvaluator;
 
@@ -85,6 +235,157 @@ mod test {
     println!();
     println!("{}", report);
     assert_eq!(2, report.get_move());
+    assert_eq!(2, report.pv()[0]);
+    assert!(report.pv().len() > 1);
+    assert_eq!(3, report.moves().len());
+  }
+
+  #[test]
+  fn play_subtractor_ucb1() {
+    let game = Subtractor::new(10, 4);
+    let mut agent = MctsAgent::new(
+      EqualPolicy::new(), TerminalEvaluator::new(), Some(1000), None);
+    agent.set_selection(Selection::Ucb1, 1.4, None);
+
+    let state = game.new_game();
+    let report = agent.select_move_with_report(&state).unwrap();
+    assert_eq!(2, report.get_move());
+  }
+
+  #[test]
+  fn tree_reuse() {
+    // Few enough samples not to solve the game.
+    let game = Subtractor::new(21, 4);
+    let agent = MctsAgent::new(
+      EqualPolicy::new(), TerminalEvaluator::new(), Some(100), None);
+
+    let mut state = game.new_game();
+    agent.select_move_with_report(&state).unwrap();
+    assert_eq!(100, agent.tree.borrow().as_ref().unwrap().samples());
+
+    state.play(1).unwrap();
+    state.play(1).unwrap();
+    agent.select_move_with_report(&state).unwrap();
+    assert!(agent.tree.borrow().as_ref().unwrap().samples() > 100);
+
+    // Unrelated position, the tree is built from scratch.
+    let state = Subtractor::new(22, 4).new_game();
+    agent.select_move_with_report(&state).unwrap();
+    assert_eq!(100, agent.tree.borrow().as_ref().unwrap().samples());
+  }
+
+  #[test]
+  fn tree_reuse_observed_moves() {
+    let game = Subtractor::new(21, 4);
+    let agent = MctsAgent::new(
+      EqualPolicy::new(), TerminalEvaluator::new(), Some(100), None);
+
+    let mut state = game.new_game();
+    agent.select_move_with_report(&state).unwrap();
+    for &m in [1, 2].iter() {
+      state.play(m).unwrap();
+      agent.observe_move(m);
+    }
+    agent.select_move_with_report(&state).unwrap();
+    assert!(agent.tree.borrow().as_ref().unwrap().samples() > 100);
+
+    // The observed moves don't lead to the state, the tree is built from
+    // scratch.
+    agent.observe_move(1);
+    agent.observe_move(1);
+    state.play(2).unwrap();
+    state.play(2).unwrap();
+    agent.select_move_with_report(&state).unwrap();
+    assert_eq!(100, agent.tree.borrow().as_ref().unwrap().samples());
+  }
+
+  #[test]
+  fn solver() {
+    let game = Subtractor::new(10, 4);
+    let agent = MctsAgent::new(
+      EqualPolicy::new(), TerminalEvaluator::new(), Some(1000000), None);
+
+    let state = game.new_game();
+    let report = agent.select_move_with_report(&state).unwrap();
+    println!("{}", report);
+    assert_eq!(2, report.get_move());
+    assert!(report.to_string().contains("proven win"));
+    // The search stops once the root is proven.
+    assert!(agent.tree.borrow().as_ref().unwrap().samples() < 1000000);
+
+    let mut state = state;
+    state.play(2).unwrap();
+    state.play(1).unwrap();
+    state.play(3).unwrap();
+    let report = agent.select_move_with_report(&state).unwrap();
+    assert!(report.to_string().contains("proven loss"));
+  }
+
+  #[test]
+  fn play_subtractor_threads() {
+    let game = Subtractor::new(10, 4);
+    let mut agent = MctsAgent::new(
+      EqualPolicy::new(), TerminalEvaluator::new(), Some(4000), None);
+    agent.set_threads(4, || (EqualPolicy::new(), TerminalEvaluator::new()));
+
+    let state = game.new_game();
+    let report = agent.select_move_with_report(&state).unwrap();
+    println!("{}", report);
+    assert_eq!(2, report.get_move());
+  }
+
+  #[test]
+  fn more_threads_than_samples() {
+    let game = Subtractor::new(10, 4);
+    let mut agent = MctsAgent::new(
+      EqualPolicy::new(), TerminalEvaluator::new(), Some(4), None);
+    agent.set_threads(8, || (EqualPolicy::new(), TerminalEvaluator::new()));
+
+    let state = game.new_game();
+    let report = agent.select_move_with_report(&state).unwrap();
+    assert_eq!(3, report.moves().len());
+  }
+
+  #[test]
+  fn play_pig() {
+    // Rolling again wins unless the die shows 1.
+    let mut state = Pig::new(3).new_game();
+    for m in ["roll", "2"].iter() {
+      let m = state.parse_move(m).unwrap();
+      state.play(m).unwrap();
+    }
+    let agent = MctsAgent::new(
+      EqualPolicy::new(), TerminalEvaluator::new(), Some(2000), None);
+    let report = agent.select_move_with_report(&state).unwrap();
+    println!("{}", report);
+    assert_eq!("roll", report.get_move().to_string());
+
+    state.play(report.get_move()).unwrap();
+    assert!(agent.select_move(&state).is_err());
+  }
+
+  #[test]
+  fn play_nim_three_players() {
+    // The second player takes the last objects rather than leaving one for
+    // the third player.
+    let mut state = Nim::new(3, &[1, 2]).new_game();
+    state.play(state.parse_move("a1").unwrap()).unwrap();
+    let mut agent = MctsAgent::new(
+      EqualPolicy::new(), TerminalEvaluator::new(), Some(1000), None);
+    agent.set_selection(Selection::Ucb1, 1.4, None);
+    let report = agent.select_move_with_report(&state).unwrap();
+    println!("{}", report);
+    assert_eq!("b2", report.get_move().to_string());
+    assert!(report.to_string().starts_with("Player 2: b2, score 1.000"));
+  }
+
+  #[test]
+  fn multiplayer_single_sample() {
+    let state = Nim::new(3, &[1, 2]).new_game();
+    let agent = MctsAgent::new(
+      EqualPolicy::new(), TerminalEvaluator::new(), Some(1), None);
+    let report = agent.select_move_with_report(&state).unwrap();
+    assert_eq!(3, report.moves().len());
   }
 
 } // mod test
@@ -1,4 +1,5 @@
 mod agent;
+mod multiplayer;
 mod report;
 mod search;
 
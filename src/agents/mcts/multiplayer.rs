@@ -0,0 +1,203 @@
+//! UCT for the games with more than two players. Every node keeps the average
+//! scores of all the players, and the player to move selects the child by its
+//! own score. The nodes are never proven.
+
+use rand::{FromEntropy, Rng};
+use rand::rngs::SmallRng;
+use std::time::Instant;
+
+use crate::def::{Evaluator, Policy, State};
+use crate::spec::Selection;
+use super::report::MoveStats;
+use super::search::selection_value;
+
+pub struct MultiplayerSearch<S: State, P: Policy<S>, E: Evaluator<S>> {
+  policy: P,
+  evaluator: E,
+  state: S,
+  root: Node<S>,
+  selection: Selection,
+  exploration: f32,
+  fpu: Option<f32>,
+  rng: SmallRng,
+}
+
+impl<S: State, P: Policy<S>, E: Evaluator<S>> MultiplayerSearch<S, P, E> {
+  pub fn new(policy: P, evaluator: E, state: S) -> Self {
+    let players = state.num_players();
+    MultiplayerSearch {
+      policy,
+      evaluator,
+      state,
+      root: Node::new(None, 0.0, players),
+      selection: Selection::Puct,
+      exploration: 1.0,
+      fpu: None,
+      rng: SmallRng::from_entropy(),
+    }
+  }
+
+  /// Sets the formula for selecting the child to sample, its exploration
+  /// constant and the value of the unvisited children.
+  pub fn set_selection(
+    &mut self,
+    selection: Selection,
+    exploration: f32,
+    fpu: Option<f32>,
+  ) {
+    self.selection = selection;
+    self.exploration = exploration;
+    self.fpu = fpu;
+  }
+
+  /// Grows the tree by `max_samples` samples, or until the deadline.
+  pub fn search(&mut self, max_samples: u64, deadline: Instant) {
+    let players = self.state.num_players();
+    let mut root =
+      std::mem::replace(&mut self.root, Node::new(None, 0.0, players));
+    // Expand the root right away, so that there are moves to report even
+    // after a single sample.
+    if root.children.is_empty() {
+      let state = self.state.clone();
+      self.expand(&state, &mut root);
+    }
+    for _ in 0..max_samples {
+      self.sample(self.state.clone(), &mut root);
+      if Instant::now() > deadline {
+        break;
+      }
+    }
+    self.root = root;
+  }
+
+  /// Statistics of the moves from the root with the scores of the player to
+  /// move.
+  pub fn root_moves(&self) -> Vec<MoveStats<S::Move>> {
+    let player = self.state.player_index();
+    self
+      .root
+      .children
+      .iter()
+      .map(|child| MoveStats {
+        m: child.last_move.unwrap(),
+        samples: child.samples,
+        score: child.scores[player],
+        proven: None,
+      })
+      .collect()
+  }
+
+  /// The line starting with the given root move, followed by the most sampled
+  /// replies.
+  pub fn principal_line(&self, first: S::Move) -> Vec<S::Move> {
+    let mut line = vec![first];
+    let mut node =
+      self.root.children.iter().find(|c| c.last_move == Some(first));
+    while let Some(n) = node {
+      node = n.children.iter().max_by_key(|c| c.samples);
+      match node {
+        Some(child) if child.samples > 0 => {
+          line.push(child.last_move.unwrap())
+        }
+        _ => break,
+      }
+    }
+    line
+  }
+
+  // Samples the subtree of the node and returns the scores of the sample.
+  fn sample(&mut self, state: S, node: &mut Node<S>) -> Vec<f32> {
+    let scores = if state.is_terminal() {
+      state.payoffs().unwrap()
+    } else if node.samples == 0 {
+      self.evaluator.evaluate_players(&state)
+    } else {
+      if node.children.is_empty() {
+        self.expand(&state, node);
+      }
+      let index = if state.is_chance() {
+        self.sample_outcome(node)
+      } else {
+        self.select_child(node, state.player_index())
+      };
+      let mut state = state;
+      let child = &mut node.children[index];
+      state.play(child.last_move.unwrap()).unwrap();
+      self.sample(state, child)
+    };
+
+    let samples = node.samples as f32;
+    for (score, sample) in node.scores.iter_mut().zip(scores.iter()) {
+      *score = (*score * samples + sample) / (samples + 1.0);
+    }
+    node.samples += 1;
+    scores
+  }
+
+  fn expand(&self, state: &S, node: &mut Node<S>) {
+    let moves = if state.is_chance() {
+      state.chances()
+    } else {
+      self.policy.get_moves(state)
+    };
+    for (m, policy_score) in moves {
+      node
+        .children
+        .push(Node::new(Some(m), policy_score, node.scores.len()));
+    }
+  }
+
+  // Samples an outcome of a chance node according to its probability.
+  fn sample_outcome(&mut self, node: &Node<S>) -> usize {
+    let mut x: f32 = self.rng.gen_range(0.0, 1.0);
+    for (i, child) in node.children.iter().enumerate() {
+      x -= child.policy_score;
+      if x < 0.0 {
+        return i;
+      }
+    }
+    node.children.len() - 1
+  }
+
+  fn select_child(&self, node: &Node<S>, player: usize) -> usize {
+    let mut best = 0;
+    let mut best_value = f32::NEG_INFINITY;
+    for (i, child) in node.children.iter().enumerate() {
+      let value = selection_value(
+        self.selection,
+        self.exploration,
+        self.fpu,
+        child.scores[player],
+        child.samples,
+        child.policy_score,
+        node.samples,
+      );
+      if value > best_value {
+        best = i;
+        best_value = value;
+      }
+    }
+    best
+  }
+}
+
+struct Node<S: State> {
+  samples: u32,
+  // The average scores of all the players.
+  scores: Vec<f32>,
+  policy_score: f32,
+  last_move: Option<S::Move>,
+  children: Vec<Self>,
+}
+
+impl<S: State> Node<S> {
+  fn new(m: Option<S::Move>, policy_score: f32, players: usize) -> Self {
+    Node {
+      samples: 0,
+      scores: vec![0.0; players],
+      policy_score,
+      last_move: m,
+      children: Vec::new(),
+    }
+  }
+}
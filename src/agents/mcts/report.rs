@@ -2,42 +2,125 @@ use std::fmt;
 
 use crate::def::AgentReport;
 
+/// Search statistics of a single move from the root.
+#[derive(Clone, Copy, Debug)]
+pub struct MoveStats<M> {
+  pub m: M,
+  pub samples: u32,
+  // Average score of the samples for the first player, or for the player to
+  // move in the games with more than two players.
+  pub score: f32,
+  // The exact value of the move for the first player, if it is proven.
+  pub proven: Option<f32>,
+}
+
 #[derive(Clone)]
 pub struct MctsReport<M: fmt::Display + Clone + 'static> {
-  best_move: M,
-  // Total number of playouts.
-  samples: u64,
-  score: f32,
-  // Player, that makes the move.
-  player: bool,
+  // Statistics of the selected move.
+  best: MoveStats<M>,
+  // Statistics of all the moves from the root, the most sampled first.
+  moves: Vec<MoveStats<M>>,
+  // Principal line: the selected move, followed by the most sampled replies.
+  pv: Vec<M>,
+  // Index of the player, that makes the move.
+  player: usize,
 }
 
+// How many moves from the root are listed by `Display`.
+const MAX_DISPLAYED_MOVES: usize = 10;
+
 impl<M: fmt::Display + Clone + 'static> MctsReport<M> {
-  pub fn new(best_move: M, samples: u64, score: f32, player: bool) -> Self {
+  /// Creates a report for `moves[best]`. `pv` should start with this move.
+  pub fn new(
+    mut moves: Vec<MoveStats<M>>,
+    best: usize,
+    pv: Vec<M>,
+    player: usize,
+  ) -> Self {
+    let best = moves[best].clone();
+    moves.sort_by_key(|stats| std::cmp::Reverse(stats.samples));
     MctsReport {
-      best_move,
-      samples,
-      score,
-      player
+      best,
+      moves,
+      pv,
+      player,
+    }
+  }
+
+  /// Returns the index of the best move: a proven win if there is one,
+  /// otherwise the most sampled move that isn't a proven loss.
+  pub fn select_best(moves: &[MoveStats<M>], player: bool) -> usize {
+    assert!(!moves.is_empty());
+    let rank = |stats: &MoveStats<M>| {
+      let class = match stats.proven {
+        Some(value) if is_win(value, player) => 2,
+        Some(value) if is_win(-value, player) => 0,
+        _ => 1,
+      };
+      (class, stats.samples)
+    };
+    let mut best = 0;
+    for (i, stats) in moves.iter().enumerate() {
+      if rank(stats) > rank(&moves[best]) {
+        best = i;
+      }
     }
+    best
   }
-} 
+
+  /// Statistics of all the moves from the root, the most sampled first.
+  pub fn moves(&self) -> &[MoveStats<M>] {
+    &self.moves
+  }
+
+  pub fn pv(&self) -> &[M] {
+    &self.pv
+  }
+
+  /// Total number of samples of the root moves.
+  pub fn samples(&self) -> u64 {
+    self.moves.iter().map(|stats| stats.samples as u64).sum()
+  }
+}
 
 impl<M: fmt::Display + Clone + 'static> fmt::Display for MctsReport<M> {
   fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-    writeln!(
-      f,
-      "Player {}: {}, score {:.3}, playouts {}",
-      if self.player { 1 } else { 2 },
-      self.best_move,
-      self.score,
-      self.samples
-    )
+    write!(f, "Player {}:", self.player + 1)?;
+    for m in self.pv.iter() {
+      write!(f, " {}", m)?;
+    }
+    write!(f, ", score {:.3}, playouts {}", self.best.score, self.samples())?;
+    let player = self.player == 0;
+    match self.best.proven {
+      Some(value) if is_win(value, player) => writeln!(f, ", proven win")?,
+      Some(value) if is_win(-value, player) => writeln!(f, ", proven loss")?,
+      Some(value) => writeln!(f, ", proven score {:.3}", value)?,
+      None => writeln!(f)?,
+    }
+
+    for stats in self.moves.iter().take(MAX_DISPLAYED_MOVES) {
+      writeln!(
+        f,
+        "  {}: {} samples, score {:.3}",
+        stats.m, stats.samples, stats.score
+      )?;
+    }
+    if self.moves.len() > MAX_DISPLAYED_MOVES {
+      writeln!(f, "  ... {} more", self.moves.len() - MAX_DISPLAYED_MOVES)?;
+    }
+
+    Ok(())
   }
 }
 
+/// Whether the value for the first player is a win for the given player,
+/// given that the payoffs are in [-1, 1].
+pub fn is_win(value: f32, player: bool) -> bool {
+  (if player { value } else { -value }) >= 1.0
+}
+
 impl<M: fmt::Display + Clone + 'static> AgentReport<M> for MctsReport<M> {
   fn get_move(&self) -> M {
-    self.best_move.clone()
+    self.best.m.clone()
   }
 }
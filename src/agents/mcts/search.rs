@@ -1,60 +1,189 @@
+use rand::{FromEntropy, Rng};
+use rand::rngs::SmallRng;
+use std::cell::RefCell;
 use std::time::Instant;
 
 use crate::def::{Evaluator, Policy, State};
-use super::report::MctsReport;
+use crate::spec::Selection;
+use super::report::{is_win, MoveStats};
+
+// How many plies below the root of the previous tree `Tree::reroot` looks
+// for the new root: the agent's own move and the opponent's reply.
+const MAX_REROOT_DEPTH: u32 = 2;
+
+/// MCTS tree together with the state in its root. It can be kept between the
+/// searches to reuse the statistics gathered for the earlier moves.
+pub struct Tree<S: State> {
+  state: S,
+  root: Node<S>,
+}
+
+impl<S: State> Tree<S> {
+  pub fn new(state: S) -> Self {
+    Tree {
+      state,
+      root: Node::new(None, 0.0),
+    }
+  }
+
+  /// Total number of samples in the tree.
+  #[cfg(test)]
+  pub fn samples(&self) -> u32 {
+    self.root.samples
+  }
+
+  /// Statistics of the moves from the root.
+  pub fn root_moves(&self) -> Vec<MoveStats<S::Move>> {
+    self
+      .root
+      .children
+      .iter()
+      .map(|child| MoveStats {
+        m: child.last_move.unwrap(),
+        samples: child.samples,
+        score: child.score,
+        proven: child.proven,
+      })
+      .collect()
+  }
+
+  /// The line starting with the given root move, followed by the most sampled
+  /// replies.
+  pub fn principal_line(&self, first: S::Move) -> Vec<S::Move> {
+    let mut line = vec![first];
+    let mut node = self.root.children.iter().find(|c| c.last_move == Some(first));
+    while let Some(n) = node {
+      node = n.children.iter().max_by_key(|c| c.samples);
+      match node {
+        Some(child) if child.samples > 0 => line.push(child.last_move.unwrap()),
+        _ => break,
+      }
+    }
+    line
+  }
+
+  /// Returns the subtree reached by playing `moves` from the root, which
+  /// should lead to `state`. Returns `None` if the subtree hasn't been
+  /// expanded, or if the game implements `State::hash()` and the hash of the
+  /// reached position doesn't match `state`.
+  pub fn advance(self, moves: &[S::Move], state: &S) -> Option<Self> {
+    let Tree { state: mut tree_state, mut root } = self;
+    for &m in moves {
+      root = root.children.into_iter().find(|c| c.last_move == Some(m))?;
+      tree_state.play(m).ok()?;
+    }
+    if tree_state.hash() != state.hash() {
+      return None;
+    }
+    Some(Tree { state: tree_state, root })
+  }
+
+  /// Returns the subtree with the given state in its root, when the moves
+  /// leading to it are unknown. The positions are compared by `State::hash()`,
+  /// so `None` is returned for the games that don't implement it, as well as
+  /// for the positions not found in the tree.
+  pub fn reroot(self, state: &S) -> Option<Self> {
+    let hash = state.hash()?;
+    Self::find(self.state, self.root, hash, MAX_REROOT_DEPTH)
+  }
+
+  fn find(state: S, node: Node<S>, hash: u64, depth: u32) -> Option<Self> {
+    if state.hash() == Some(hash) {
+      return Some(Tree { state, root: node });
+    }
+    if depth == 0 {
+      return None;
+    }
+    for child in node.children.into_iter() {
+      let mut child_state = state.clone();
+      child_state.play(child.last_move.unwrap()).unwrap();
+      if let Some(tree) = Self::find(child_state, child, hash, depth - 1) {
+        return Some(tree);
+      }
+    }
+    None
+  }
+}
 
 pub struct MctsSearch<S: State, P: Policy<S>, E: Evaluator<S>> {
   policy: P,
   evaluator: E,
-  root_state: S,
+  tree: Tree<S>,
+  selection: Selection,
+  exploration: f32,
+  fpu: Option<f32>,
+  // Samples the outcomes of chance nodes.
+  rng: RefCell<SmallRng>,
 }
 
 impl<S: State, P: Policy<S>, E: Evaluator<S>> MctsSearch<S, P, E> {
-  pub fn new(policy: P, evaluator: E, state: S) -> Self {
+  /// Creates a search that continues to grow an existing tree.
+  pub fn with_tree(policy: P, evaluator: E, tree: Tree<S>) -> Self {
     MctsSearch {
       policy,
       evaluator,
-      root_state: state,
+      tree,
+      selection: Selection::Puct,
+      exploration: 1.0,
+      fpu: None,
+      rng: RefCell::new(SmallRng::from_entropy()),
     }
   }
 
+  /// Sets the formula for selecting the child to sample, its exploration
+  /// constant and the value of the unvisited children.
+  pub fn set_selection(
+    &mut self,
+    selection: Selection,
+    exploration: f32,
+    fpu: Option<f32>,
+  ) {
+    self.selection = selection;
+    self.exploration = exploration;
+    self.fpu = fpu;
+  }
+
+  pub fn into_tree(self) -> Tree<S> {
+    self.tree
+  }
+
+  /// Grows the tree by `max_samples` samples, or until the deadline.
   pub fn search(
-    &self,
+    &mut self,
     max_samples: u64,
     deadline: Instant,
-  ) -> Result<MctsReport<S::Move>, &'static str> {
-    if self.root_state.is_terminal() {
+  ) -> Result<(), &'static str> {
+    if self.tree.state.is_terminal() {
       return Err("the state is terminal");
     }
 
-    let mut root_node = Node::new(None, 0.0);
+    let mut root_node =
+      std::mem::replace(&mut self.tree.root, Node::new(None, 0.0));
 
     for _ in 0..max_samples {
-      self.sample(self.root_state.clone(), &mut root_node);
-      if Instant::now() > deadline { break }
+      self.sample(self.tree.state.clone(), &mut root_node);
+      if root_node.proven.is_some() || Instant::now() > deadline { break }
     }
 
-    let root_node = root_node;
+    self.tree.root = root_node;
+    assert!(!self.tree.root.children.is_empty());
+    Ok(())
+  }
 
-    assert!(!root_node.children.is_empty());
-    let mut best_child: Option<&Node<S>> = None;
-    for child in root_node.children.iter() {
-      if best_child.is_none() || child.samples > best_child.unwrap().samples {
-        best_child = Some(child);
-      }
+  fn sample(&self, state: S, node: &mut Node<S>) -> f32 {
+    if let Some(value) = node.proven {
+      node.samples += 1;
+      return value;
     }
 
-    let best_child = best_child.unwrap();
-
-    Ok(MctsReport::new(
-      best_child.last_move.unwrap(),
-      best_child.samples as u64,
-      best_child.score,
-      self.root_state.player(),
-    ))
-  }
+    if state.is_terminal() {
+      let payoff = state.payoff().unwrap();
+      node.proven = Some(payoff);
+      node.score = payoff;
+      node.samples += 1;
+      return payoff;
+    }
 
-  fn sample(&self, state: S, node: &mut Node<S>) -> f32 {
     if node.samples == 0 {
       let score = self.evaluator.evaluate(&state);
       node.score = score;
@@ -62,10 +191,8 @@ impl<S: State, P: Policy<S>, E: Evaluator<S>> MctsSearch<S, P, E> {
       return score;
     }
 
-    if state.is_terminal() {
-      node.score = state.payoff().unwrap();
-      node.samples += 1;
-      return node.score;
+    if state.is_chance() {
+      return self.sample_chance(state, node);
     }
 
     if node.children.is_empty() {
@@ -75,10 +202,10 @@ impl<S: State, P: Policy<S>, E: Evaluator<S>> MctsSearch<S, P, E> {
     let player = state.player();
     let mut best_child = None;
     let mut best_value = 0.0;
-    let numerator = (node.samples as f32).sqrt();
+    let parent_samples = node.samples;
 
     for child in node.children.iter_mut() {
-      let value = (if player {child.score} else {-child.score}) + child.policy_score * numerator / (child.samples + 1) as f32;
+      let value = self.child_value(child, player, parent_samples);
       if best_child.is_none() || value > best_value {
         best_child = Some(child);
         best_value = value;
@@ -92,14 +219,98 @@ impl<S: State, P: Policy<S>, E: Evaluator<S>> MctsSearch<S, P, E> {
     
     node.score = (node.score * node.samples as f32 + child_score) / (node.samples + 1) as f32;
     node.samples += 1;
+    node.update_proof(player);
+    if let Some(value) = node.proven {
+      node.score = value;
+    }
 
     return child_score;
   }
+
+  // Samples an outcome of the chance node according to its probability. The
+  // score of the node is the average of the samples, and it is never proven.
+  fn sample_chance(&self, state: S, node: &mut Node<S>) -> f32 {
+    if node.children.is_empty() {
+      for (m, probability) in state.chances() {
+        node.children.push(Node::new(Some(m), probability));
+      }
+    }
+
+    let mut x: f32 = self.rng.borrow_mut().gen_range(0.0, 1.0);
+    let mut index = node.children.len() - 1;
+    for (i, child) in node.children.iter().enumerate() {
+      x -= child.policy_score;
+      if x < 0.0 {
+        index = i;
+        break;
+      }
+    }
+
+    let mut state = state;
+    let child = &mut node.children[index];
+    state.play(child.last_move.unwrap()).unwrap();
+    let child_score = self.sample(state, child);
+
+    node.score = (node.score * node.samples as f32 + child_score)
+      / (node.samples + 1) as f32;
+    node.samples += 1;
+    child_score
+  }
+
+  // The value of the child in the selection formula for the player, who makes
+  // the move.
+  fn child_value(&self, child: &Node<S>, player: bool, parent_samples: u32) -> f32 {
+    let q = if player { child.score } else { -child.score };
+    if let Some(value) = child.proven {
+      // Proven losses are only sampled once all the moves are proven, and
+      // then the node itself is proven.
+      if is_win(-value, player) {
+        return f32::NEG_INFINITY;
+      }
+    }
+    selection_value(
+      self.selection,
+      self.exploration,
+      self.fpu,
+      q,
+      child.samples,
+      child.policy_score,
+      parent_samples,
+    )
+  }
+}
+
+/// The value of a child with `samples` samples, average score `q` for the
+/// player to move and policy weight `policy_score` in the selection formula.
+pub fn selection_value(
+  selection: Selection,
+  exploration: f32,
+  fpu: Option<f32>,
+  q: f32,
+  samples: u32,
+  policy_score: f32,
+  parent_samples: u32,
+) -> f32 {
+  match selection {
+    Selection::Puct => {
+      let q = if samples == 0 { fpu.unwrap_or(0.0) } else { q };
+      q + exploration * policy_score * (parent_samples as f32).sqrt()
+        / (samples + 1) as f32
+    }
+    Selection::Ucb1 => {
+      if samples == 0 {
+        return fpu.unwrap_or(f32::INFINITY);
+      }
+      q + exploration * ((parent_samples as f32).ln() / samples as f32).sqrt()
+    }
+  }
 }
 
 struct Node<S: State> {
   samples: u32,
   score: f32,
+  // The exact minimax value for the first player, once it is proven.
+  proven: Option<f32>,
   policy_score: f32,
   last_move: Option<S::Move>,
   children: Vec<Self>,
@@ -110,6 +321,7 @@ impl<S: State> Node<S> {
     Node {
       samples: 0,
       score: 0.0,
+      proven: None,
       policy_score,
       last_move: m,
       children: Vec::new()
@@ -124,6 +336,34 @@ impl<S: State> Node<S> {
     }            
   }
 
+  // MCTS-Solver: the node is proven if one of the moves is a proven win for
+  // the player, or if all the moves are proven.
+  fn update_proof(&mut self, player: bool) {
+    let mut best: Option<f32> = None;
+    let mut all_proven = true;
+    for child in self.children.iter() {
+      match child.proven {
+        Some(value) if is_win(value, player) => {
+          self.proven = Some(value);
+          return;
+        }
+        Some(value) => {
+          let better = match best {
+            Some(b) => if player { value > b } else { value < b },
+            None => true,
+          };
+          if better {
+            best = Some(value);
+          }
+        }
+        None => all_proven = false,
+      }
+    }
+    if all_proven {
+      self.proven = best;
+    }
+  }
+
   fn debug(&self, f: &mut std::fmt::Formatter, indent: usize) -> std::fmt::Result {
     if self.samples < 2 && indent > 4 { return Ok(()); }
     write!(f, "{}", std::iter::repeat(" ").take(indent).collect::<String>())?;
@@ -132,6 +372,9 @@ impl<S: State> Node<S> {
       None => write!(f, "()")?,
     }
     write!(f, " {} {}", self.samples, self.score)?;
+    if let Some(value) = self.proven {
+      write!(f, " proven {}", value)?;
+    }
     writeln!(f)?;
     for c in self.children.iter() {
       c.debug(f, indent + 2)?;
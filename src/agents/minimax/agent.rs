@@ -1,24 +1,39 @@
 use rand;
 use std::marker::PhantomData;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
 use std::time::{Duration, Instant};
 
 use crate::def::{Agent, AgentReport, Evaluator, State};
-use crate::spec::AgentSpec;
+use crate::spec::{AgentSpec, MoveOrdering, Multiplayer};
+use super::multiplayer::MultiplayerSearch;
 use super::search::{MinimaxSearch, SearchResult};
 use super::report::MinimaxReport;
+use super::table::TranspositionTable;
 
 pub struct MinimaxAgent<S: State, E: Evaluator<S>> {
   _state: PhantomData<S>,
   evaluator: E,
   max_depth: u32,
   time_limit: Option<Duration>,
+  table_size: usize,
+  ordering: MoveOrdering,
+  multiplayer: Multiplayer,
+  threads: usize,
+  // Creates evaluators for the helper threads.
+  new_evaluator: Option<Box<Fn() -> E + Send + Sync>>,
 }
 
 impl<S: State, E: Evaluator<S>> MinimaxAgent<S, E> {
+  /// Creates an agent. `table_size` is the number of entries in the
+  /// transposition table, 0 to search without the table.
   pub fn new(
     evaluator: E,
     max_depth: u32,
     time_limit: Option<Duration>,
+    table_size: usize,
+    ordering: MoveOrdering,
   ) -> Self {
     assert!(max_depth > 0);
     MinimaxAgent {
@@ -26,6 +41,90 @@ impl<S: State, E: Evaluator<S>> MinimaxAgent<S, E> {
       evaluator,
       max_depth,
       time_limit,
+      table_size,
+      ordering,
+      multiplayer: Multiplayer::MaxN,
+      threads: 1,
+      new_evaluator: None,
+    }
+  }
+
+  /// Sets the search for the games with more than two players. Such games are
+  /// searched in a single thread without the transposition table.
+  pub fn set_multiplayer(&mut self, multiplayer: Multiplayer) {
+    self.multiplayer = multiplayer;
+  }
+
+  /// Makes the agent search in `threads` threads (Lazy SMP). Every helper
+  /// thread runs its own iterative deepening with an evaluator created by
+  /// `new_evaluator`. The threads only share the results through the
+  /// transposition table, so no helpers are started if `table_size` is 0.
+  pub fn set_threads<F>(&mut self, threads: usize, new_evaluator: F)
+  where
+    F: 'static + Fn() -> E + Send + Sync,
+  {
+    assert!(threads > 0);
+    self.threads = threads;
+    self.new_evaluator = Some(Box::new(new_evaluator));
+  }
+}
+
+impl<S: State, E: Evaluator<S>> MinimaxAgent<S, E> {
+  // Iterative deepening with max-n or paranoid search. Stops early once the
+  // search reaches the end of the game in all the lines.
+  fn select_multiplayer(
+    &self,
+    state: &S,
+    start_time: Instant,
+    deadline: Option<Instant>,
+  ) -> MinimaxReport<S::Move> {
+    let mut search = MultiplayerSearch::new(
+      &self.evaluator, 1, 0.999, deadline, self.multiplayer);
+    let mut report = MinimaxReport {
+      score: 0.0,
+      pv: vec![state.get_random_move(&mut rand::thread_rng()).unwrap()],
+      samples: 0,
+      duration: Duration::new(0, 0),
+      player: state.player_index(),
+      depth: 0,
+    };
+    for depth in 1..(self.max_depth + 1) {
+      search.set_depth(depth);
+      match search.full_search(state) {
+        Some((score, pv)) => {
+          report.score = score;
+          report.pv = pv;
+          report.samples = search.leaves;
+          report.depth = depth;
+        }
+        None => break,
+      }
+      if !search.depth_cutoff {
+        break;
+      }
+    }
+    report.duration = Instant::now() - start_time;
+    report
+  }
+}
+
+// Iterative deepening in a helper thread. The results of the search are
+// ignored, apart from the entries it adds to the transposition table. Odd
+// helpers search one ply deeper than the main thread to diversify the search.
+fn run_helper<S: State, E: Evaluator<S>>(
+  mut minimax: MinimaxSearch<S, E>,
+  index: usize,
+  state: &S,
+  max_depth: u32,
+) {
+  for depth in (1 + index as u32 % 2)..(max_depth + 1) {
+    minimax.set_depth(depth);
+    match minimax.full_search(state) {
+      SearchResult::Found(_, mut pv) => {
+        pv.reverse();
+        minimax.set_pv(&pv);
+      }
+      _ => break,
     }
   }
 }
@@ -38,6 +137,9 @@ impl<S: State, E: Evaluator<S>> Agent<S> for MinimaxAgent<S, E> {
     if state.is_terminal() {
       return Err("Terminal state");
     }
+    if state.is_chance() {
+      return Err("The next move is made by chance");
+    }
 
     let start_time = Instant::now();
     let deadline = match self.time_limit {
@@ -45,31 +147,68 @@ impl<S: State, E: Evaluator<S>> Agent<S> for MinimaxAgent<S, E> {
       None => None,
     };
 
-    let mut minimax = MinimaxSearch::new(&self.evaluator, 1, 0.999, deadline);
+    if state.num_players() > 2 {
+      let report = self.select_multiplayer(state, start_time, deadline);
+      return Ok(Box::new(report));
+    }
+
+    let table = if self.table_size > 0 {
+      Some(Arc::new(TranspositionTable::new(self.table_size)))
+    } else {
+      None
+    };
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let mut minimax =
+      MinimaxSearch::new(&self.evaluator, 1, 0.999, deadline, 0, self.ordering);
+    if let Some(ref table) = table {
+      minimax.set_table(table.clone());
+    }
     let mut report = MinimaxReport {
       score: 0.0,
       pv: vec![state.get_random_move(&mut rand::thread_rng()).unwrap()],
       samples: 0,
       duration: Duration::new(0, 0),
-      player: state.player(),
+      player: state.player_index(),
       depth: 0,
     };
 
-    for depth in 1..(self.max_depth + 1) {
-      minimax.set_depth(depth);
-      let result = minimax.full_search(state);
-      match result {
-        SearchResult::Deadline => break,
-        SearchResult::Found(score, mut pv) => {
-          pv.reverse();
-          report.score = score;
-          report.pv = pv;
-          report.samples = minimax.leaves;
-          report.depth = depth;
+    thread::scope(|scope| {
+      if let (Some(new_evaluator), Some(table)) = (&self.new_evaluator, &table)
+      {
+        let (max_depth, ordering) = (self.max_depth, self.ordering);
+        for index in 1..self.threads {
+          let table = table.clone();
+          let stop = stop.clone();
+          scope.spawn(move || {
+            let mut minimax = MinimaxSearch::new(
+              new_evaluator(), 1, 0.999, deadline, 0, ordering);
+            minimax.set_table(table);
+            minimax.set_stop(stop);
+            run_helper(minimax, index, state, max_depth);
+          });
         }
-        _ => unreachable!(),
       }
-    }
+
+      for depth in 1..(self.max_depth + 1) {
+        minimax.set_depth(depth);
+        let result = minimax.full_search(state);
+        match result {
+          SearchResult::Deadline => break,
+          SearchResult::Found(score, mut pv) => {
+            pv.reverse();
+            report.score = score;
+            report.pv = pv;
+            report.samples = minimax.leaves;
+            report.depth = depth;
+            minimax.set_pv(&report.pv);
+          }
+          _ => unreachable!(),
+        }
+      }
+
+      stop.store(true, Ordering::Relaxed);
+    });
 
     report.duration = Instant::now() - start_time;
 
@@ -83,6 +222,10 @@ impl<S: State, E: Evaluator<S>> Agent<S> for MinimaxAgent<S, E> {
       depth: self.max_depth,
       time_per_move: convert_duration(self.time_limit),
       evaluator: self.evaluator.spec(),
+      table_size: self.table_size,
+      ordering: self.ordering,
+      multiplayer: self.multiplayer,
+      threads: self.threads,
       name: String::new()
     }
   }
@@ -98,15 +241,23 @@ fn convert_duration(duration: Option<Duration>) -> f64 {
 #[cfg(test)]
 mod test {
 
+  use std::sync::atomic::AtomicUsize;
+
   use crate::def::{Agent, Game};
-  use crate::games::Subtractor;
+  use crate::games::{Hexapawn, Nim, Subtractor};
   use crate::evaluators::TerminalEvaluator;
   
   use super::*;
 
   #[test]
   fn subtractor() {
-    let agent = MinimaxAgent::new(TerminalEvaluator::new(), 10, None);
+    let agent = MinimaxAgent::new(
+      TerminalEvaluator::new(),
+      10,
+      None,
+      0,
+      MoveOrdering::Random,
+    );
     let game = Subtractor::new(10, 4);
     let mut state = game.new_game();
 
@@ -121,4 +272,127 @@ mod test {
     assert_eq!(3, report.get_move());
   }
 
+  #[test]
+  fn subtractor_table() {
+    let agent = MinimaxAgent::new(
+      TerminalEvaluator::new(),
+      12,
+      None,
+      1000,
+      MoveOrdering::Random,
+    );
+    let game = Subtractor::new(21, 4);
+    let mut state = game.new_game();
+
+    let report = agent.select_move(&state).unwrap();
+    assert_eq!(1, report.get_move());
+
+    state.play(1).unwrap();
+    state.play(2).unwrap();
+
+    let report = agent.select_move(&state).unwrap();
+    assert_eq!(2, report.get_move());
+  }
+
+  #[test]
+  fn hexapawn_ordered() {
+    let agent = MinimaxAgent::new(
+      TerminalEvaluator::new(),
+      10,
+      None,
+      1000,
+      MoveOrdering::Ordered,
+    );
+    let game = Hexapawn::new(3, 3);
+    let mut state = game.new_game();
+
+    // The second player wins in 3x3 Hexapawn.
+    let report = agent.select_move(&state).unwrap();
+    state.play(report.get_move()).unwrap();
+    let report = agent.select_move(&state).unwrap();
+    println!("{}", report);
+    assert!(report.to_string().contains("score -0.9"));
+  }
+
+  #[test]
+  fn hexapawn_threads() {
+    let mut agent = MinimaxAgent::new(
+      TerminalEvaluator::new(),
+      10,
+      None,
+      1000,
+      MoveOrdering::Ordered,
+    );
+    agent.set_threads(4, TerminalEvaluator::new);
+    let game = Hexapawn::new(3, 3);
+    let mut state = game.new_game();
+
+    let report = agent.select_move(&state).unwrap();
+    state.play(report.get_move()).unwrap();
+    let report = agent.select_move(&state).unwrap();
+    println!("{}", report);
+    assert!(report.to_string().contains("score -0.9"));
+  }
+
+  #[test]
+  fn subtractor_threads() {
+    let mut agent = MinimaxAgent::new(
+      TerminalEvaluator::new(),
+      12,
+      Some(Duration::from_secs(10)),
+      1000,
+      MoveOrdering::Random,
+    );
+    agent.set_threads(3, TerminalEvaluator::new);
+    let game = Subtractor::new(21, 4);
+    let state = game.new_game();
+
+    let report = agent.select_move(&state).unwrap();
+    assert_eq!(1, report.get_move());
+  }
+
+  #[test]
+  fn threads_without_table() {
+    static HELPERS: AtomicUsize = AtomicUsize::new(0);
+    let mut agent = MinimaxAgent::new(
+      TerminalEvaluator::new(),
+      12,
+      None,
+      0,
+      MoveOrdering::Random,
+    );
+    agent.set_threads(3, || {
+      HELPERS.fetch_add(1, Ordering::SeqCst);
+      TerminalEvaluator::new()
+    });
+    let state = Subtractor::new(21, 4).new_game();
+
+    let report = agent.select_move(&state).unwrap();
+    assert_eq!(1, report.get_move());
+    assert_eq!(0, HELPERS.load(Ordering::SeqCst));
+  }
+
+  #[test]
+  fn nim_three_players() {
+    let mut state = Nim::new(3, &[1, 2]).new_game();
+    state.play(state.parse_move("a1").unwrap()).unwrap();
+    for &multiplayer in [Multiplayer::MaxN, Multiplayer::Paranoid].iter() {
+      let mut agent = MinimaxAgent::new(
+        TerminalEvaluator::new(),
+        10,
+        None,
+        0,
+        MoveOrdering::Random,
+      );
+      agent.set_multiplayer(multiplayer);
+      let report = agent.select_move(&state).unwrap();
+      println!("{}", report);
+      assert_eq!("b2", report.get_move().to_string());
+      // The search stops once it reaches the end of the game.
+      assert!(report
+        .to_string()
+        .contains("Player 2: b2, score 0.999, depth 2"));
+    }
+  }
+
 }
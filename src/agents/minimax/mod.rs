@@ -1,6 +1,8 @@
 mod agent;
+mod multiplayer;
 mod report;
 mod search;
+mod table;
 
 pub use self::agent::MinimaxAgent;
 pub use self::report::MinimaxReport;
@@ -0,0 +1,277 @@
+//! Search for the games with more than two players. Max-n assumes, that every
+//! player maximises its own score. Paranoid search assumes, that all the other
+//! players minimise the score of the player at the root, which turns the game
+//! into a two-player one and allows alpha-beta pruning.
+
+use rand::FromEntropy;
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use std::f32;
+use std::marker::PhantomData;
+use std::time::Instant;
+
+use crate::def::{Evaluator, State};
+use crate::spec::Multiplayer;
+
+pub struct MultiplayerSearch<S: State, E: Evaluator<S>> {
+  _state: PhantomData<S>,
+  evaluator: E,
+  mode: Multiplayer,
+  deadline: Option<Instant>,
+  discount: f32,
+  max_depth: u32,
+
+  depth: u32,
+  // Index of the player to move at the root.
+  root_player: usize,
+  pub leaves: u64,
+  // Whether any leaf was cut off by the depth limit rather than being
+  // terminal, i.e. whether a deeper search may give a different result.
+  pub depth_cutoff: bool,
+  rng: SmallRng,
+}
+
+impl<S: State, E: Evaluator<S>> MultiplayerSearch<S, E> {
+  pub fn new(
+    evaluator: E,
+    depth: u32,
+    discount: f32,
+    deadline: Option<Instant>,
+    mode: Multiplayer,
+  ) -> Self {
+    assert!(discount <= 1.0);
+    MultiplayerSearch {
+      _state: PhantomData,
+      evaluator,
+      mode,
+      deadline,
+      discount,
+      max_depth: depth,
+
+      depth: 0,
+      root_player: 0,
+      leaves: 0,
+      depth_cutoff: false,
+      rng: SmallRng::from_entropy(),
+    }
+  }
+
+  pub fn set_depth(&mut self, depth: u32) {
+    assert!(depth > 0);
+    self.max_depth = depth;
+  }
+
+  /// Returns the score of the player to move and the principal variation,
+  /// starting from the root, or None if the deadline is exceeded.
+  pub fn full_search(&mut self, state: &S) -> Option<(f32, Vec<S::Move>)> {
+    self.depth = 0;
+    self.depth_cutoff = false;
+    self.root_player = state.player_index();
+    let (score, mut pv) = match self.mode {
+      Multiplayer::MaxN => {
+        let (scores, pv) = self.max_n(state)?;
+        (scores[self.root_player], pv)
+      }
+      Multiplayer::Paranoid => self.paranoid(state, f32::MIN, f32::MAX)?,
+    };
+    pv.reverse();
+    Some((score, pv))
+  }
+
+  fn deadline_exceeded(&self) -> bool {
+    self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+  }
+
+  // The discounted evaluation of a leaf for every player, if the state is a
+  // leaf.
+  fn evaluate_leaf(&mut self, state: &S) -> Option<Vec<f32>> {
+    if !state.is_terminal() && self.depth < self.max_depth {
+      return None;
+    }
+    if !state.is_terminal() {
+      self.depth_cutoff = true;
+    }
+    self.leaves += 1;
+    let discount = self.discount.powi(self.depth as i32);
+    let scores = self.evaluator.evaluate_players(state);
+    Some(scores.into_iter().map(|score| score * discount).collect())
+  }
+
+  fn shuffled_moves(&mut self, state: &S) -> Vec<S::Move> {
+    // Shuffle to choose randomly between equal moves.
+    let mut moves: Vec<S::Move> = state.iter_moves().collect();
+    moves.shuffle(&mut self.rng);
+    moves
+  }
+
+  // Returns the scores of all the players and the reversed principal
+  // variation.
+  fn max_n(&mut self, state: &S) -> Option<(Vec<f32>, Vec<S::Move>)> {
+    if self.deadline_exceeded() {
+      return None;
+    }
+    if let Some(scores) = self.evaluate_leaf(state) {
+      return Some((scores, Vec::new()));
+    }
+
+    let mut state_clone = state.clone();
+    self.depth += 1;
+    let mut best: Option<(Vec<f32>, Vec<S::Move>)> = None;
+    if state.is_chance() {
+      // The expected scores of the outcomes.
+      let mut scores = vec![0.0; state.num_players()];
+      for (m, probability) in state.chances() {
+        state_clone.play(m).unwrap();
+        let child = self.max_n(&state_clone);
+        state_clone.undo(m).unwrap();
+        for (score, child_score) in scores.iter_mut().zip(child?.0) {
+          *score += probability * child_score;
+        }
+      }
+      best = Some((scores, Vec::new()));
+    } else {
+      let player = state.player_index();
+      for m in self.shuffled_moves(state) {
+        state_clone.play(m).unwrap();
+        let child = self.max_n(&state_clone);
+        state_clone.undo(m).unwrap();
+        let (scores, mut pv) = child?;
+        if best.as_ref().is_none_or(|b| scores[player] > b.0[player]) {
+          pv.push(m);
+          best = Some((scores, pv));
+        }
+      }
+    }
+    self.depth -= 1;
+    best
+  }
+
+  // Alpha-beta search for the score of the root player. Returns the score and
+  // the reversed principal variation.
+  fn paranoid(
+    &mut self,
+    state: &S,
+    lo: f32,
+    hi: f32,
+  ) -> Option<(f32, Vec<S::Move>)> {
+    if self.deadline_exceeded() {
+      return None;
+    }
+    if let Some(scores) = self.evaluate_leaf(state) {
+      return Some((scores[self.root_player], Vec::new()));
+    }
+
+    let mut state_clone = state.clone();
+    self.depth += 1;
+    let result = if state.is_chance() {
+      // The outcomes are searched with the full window, as in expectimax.
+      let mut score = 0.0;
+      for (m, probability) in state.chances() {
+        state_clone.play(m).unwrap();
+        let child = self.paranoid(&state_clone, f32::MIN, f32::MAX);
+        state_clone.undo(m).unwrap();
+        score += probability * child?.0;
+      }
+      (score, Vec::new())
+    } else {
+      let maximize = state.player_index() == self.root_player;
+      let (mut lo, mut hi) = (lo, hi);
+      let mut best = (
+        if maximize { f32::NEG_INFINITY } else { f32::INFINITY },
+        Vec::new(),
+      );
+      for m in self.shuffled_moves(state) {
+        state_clone.play(m).unwrap();
+        let child = self.paranoid(&state_clone, lo, hi);
+        state_clone.undo(m).unwrap();
+        let (score, mut pv) = child?;
+        if best.1.is_empty()
+          || (maximize && score > best.0)
+          || (!maximize && score < best.0)
+        {
+          pv.push(m);
+          best = (score, pv);
+        }
+        if maximize {
+          lo = lo.max(score);
+        } else {
+          hi = hi.min(score);
+        }
+        if lo >= hi {
+          break;
+        }
+      }
+      best
+    };
+    self.depth -= 1;
+    Some(result)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::def::Game;
+  use crate::evaluators::TerminalEvaluator;
+  use crate::games::Nim;
+
+  use super::*;
+
+  fn search(
+    state: &<Nim as Game>::State,
+    mode: Multiplayer,
+  ) -> (f32, Vec<String>) {
+    let evaluator = TerminalEvaluator::new();
+    let mut search = MultiplayerSearch::new(&evaluator, 20, 1.0, None, mode);
+    let (score, pv) = search.full_search(state).unwrap();
+    assert!(!search.depth_cutoff);
+    (score, pv.iter().map(|m| m.to_string()).collect())
+  }
+
+  #[test]
+  fn immediate_win() {
+    let state = Nim::new(3, &[3]).new_game();
+    for &mode in [Multiplayer::MaxN, Multiplayer::Paranoid].iter() {
+      let (score, pv) = search(&state, mode);
+      assert_eq!(1.0, score);
+      assert_eq!(vec!["a3"], pv);
+    }
+  }
+
+  #[test]
+  fn own_win() {
+    // The second player takes the last objects rather than leaving one for the
+    // third player.
+    let mut state = Nim::new(3, &[1, 2]).new_game();
+    state.play(state.parse_move("a1").unwrap()).unwrap();
+    for &mode in [Multiplayer::MaxN, Multiplayer::Paranoid].iter() {
+      assert_eq!((1.0, vec!["b2".to_string()]), search(&state, mode));
+    }
+  }
+
+  #[test]
+  fn paranoid_is_pessimistic() {
+    // The other two players can cooperate to take the last object. Under
+    // max-n an indifferent player may let the first player win.
+    for heaps in [vec![1, 4], vec![2, 2, 2], vec![3, 3, 3]].iter() {
+      let state = Nim::new(3, heaps).new_game();
+      let (paranoid, _) = search(&state, Multiplayer::Paranoid);
+      assert_eq!(-1.0, paranoid, "{:?}", heaps);
+      let (max_n, pv) = search(&state, Multiplayer::MaxN);
+      assert!(max_n >= paranoid, "{:?}", heaps);
+      assert!(!pv.is_empty());
+    }
+  }
+
+  #[test]
+  fn depth_limit() {
+    let state = Nim::new(4, &[3, 4, 5]).new_game();
+    let evaluator = TerminalEvaluator::new();
+    let mut search =
+      MultiplayerSearch::new(&evaluator, 2, 1.0, None, Multiplayer::MaxN);
+    let (score, pv) = search.full_search(&state).unwrap();
+    assert_eq!(0.0, score);
+    assert_eq!(2, pv.len());
+    assert!(search.depth_cutoff);
+  }
+
+}  // mod test
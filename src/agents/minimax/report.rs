@@ -5,18 +5,21 @@ use crate::def::AgentReport;
 
 #[derive(Clone)]
 pub struct MinimaxReport<M: fmt::Display + 'static> {
+  // Score for the first player, or for the player to move in the games with
+  // more than two players.
   pub score: f32,
   // Principle variation
   pub pv: Vec<M>,
   pub samples: u64,
   pub duration: Duration,
-  pub player: bool,
+  // Index of the player to move.
+  pub player: usize,
   pub depth: u32
 }
 
 impl<M: fmt::Display + 'static> fmt::Display for MinimaxReport<M> {
   fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-    write!(f, "Player {}:", if self.player { 1 } else { 2 })?;
+    write!(f, "Player {}:", self.player + 1)?;
 
     for m in self.pv.iter() {
       write!(f, " {}", m)?;
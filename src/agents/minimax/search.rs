@@ -2,18 +2,26 @@ use rand::FromEntropy;
 use rand::rngs::SmallRng;
 use rand::seq::SliceRandom;
 use std;
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::f32;
 use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Instant;
 use std::marker::PhantomData;
 
 use crate::def::{Evaluator, State};
+use crate::spec::MoveOrdering;
 use super::MinimaxReport;
+use super::table::{Bound, Entry, TranspositionTable};
 
 pub struct MinimaxSearch<S: State, E: Evaluator<S>> {
   _state: PhantomData<S>,
   evaluator: E,
   deadline: Option<Instant>,
+  // If set by another thread, the search is stopped as if by the deadline.
+  stop: Option<Arc<AtomicBool>>,
   // Discount per depth.
   discount: Vec<f32>,
   max_depth: u32,
@@ -21,6 +29,17 @@ pub struct MinimaxSearch<S: State, E: Evaluator<S>> {
   depth: u32,
   pub leaves: u64,
   rng: SmallRng,
+  table: Option<Arc<TranspositionTable<S::Move>>>,
+
+  ordering: MoveOrdering,
+  // Principal variation from the previous iteration, starting from the root.
+  pv: Vec<S::Move>,
+  // Whether the current node lies on `pv`.
+  on_pv: bool,
+  // Two most recent moves per depth, that caused a cutoff.
+  killers: Vec<[Option<S::Move>; 2]>,
+  // Cutoff counts weighted by depth for every (player, move) pair.
+  history: HashMap<(bool, S::Move), u64>,
 }
 
 #[derive(Debug)]
@@ -32,11 +51,17 @@ pub enum SearchResult<M: 'static + Copy + fmt::Debug> {
 }
 
 impl<S: State, E: Evaluator<S>> MinimaxSearch<S, E> {
+  /// Creates a search. If `table_size` is positive, the search will use a
+  /// transposition table with this number of entries, that is kept between
+  /// the calls to `full_search()`, as are killer moves and history for
+  /// `MoveOrdering::Ordered`.
   pub fn new(
     evaluator: E,
     depth: u32,
     discount: f32,
     deadline: Option<Instant>,
+    table_size: usize,
+    ordering: MoveOrdering,
   ) -> Self {
     assert!(discount <= 1.0);
     let discount_vec = (0..(depth + 1))
@@ -46,12 +71,24 @@ impl<S: State, E: Evaluator<S>> MinimaxSearch<S, E> {
       _state: PhantomData{},
       evaluator,
       deadline,
+      stop: None,
       discount: discount_vec,
       max_depth: depth,
 
       depth: 0,
       leaves: 0,
       rng: SmallRng::from_entropy(),
+      table: if table_size > 0 {
+        Some(Arc::new(TranspositionTable::new(table_size)))
+      } else {
+        None
+      },
+
+      ordering,
+      pv: Vec::new(),
+      on_pv: false,
+      killers: vec![[None; 2]; depth as usize],
+      history: HashMap::new(),
     }
   }
 
@@ -63,10 +100,29 @@ impl<S: State, E: Evaluator<S>> MinimaxSearch<S, E> {
       .collect();
     self.max_depth = depth;
     self.discount = discount_vec;
+    self.killers.resize(depth as usize, [None; 2]);
+  }
+
+  /// Replaces the transposition table with a table, that may be shared with
+  /// searches in other threads.
+  pub fn set_table(&mut self, table: Arc<TranspositionTable<S::Move>>) {
+    self.table = Some(table);
+  }
+
+  /// Sets a flag, that stops the search when raised.
+  pub fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+    self.stop = Some(stop);
+  }
+
+  /// Sets the principal variation, starting from the root, that will be
+  /// searched first with `MoveOrdering::Ordered`.
+  pub fn set_pv(&mut self, pv: &[S::Move]) {
+    self.pv = pv.to_vec();
   }
 
   pub fn full_search(&mut self, state: &S) -> SearchResult<S::Move> {
     self.depth = 0;
+    self.on_pv = true;
     self.search(state, f32::MIN, f32::MAX)
   }
 
@@ -75,6 +131,11 @@ impl<S: State, E: Evaluator<S>> MinimaxSearch<S, E> {
     if self.deadline.is_some() && Instant::now() >= self.deadline.unwrap() {
       return SearchResult::Deadline;
     }
+    if let Some(ref stop) = self.stop {
+      if stop.load(Ordering::Relaxed) {
+        return SearchResult::Deadline;
+      }
+    }
 
     if state.is_terminal() || self.depth == self.max_depth {
       self.leaves += 1;
@@ -89,6 +150,35 @@ impl<S: State, E: Evaluator<S>> MinimaxSearch<S, E> {
       return SearchResult::Found(evaluation, Vec::new());
     }
 
+    if state.is_chance() {
+      return self.search_chance(state, lo, hi);
+    }
+
+    let remaining_depth = self.max_depth - self.depth;
+    let hash = match self.table {
+      Some(_) => state.hash(),
+      None => None,
+    };
+    let mut table_move = None;
+
+    if let Some(entry) = hash.and_then(|h| self.table.as_ref()?.get(h)) {
+      table_move = entry.best_move;
+      // Never cut off at the root, since we need the best move.
+      if self.depth > 0 && entry.depth >= remaining_depth {
+        let score = entry.score * self.discount[self.depth as usize];
+        match entry.bound {
+          Bound::Exact if score <= lo => return SearchResult::Lower,
+          Bound::Exact if score >= hi => return SearchResult::Higher,
+          Bound::Exact => {
+            return SearchResult::Found(score, table_move.into_iter().collect())
+          }
+          Bound::AtLeast if score >= hi => return SearchResult::Higher,
+          Bound::AtMost if score <= lo => return SearchResult::Lower,
+          _ => (),
+        }
+      }
+    }
+
     let player = state.player();
     let mut lo = lo;
     let mut hi = hi;
@@ -98,12 +188,20 @@ impl<S: State, E: Evaluator<S>> MinimaxSearch<S, E> {
     } else {
       SearchResult::Higher
     };
+    let mut best_move = None;
 
     self.depth += 1;
 
+    let pv_move = if self.on_pv {
+      self.pv.get(self.depth as usize - 1).cloned()
+    } else {
+      None
+    };
     let mut moves: Vec<S::Move> = state.iter_moves().collect();
-    moves.shuffle(&mut self.rng);
+    self.order_moves(&mut moves, player, pv_move, table_move);
+
     for m in moves {
+      self.on_pv = pv_move == Some(m);
       state_clone.play(m).unwrap();
       let child_result = self.search(&state_clone, lo, hi);
       match child_result {
@@ -114,18 +212,23 @@ impl<S: State, E: Evaluator<S>> MinimaxSearch<S, E> {
         SearchResult::Lower => {
           if !player {
             result = SearchResult::Lower;
+            best_move = Some(m);
+            self.add_cutoff(m, player, remaining_depth);
             break;
           }
         }
         SearchResult::Higher => {
           if player {
             result = SearchResult::Higher;
+            best_move = Some(m);
+            self.add_cutoff(m, player, remaining_depth);
             break;
           }
         }
         SearchResult::Found(score, mut pv) => {
           pv.push(m);
           result = SearchResult::Found(score, pv);
+          best_move = Some(m);
           if player {
             lo = score;
           } else {
@@ -138,8 +241,133 @@ impl<S: State, E: Evaluator<S>> MinimaxSearch<S, E> {
 
     self.depth -= 1;
 
+    if let Some(hash) = hash {
+      self.store(hash, remaining_depth, &result, lo, hi, best_move);
+    }
+
     result
   }
+
+  // Expectimax: the value of a chance node is the expected value of its
+  // outcomes. The outcomes are searched with the full window, so there are no
+  // cutoffs right below a chance node.
+  fn search_chance(
+    &mut self,
+    state: &S,
+    lo: f32,
+    hi: f32,
+  ) -> SearchResult<S::Move> {
+    let mut state_clone = state.clone();
+    let mut value = 0.0;
+    let mut result = None;
+
+    self.depth += 1;
+    // The outcomes after the principal variation may differ from those on it.
+    self.on_pv = false;
+    for (m, probability) in state.chances() {
+      state_clone.play(m).unwrap();
+      match self.search(&state_clone, f32::MIN, f32::MAX) {
+        SearchResult::Found(score, _) => value += probability * score,
+        _ => {
+          result = Some(SearchResult::Deadline);
+          break;
+        }
+      }
+      state_clone.undo(m).unwrap();
+    }
+    self.depth -= 1;
+
+    result.unwrap_or_else(|| {
+      if value <= lo {
+        SearchResult::Lower
+      } else if value >= hi {
+        SearchResult::Higher
+      } else {
+        SearchResult::Found(value, Vec::new())
+      }
+    })
+  }
+
+  fn order_moves(
+    &mut self,
+    moves: &mut [S::Move],
+    player: bool,
+    pv_move: Option<S::Move>,
+    table_move: Option<S::Move>,
+  ) {
+    // Shuffle in any case, to choose randomly between equal moves.
+    moves.shuffle(&mut self.rng);
+
+    match self.ordering {
+      MoveOrdering::Random => {
+        // Try the best move from the previous search first.
+        if let Some(m) = table_move {
+          if let Some(i) = moves.iter().position(|&x| x == m) {
+            moves.swap(0, i);
+          }
+        }
+      }
+      MoveOrdering::Ordered => {
+        let killers = self.killers[self.depth as usize - 1];
+        let history = &self.history;
+        moves.sort_by_cached_key(|&m| {
+          Reverse(if Some(m) == pv_move {
+            u64::MAX
+          } else if Some(m) == table_move {
+            u64::MAX - 1
+          } else if Some(m) == killers[0] {
+            u64::MAX - 2
+          } else if Some(m) == killers[1] {
+            u64::MAX - 3
+          } else {
+            *history.get(&(player, m)).unwrap_or(&0)
+          })
+        });
+      }
+    }
+  }
+
+  // Updates killer moves and history after move `m` caused a cutoff.
+  fn add_cutoff(&mut self, m: S::Move, player: bool, depth: u32) {
+    if self.ordering != MoveOrdering::Ordered {
+      return;
+    }
+    let killers = &mut self.killers[self.depth as usize - 1];
+    if killers[0] != Some(m) {
+      killers[1] = killers[0];
+      killers[0] = Some(m);
+    }
+    *self.history.entry((player, m)).or_insert(0) +=
+      depth as u64 * depth as u64;
+  }
+
+  fn store(
+    &mut self,
+    hash: u64,
+    depth: u32,
+    result: &SearchResult<S::Move>,
+    lo: f32,
+    hi: f32,
+    best_move: Option<S::Move>,
+  ) {
+    let (bound, score) = match result {
+      SearchResult::Deadline => return,
+      SearchResult::Lower => (Bound::AtMost, lo),
+      SearchResult::Higher => (Bound::AtLeast, hi),
+      SearchResult::Found(score, _) => (Bound::Exact, *score),
+    };
+    // The scores are discounted by the distance from the root. Store them
+    // relative to the current position, so that they could be reused when
+    // the same position is reached at a different depth.
+    let score = score / self.discount[self.depth as usize];
+    self.table.as_ref().unwrap().put(Entry {
+      hash,
+      depth,
+      bound,
+      score,
+      best_move,
+    });
+  }
 }
 
 // discount -- a value <= 1.0, but close to it. The payoff will be multiplied
@@ -150,7 +378,14 @@ pub fn minimax_fixed_depth<S: State, E: Evaluator<S>>(
   depth: u32,
   discount: f32,
 ) -> MinimaxReport<S::Move> {
-  let mut minimax = MinimaxSearch::new(evaluator, depth, discount, None);
+  let mut minimax = MinimaxSearch::new(
+    evaluator,
+    depth,
+    discount,
+    None,
+    0,
+    MoveOrdering::Random,
+  );
   let start_time = Instant::now();
   if let SearchResult::Found(score, pv) =
     minimax.search(state, std::f32::MIN, std::f32::MAX)
@@ -163,7 +398,7 @@ pub fn minimax_fixed_depth<S: State, E: Evaluator<S>>(
       pv,
       samples: minimax.leaves,
       duration: start_time.elapsed(),
-      player: state.player(),
+      player: state.player_index(),
       depth,
     }
   } else {
@@ -174,10 +409,12 @@ pub fn minimax_fixed_depth<S: State, E: Evaluator<S>>(
 #[cfg(test)]
 mod test {
 
+  use rand::SeedableRng;
+
   use crate::def::{AgentReport, Game};
-  use crate::games::Subtractor;
+  use crate::games::{Pig, Subtractor};
   use crate::evaluators::TerminalEvaluator;
-  
+
   use super::*;
 
   #[test]
@@ -212,7 +449,14 @@ mod test {
     let mut state = game.new_game();
     let evaluator = TerminalEvaluator::new();
 
-    let mut minimax = MinimaxSearch::new(&evaluator, 10, 0.999, None);
+    let mut minimax = MinimaxSearch::new(
+      &evaluator,
+      10,
+      0.999,
+      None,
+      0,
+      MoveOrdering::Random,
+    );
     let result = minimax.full_search(&state);
 
     match result {
@@ -222,7 +466,14 @@ mod test {
 
     state.play(3).unwrap();
 
-    let mut minimax = MinimaxSearch::new(&evaluator, 10, 0.999, None);
+    let mut minimax = MinimaxSearch::new(
+      &evaluator,
+      10,
+      0.999,
+      None,
+      0,
+      MoveOrdering::Random,
+    );
     let result = minimax.full_search(&state);
 
     // println!("{:?}", result);
@@ -232,4 +483,109 @@ mod test {
     };
   }
 
+  #[test]
+  fn transposition_table() {
+    let game = Subtractor::new(21, 4);
+    let state = game.new_game();
+    let evaluator = TerminalEvaluator::new();
+    // Both searches shuffle the moves with the same seed, so that the result
+    // doesn't depend on the luck of the move order.
+    let mut minimax = MinimaxSearch::new(
+      &evaluator,
+      12,
+      0.999,
+      None,
+      0,
+      MoveOrdering::Random,
+    );
+    minimax.rng = SmallRng::seed_from_u64(1);
+    let (score, leaves) = match minimax.full_search(&state) {
+      SearchResult::Found(score, pv) => {
+        assert_eq!(1, pv[pv.len() - 1]);
+        (score, minimax.leaves)
+      }
+      _ => panic!(),
+    };
+
+    let mut minimax = MinimaxSearch::new(
+      &evaluator,
+      12,
+      0.999,
+      None,
+      1000,
+      MoveOrdering::Random,
+    );
+    minimax.rng = SmallRng::seed_from_u64(1);
+    match minimax.full_search(&state) {
+      SearchResult::Found(table_score, pv) => {
+        assert_eq!(1, pv[pv.len() - 1]);
+        assert_eq!(score, table_score);
+      }
+      _ => panic!(),
+    };
+    assert!(minimax.leaves < leaves);
+  }
+
+
+  #[test]
+  fn ordered() {
+    let game = Subtractor::new(21, 4);
+    let state = game.new_game();
+    let evaluator = TerminalEvaluator::new();
+
+    let mut minimax = MinimaxSearch::new(
+      &evaluator,
+      1,
+      0.999,
+      None,
+      0,
+      MoveOrdering::Ordered,
+    );
+    for depth in 1..13 {
+      minimax.set_depth(depth);
+      match minimax.full_search(&state) {
+        SearchResult::Found(_, mut pv) => {
+          pv.reverse();
+          if depth == 12 {
+            assert_eq!(1, pv[0]);
+          }
+          minimax.set_pv(&pv);
+        }
+        _ => panic!(),
+      }
+    }
+  }
+
+  #[test]
+  fn expectimax() {
+    // Two points of the three needed are rolled. Rolling again wins unless
+    // the die shows 1, holding lets the opponent move.
+    let mut state = Pig::new(3).new_game();
+    for m in ["roll", "2"].iter() {
+      let m = state.parse_move(m).unwrap();
+      state.play(m).unwrap();
+    }
+    let evaluator = TerminalEvaluator::new();
+    let report = minimax_fixed_depth(&state, &evaluator, 3, 1.0);
+    assert_eq!("roll", report.get_move().to_string());
+    assert!((report.score - 5.0 / 6.0).abs() < 1e-6);
+
+    state.play(report.get_move()).unwrap();
+    assert!(state.is_chance());
+    let mut minimax = MinimaxSearch::new(
+      &evaluator,
+      2,
+      1.0,
+      None,
+      0,
+      MoveOrdering::Random,
+    );
+    match minimax.full_search(&state) {
+      SearchResult::Found(score, _) => {
+        assert!((score - 5.0 / 6.0).abs() < 1e-6)
+      }
+      _ => panic!(),
+    }
+  }
+
 }
@@ -0,0 +1,99 @@
+//! Transposition table, storing the results of the previous searches.
+
+use std::sync::Mutex;
+
+/// The relation between the stored score and the actual minimax value of the
+/// position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bound {
+  Exact,
+  // The value is at least the stored score.
+  AtLeast,
+  // The value is at most the stored score.
+  AtMost,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Entry<M: Copy> {
+  pub hash: u64,
+  // The depth of the search below the position.
+  pub depth: u32,
+  pub bound: Bound,
+  pub score: f32,
+  pub best_move: Option<M>,
+}
+
+/// A fixed-size hash table. On collision a new entry replaces the old one,
+/// unless it is the same position searched to a smaller depth.
+///
+/// Every entry is locked separately, so that the table can be shared between
+/// several search threads.
+pub struct TranspositionTable<M: Copy> {
+  entries: Vec<Mutex<Option<Entry<M>>>>,
+}
+
+impl<M: Copy> TranspositionTable<M> {
+  pub fn new(size: usize) -> Self {
+    assert!(size > 0);
+    TranspositionTable {
+      entries: (0..size).map(|_| Mutex::new(None)).collect(),
+    }
+  }
+
+  pub fn get(&self, hash: u64) -> Option<Entry<M>> {
+    match *self.entries[self.index(hash)].lock().unwrap() {
+      Some(entry) if entry.hash == hash => Some(entry),
+      _ => None,
+    }
+  }
+
+  pub fn put(&self, entry: Entry<M>) {
+    let mut slot = self.entries[self.index(entry.hash)].lock().unwrap();
+    if let Some(ref old) = *slot {
+      if old.hash == entry.hash && old.depth > entry.depth {
+        return;
+      }
+    }
+    *slot = Some(entry);
+  }
+
+  fn index(&self, hash: u64) -> usize {
+    (hash % self.entries.len() as u64) as usize
+  }
+}
+
+#[cfg(test)]
+mod test {
+
+  use super::*;
+
+  fn entry(hash: u64, depth: u32, score: f32) -> Entry<u32> {
+    Entry {
+      hash,
+      depth,
+      bound: Bound::Exact,
+      score,
+      best_move: Some(1),
+    }
+  }
+
+  #[test]
+  fn put_get() {
+    let table = TranspositionTable::new(10);
+    assert!(table.get(5).is_none());
+
+    table.put(entry(5, 2, 0.5));
+    assert_eq!(0.5, table.get(5).unwrap().score);
+    assert!(table.get(15).is_none());
+
+    // Shallower search of the same position doesn't replace the entry.
+    table.put(entry(5, 1, 0.25));
+    assert_eq!(0.5, table.get(5).unwrap().score);
+
+    // A different position does.
+    table.put(entry(15, 1, 0.25));
+    assert!(table.get(5).is_none());
+    assert_eq!(0.25, table.get(15).unwrap().score);
+  }
+
+}
@@ -0,0 +1,401 @@
+//! Counterfactual regret minimisation (CFR) for the games with imperfect
+//! information, and the exploitability of the strategies in such games.
+//!
+//! The solver traverses the whole game tree on every iteration, so it is only
+//! practical for small games like Kuhn and Leduc poker. The players are updated
+//! alternately. With CFR+ the negative regrets are reset to zero and the
+//! strategies of the later iterations get larger weights in the average
+//! strategy, which converges much faster.
+
+use std::collections::HashMap;
+
+use crate::def::{ImperfectInfoState, Policy, State};
+
+// The statistics of an information set.
+struct Node<M> {
+  moves: Vec<M>,
+  // The cumulative counterfactual regrets of the moves.
+  regrets: Vec<f64>,
+  // The sums of the move probabilities, weighted by the player's own reach
+  // probability, which give the average strategy.
+  strategy_sum: Vec<f64>,
+}
+
+impl<M> Node<M> {
+  fn new(moves: Vec<M>) -> Self {
+    let n = moves.len();
+    Node {
+      moves,
+      regrets: vec![0.0; n],
+      strategy_sum: vec![0.0; n],
+    }
+  }
+
+  // Regret matching: the probabilities are proportional to the positive
+  // regrets, or uniform if there are none.
+  fn strategy(&self) -> Vec<f64> {
+    let positive: Vec<f64> = self.regrets.iter().map(|&r| r.max(0.0)).collect();
+    let total: f64 = positive.iter().sum();
+    if total > 0.0 {
+      positive.iter().map(|&r| r / total).collect()
+    } else {
+      vec![1.0 / self.moves.len() as f64; self.moves.len()]
+    }
+  }
+
+  fn average_strategy(&self) -> Vec<f64> {
+    let total: f64 = self.strategy_sum.iter().sum();
+    if total > 0.0 {
+      self.strategy_sum.iter().map(|&s| s / total).collect()
+    } else {
+      vec![1.0 / self.moves.len() as f64; self.moves.len()]
+    }
+  }
+}
+
+pub struct CfrSolver<S: ImperfectInfoState> {
+  root: S,
+  // Use CFR+ rather than the vanilla CFR.
+  plus: bool,
+  iterations: u64,
+  nodes: HashMap<S::InfoSet, Node<S::Move>>,
+}
+
+impl<S: ImperfectInfoState> CfrSolver<S> {
+  /// Creates a solver for the game starting in `root`. Uses CFR+ if `plus` is
+  /// set.
+  pub fn new(root: S, plus: bool) -> Self {
+    CfrSolver {
+      root,
+      plus,
+      iterations: 0,
+      nodes: HashMap::new(),
+    }
+  }
+
+  /// Number of the iterations made so far.
+  pub fn iterations(&self) -> u64 {
+    self.iterations
+  }
+
+  /// Runs the given number of iterations, each updating both players.
+  pub fn train(&mut self, iterations: u64) {
+    for _ in 0..iterations {
+      self.iterations += 1;
+      for &traverser in [true, false].iter() {
+        let root = self.root.clone();
+        self.traverse(&root, traverser, 1.0, 1.0);
+      }
+    }
+  }
+
+  /// Returns the average strategy, which converges to a Nash equilibrium in
+  /// two-player zero-sum games.
+  pub fn policy(&self) -> CfrPolicy<S> {
+    let strategies = self
+      .nodes
+      .iter()
+      .map(|(info_set, node)| {
+        let moves = node
+          .moves
+          .iter()
+          .cloned()
+          .zip(node.average_strategy().into_iter().map(|p| p as f32))
+          .collect();
+        (info_set.clone(), moves)
+      })
+      .collect();
+    CfrPolicy { strategies }
+  }
+
+  // Updates the regrets of the traverser in the subtree of the state and
+  // returns its expected payoff for the traverser. `reach` is the traverser's
+  // own probability to reach the state, and `others_reach` is the probability
+  // of the opponent's and the chance moves.
+  fn traverse(
+    &mut self,
+    state: &S,
+    traverser: bool,
+    reach: f64,
+    others_reach: f64,
+  ) -> f64 {
+    if state.is_terminal() {
+      let payoff = state.payoff().unwrap() as f64;
+      return if traverser { payoff } else { -payoff };
+    }
+    if state.is_chance() {
+      let mut value = 0.0;
+      for (m, p) in state.chances() {
+        let p = p as f64;
+        let child = play(state, m);
+        value += p * self.traverse(&child, traverser, reach, others_reach * p);
+      }
+      return value;
+    }
+
+    let info_set = state.info_set();
+    let (moves, strategy) = {
+      let node = self
+        .nodes
+        .entry(info_set.clone())
+        .or_insert_with(|| Node::new(state.iter_moves().collect()));
+      (node.moves.clone(), node.strategy())
+    };
+
+    if state.player() != traverser {
+      let mut value = 0.0;
+      for (&m, &p) in moves.iter().zip(strategy.iter()) {
+        let child = play(state, m);
+        value += p * self.traverse(&child, traverser, reach, others_reach * p);
+      }
+      return value;
+    }
+
+    let values: Vec<f64> = moves
+      .iter()
+      .zip(strategy.iter())
+      .map(|(&m, &p)| {
+        let child = play(state, m);
+        self.traverse(&child, traverser, reach * p, others_reach)
+      })
+      .collect();
+    let value: f64 =
+      values.iter().zip(strategy.iter()).map(|(v, p)| v * p).sum();
+
+    let weight = if self.plus { self.iterations as f64 } else { 1.0 };
+    let plus = self.plus;
+    let node = self.nodes.get_mut(&info_set).unwrap();
+    for i in 0..moves.len() {
+      node.regrets[i] += others_reach * (values[i] - value);
+      if plus {
+        node.regrets[i] = node.regrets[i].max(0.0);
+      }
+      node.strategy_sum[i] += weight * reach * strategy[i];
+    }
+    value
+  }
+}
+
+/// A fixed strategy, e.g. the average strategy of the CFR solver. The moves in
+/// the unknown information sets are equally probable.
+pub struct CfrPolicy<S: ImperfectInfoState> {
+  strategies: HashMap<S::InfoSet, Vec<(S::Move, f32)>>,
+}
+
+impl<S: ImperfectInfoState> Policy<S> for CfrPolicy<S> {
+  fn get_moves(&self, state: &S) -> Vec<(S::Move, f32)> {
+    if state.is_chance() {
+      return state.chances();
+    }
+    match self.strategies.get(&state.info_set()) {
+      Some(moves) => moves.clone(),
+      None => {
+        let moves: Vec<S::Move> = state.iter_moves().collect();
+        let weight = 1.0 / moves.len() as f32;
+        moves.into_iter().map(|m| (m, weight)).collect()
+      }
+    }
+  }
+}
+
+/// Returns the exploitability of the policy in a two-player zero-sum game: the
+/// average of the payoffs, that the best responses of the two players to the
+/// policy achieve. It is zero for a Nash equilibrium.
+pub fn exploitability<S, P>(root: &S, policy: &P) -> f32
+where
+  S: ImperfectInfoState,
+  P: Policy<S>,
+{
+  let value = |player| BestResponse::new(root, policy, player).value();
+  ((value(true) + value(false)) / 2.0) as f32
+}
+
+// The best response of a player to a fixed policy of the opponent.
+struct BestResponse<'a, S: ImperfectInfoState, P: Policy<S>> {
+  root: S,
+  policy: &'a P,
+  player: bool,
+  // The states of the player's information sets with the probabilities of the
+  // opponent's and the chance moves leading to them.
+  states: HashMap<S::InfoSet, Vec<(S, f64)>>,
+  // The best moves in the player's information sets.
+  best_moves: HashMap<S::InfoSet, S::Move>,
+}
+
+impl<'a, S: ImperfectInfoState, P: Policy<S>> BestResponse<'a, S, P> {
+  fn new(root: &S, policy: &'a P, player: bool) -> Self {
+    let mut response = BestResponse {
+      root: root.clone(),
+      policy,
+      player,
+      states: HashMap::new(),
+      best_moves: HashMap::new(),
+    };
+    response.collect_states(root, 1.0);
+    response
+  }
+
+  fn collect_states(&mut self, state: &S, reach: f64) {
+    if state.is_terminal() {
+      return;
+    }
+    if !state.is_chance() && state.player() == self.player {
+      self
+        .states
+        .entry(state.info_set())
+        .or_default()
+        .push((state.clone(), reach));
+      for m in state.iter_moves() {
+        self.collect_states(&play(state, m), reach);
+      }
+      return;
+    }
+    for (m, p) in self.moves(state) {
+      self.collect_states(&play(state, m), reach * p);
+    }
+  }
+
+  // The moves of the chance or the opponent with their probabilities.
+  fn moves(&self, state: &S) -> Vec<(S::Move, f64)> {
+    if state.is_chance() {
+      state.chances()
+    } else {
+      self.policy.get_moves(state)
+    }
+    .into_iter()
+    .map(|(m, p)| (m, p as f64))
+    .collect()
+  }
+
+  // The expected payoff of the best responding player in the root.
+  fn value(&mut self) -> f64 {
+    let root = self.root.clone();
+    self.state_value(&root)
+  }
+
+  fn state_value(&mut self, state: &S) -> f64 {
+    if state.is_terminal() {
+      let payoff = state.payoff().unwrap() as f64;
+      return if self.player { payoff } else { -payoff };
+    }
+    if !state.is_chance() && state.player() == self.player {
+      let m = self.best_move(state.info_set());
+      return self.state_value(&play(state, m));
+    }
+    self
+      .moves(state)
+      .into_iter()
+      .map(|(m, p)| p * self.state_value(&play(state, m)))
+      .sum()
+  }
+
+  fn best_move(&mut self, info_set: S::InfoSet) -> S::Move {
+    if let Some(&m) = self.best_moves.get(&info_set) {
+      return m;
+    }
+    let states = self.states[&info_set].clone();
+    let moves: Vec<S::Move> = states[0].0.iter_moves().collect();
+    let mut best = (moves[0], f64::NEG_INFINITY);
+    for m in moves {
+      let value: f64 = states
+        .iter()
+        .map(|(state, reach)| reach * self.state_value(&play(state, m)))
+        .sum();
+      if value > best.1 {
+        best = (m, value);
+      }
+    }
+    self.best_moves.insert(info_set, best.0);
+    best.0
+  }
+}
+
+fn play<S: State>(state: &S, m: S::Move) -> S {
+  let mut child = state.clone();
+  child.play(m).unwrap();
+  child
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::def::Game;
+  use crate::equal_policy::EqualPolicy;
+  use crate::games::Poker;
+
+  // The expected payoff of the first player, when both players follow the
+  // policy.
+  fn expected_payoff<S: State, P: Policy<S>>(state: &S, policy: &P) -> f64 {
+    if state.is_terminal() {
+      return state.payoff().unwrap() as f64;
+    }
+    let moves = if state.is_chance() {
+      state.chances()
+    } else {
+      policy.get_moves(state)
+    };
+    moves
+      .into_iter()
+      .map(|(m, p)| p as f64 * expected_payoff(&play(state, m), policy))
+      .sum()
+  }
+
+  #[test]
+  fn kuhn_uniform() {
+    let root = Poker::kuhn().new_game();
+    let uniform = EqualPolicy::new();
+    assert_eq!(0.125, expected_payoff(&root, &uniform) as f32);
+    // The best responses win 0.5 and 0.41(6) chips.
+    let e = exploitability(&root, &uniform);
+    assert!((e - 11.0 / 24.0).abs() < 1e-5, "{}", e);
+  }
+
+  #[test]
+  fn kuhn_cfr() {
+    let root = Poker::kuhn().new_game();
+    for &plus in [false, true].iter() {
+      let mut solver = CfrSolver::new(root.clone(), plus);
+      solver.train(1000);
+      assert_eq!(1000, solver.iterations());
+      let policy = solver.policy();
+      let e = exploitability(&root, &policy);
+      assert!(e < 0.01, "exploitability {}", e);
+      // The value of the game for the first player is -1/18.
+      let value = expected_payoff(&root, &policy);
+      assert!((value + 1.0 / 18.0).abs() < 0.01, "value {}", value);
+    }
+  }
+
+  #[test]
+  fn kuhn_equilibrium() {
+    let root = Poker::kuhn().new_game();
+    let mut solver = CfrSolver::new(root.clone(), true);
+    solver.train(1000);
+    let policy = solver.policy();
+    let mut state = root;
+    for m in "q j".split_whitespace() {
+      let m = state.parse_move(m).unwrap();
+      state.play(m).unwrap();
+    }
+    // The second player always folds a jack facing a bet.
+    state.play(state.parse_move("bet").unwrap()).unwrap();
+    let moves = policy.get_moves(&state);
+    let fold = moves.iter().find(|(m, _)| m.to_string() == "fold").unwrap();
+    assert!(fold.1 > 0.99, "{:?}", moves);
+  }
+
+  #[test]
+  fn leduc_cfr() {
+    let root = Poker::leduc().new_game();
+    let uniform = exploitability(&root, &EqualPolicy::new());
+    let mut solver = CfrSolver::new(root.clone(), true);
+    solver.train(10);
+    let early = exploitability(&root, &solver.policy());
+    solver.train(90);
+    let late = exploitability(&root, &solver.policy());
+    assert!(early < uniform, "{} {}", early, uniform);
+    assert!(late < early, "{} {}", late, early);
+    assert!(late < 0.2, "{}", late);
+  }
+
+}  // mod test
@@ -2,10 +2,12 @@
 
 use rand;
 use std::fmt;
+use std::hash::Hash;
 use std::ops::Deref;
 use std::time::Duration;
 
-use crate::spec::{AgentSpec, EvaluatorSpec, FeatureExtractorSpec, RegressionSpec};
+use crate::spec::{AgentSpec, EvaluatorSpec, FeatureExtractorSpec, MoveOrdering,
+                  Multiplayer, RegressionSpec};
 
 /// A trait for a game rules set.
 pub trait Game: 'static + Sync {
@@ -18,12 +20,32 @@ pub trait Game: 'static + Sync {
 
 /// A trait for a game state. Lifetime parameter `'g` corresponds to Game object
 /// lifetime.
-pub trait State: Clone + fmt::Display {
-  type Move: 'static + Clone + Copy + fmt::Debug + fmt::Display;
+pub trait State: Clone + fmt::Display + Send + Sync {
+  type Move: 'static
+    + Clone
+    + Copy
+    + Eq
+    + Hash
+    + Send
+    + Sync
+    + fmt::Debug
+    + fmt::Display;
 
   /// Returns true if it's the turn of the first player.
   fn player(&self) -> bool;
 
+  /// Returns the number of players. The games with more than two players
+  /// override `player_index()` and `payoffs()`, while `player()` and
+  /// `payoff()` still refer to the first player.
+  fn num_players(&self) -> usize {
+    2
+  }
+
+  /// Returns the index of the player to move, 0 for the first player.
+  fn player_index(&self) -> usize {
+    if self.player() { 0 } else { 1 }
+  }
+
   /// Returns true if position is terminal.
   fn is_terminal(&self) -> bool {
     self.payoff() == None
@@ -33,6 +55,25 @@ pub trait State: Clone + fmt::Display {
   /// otherwize.
   fn payoff(&self) -> Option<f32>;
 
+  /// Returns the payoffs of all the players if position is terminal, None
+  /// otherwise.
+  fn payoffs(&self) -> Option<Vec<f32>> {
+    self.payoff().map(two_player_payoffs)
+  }
+
+  /// Returns true if the next move is made by chance (e.g. a dice roll) rather
+  /// than by a player. The moves of a chance node are its outcomes, and
+  /// `get_random_move()` samples them according to `chances()`.
+  fn is_chance(&self) -> bool {
+    false
+  }
+
+  /// Returns the outcomes of a chance node with their probabilities, that sum
+  /// up to 1. Returns an empty vector if a player is to move.
+  fn chances(&self) -> Vec<(Self::Move, f32)> {
+    Vec::new()
+  }
+
   /// Returns an iterator over all legal moves in the given position.
   fn iter_moves<'s>(&'s self) -> Box<Iterator<Item = Self::Move> + 's>;
 
@@ -48,6 +89,31 @@ pub trait State: Clone + fmt::Display {
 
   /// Parse move, represented as a string.
   fn parse_move(&self, move_str: &str) -> Result<Self::Move, &'static str>;
+
+  /// Returns a hash of the position, used as a key in transposition tables.
+  /// Equal positions with the same player to move should have equal hashes.
+  /// Returns None if the game doesn't support hashing.
+  fn hash(&self) -> Option<u64> {
+    None
+  }
+}
+
+/// Returns the payoffs of both players in a two-player zero-sum game, given the
+/// payoff of the first player.
+pub fn two_player_payoffs(payoff: f32) -> Vec<f32> {
+  vec![payoff, -payoff]
+}
+
+/// A trait for a game state with imperfect information, e.g. hidden cards.
+/// The player to move can't distinguish the states with the same information
+/// set.
+pub trait ImperfectInfoState: State {
+  type InfoSet: Clone + Eq + Hash + Send + Sync + fmt::Debug + fmt::Display;
+
+  /// Returns the information set of the player to move: everything the
+  /// player knows about the state. The states in the same information set
+  /// must have the same legal moves.
+  fn info_set(&self) -> Self::InfoSet;
 }
 
 pub trait AgentReport<M>: fmt::Display {
@@ -61,6 +127,11 @@ pub trait Agent<S: State> {
     state: &S,
   ) -> Result<Box<AgentReport<S::Move>>, &'static str>;
 
+  /// Notifies the agent about a move played in the game, by any of the
+  /// players or by chance. The agents that keep a search tree between the
+  /// moves use it to follow the game.
+  fn observe_move(&self, _m: S::Move) {}
+
   fn spec(&self) -> AgentSpec;
 }
 
@@ -77,6 +148,12 @@ pub trait Evaluator<S: State> {
     }
   }
 
+  /// Evaluates the state for every player. Must be overridden for the games
+  /// with more than two players.
+  fn evaluate_players(&self, state: &S) -> Vec<f32> {
+    two_player_payoffs(self.evaluate(state))
+  }
+
   fn spec(&self) -> EvaluatorSpec;
 
   fn report(&self) {}
@@ -87,6 +164,10 @@ impl<'a, S: State, U: ?Sized + Evaluator<S>> Evaluator<S> for &'a U {
     (*self).evaluate(state)
   }
 
+  fn evaluate_players(&self, state: &S) -> Vec<f32> {
+    (*self).evaluate_players(state)
+  }
+
   fn spec(&self) -> EvaluatorSpec {
     (*self).spec()
   }
@@ -101,6 +182,10 @@ impl<S: State, U: ?Sized + Evaluator<S>> Evaluator<S> for Box<U> {
     self.deref().evaluate(state)
   }
 
+  fn evaluate_players(&self, state: &S) -> Vec<f32> {
+    self.deref().evaluate_players(state)
+  }
+
   fn spec(&self) -> EvaluatorSpec {
     self.deref().spec()
   }
@@ -124,6 +209,12 @@ impl<'a, S: State, P: Policy<S>> Policy<S> for &'a P {
   }
 }
 
+impl<S: State, U: ?Sized + Policy<S>> Policy<S> for Box<U> {
+  fn get_moves(&self, state: &S) -> Vec<(S::Move, f32)> {
+    self.deref().get_moves(state)
+  }
+}
+
 pub trait FeatureExtractor<S: State> {
   fn nfeatures(&self) -> usize;
 
@@ -157,4 +248,19 @@ pub trait Regression: Clone {
 pub trait Trainer<G: Game> {
   fn train(&mut self, steps: u64, time_limit: Duration);
   fn build_evaluator(&self) -> Box<Evaluator<G::State>>;
+
+  /// Returns the spec of an agent, that uses the trained model. By default
+  /// it's a minimax agent with the trained evaluator.
+  fn build_agent_spec(&self) -> AgentSpec {
+    AgentSpec::Minimax {
+      depth: 1000,
+      time_per_move: 0.0,
+      evaluator: self.build_evaluator().spec(),
+      table_size: 0,
+      ordering: MoveOrdering::Random,
+      multiplayer: Multiplayer::MaxN,
+      threads: 0,
+      name: String::new(),
+    }
+  }
 }
@@ -5,7 +5,7 @@ use std::time::{Duration, Instant};
 
 use crate::agents::MinimaxAgent;
 use crate::def::{Agent, Evaluator, FeatureExtractor, Game, Regression, State, Trainer};
-use crate::spec::EvaluatorSpec;
+use crate::spec::{EvaluatorSpec, MoveOrdering};
 
 use super::FeatureEvaluator;
 
@@ -72,7 +72,9 @@ where
     for _game in 0..self.ngames {
       let mut state = self.game.new_game();
       while !state.is_terminal() {
-        let m = if player1_first == state.player() {
+        let m = if state.is_chance() {
+          state.get_random_move(&mut *self.rng.borrow_mut()).unwrap()
+        } else if player1_first == state.player() {
           player1.select_move(&state).unwrap().get_move()
         } else {
           player2.select_move(&state).unwrap().get_move()
@@ -142,7 +144,13 @@ where
     };
 
     let mut current_agent =
-      MinimaxAgent::new(self.build_unboxed_evaluator(), self.minimax_depth, None);
+      MinimaxAgent::new(
+        self.build_unboxed_evaluator(),
+        self.minimax_depth,
+        None,
+        0,
+        MoveOrdering::Random,
+      );
 
     for _step in 0..steps {
       if let Some(d) = deadline {
@@ -164,6 +172,8 @@ where
         ),
         self.minimax_depth,
         None,
+        0,
+        MoveOrdering::Random,
       );
 
       let new_payoff = self.run_games(&new_agent, &current_agent);
@@ -7,7 +7,8 @@ use std::time::{Duration, Instant};
 use crate::def::{Evaluator, Game, Trainer};
 use crate::ladder::Ladder;
 use crate::registry::create_evaluator;
-use crate::spec::{AgentSpec, EvaluatorSpec, FeatureExtractorSpec, RegressionSpec};
+use crate::spec::{AgentSpec, EvaluatorSpec, FeatureExtractorSpec, MoveOrdering,
+                  Multiplayer, RegressionSpec};
 
 pub struct LadderAnnealingTrainer<G: Game> {
   game: &'static G,
@@ -32,7 +33,7 @@ impl<G: Game> LadderAnnealingTrainer<G> {
     temperature: f32,
     ngames: usize,
   ) -> Self {
-    let mut ladder = Ladder::new(game, 8);
+    let mut ladder = Ladder::new(game, 8).unwrap();
     ladder.add_participant(&AgentSpec::Random);
     LadderAnnealingTrainer {
       game,
@@ -67,6 +68,10 @@ impl<G: Game> LadderAnnealingTrainer<G> {
     if let &AgentSpec::Minimax {
       depth: _,
       time_per_move: _,
+      table_size: _,
+      ordering: _,
+      multiplayer: _,
+      threads: _,
       name: _,
       ref evaluator
     } = agent_spec
@@ -104,6 +109,10 @@ impl<G: Game> Trainer<G> for LadderAnnealingTrainer<G> {
       let new_agent_spec = AgentSpec::Minimax {
         depth: self.minimax_depth,
         time_per_move: 0.0,
+        table_size: 0,
+        ordering: MoveOrdering::Random,
+        multiplayer: Multiplayer::MaxN,
+        threads: 0,
         name: format!("Annealing{}", step),
         evaluator: EvaluatorSpec::Features {
           extractor: self.extractor_spec.clone(),
@@ -138,6 +147,10 @@ impl<G: Game> Trainer<G> for LadderAnnealingTrainer<G> {
     if let &AgentSpec::Minimax {
       depth: _,
       time_per_move: _,
+      table_size: _,
+      ordering: _,
+      multiplayer: _,
+      threads: _,
       name: _,
       ref evaluator,
     } = agent_spec
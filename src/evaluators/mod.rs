@@ -1,6 +1,7 @@
 mod annealing;
 pub mod features;
 mod ladder_annealing;
+mod policy_trainer;
 mod regression;
 mod reinforce;
 mod sampler;
@@ -10,6 +11,7 @@ pub mod train_subtractor_eval;
 pub use self::annealing::AnnealingTrainer;
 pub use self::features::FeatureEvaluator;
 pub use self::ladder_annealing::LadderAnnealingTrainer;
+pub use self::policy_trainer::PolicyTrainer;
 pub use self::regression::LinearRegressionTanh;
 pub use self::reinforce::ReinforceTrainer;
 pub use self::sampler::SamplerEvaluator;
@@ -0,0 +1,224 @@
+//! Training of `FeaturePolicy` from the visit distributions of MCTS in
+//! self-play games.
+
+use rand::{Rng, FromEntropy};
+use rand::rngs::SmallRng;
+use std::time::{Duration, Instant};
+
+use crate::agents::MctsAgent;
+use crate::def::{Evaluator, FeatureExtractor, Game, State, Trainer};
+use crate::feature_policy::FeaturePolicy;
+use crate::opt::{AdamOptimizer, Optimizer};
+use crate::registry::create_evaluator;
+use crate::spec::{AgentSpec, EvaluatorSpec, Selection};
+
+/// Plays games of MCTS agent that uses the trained policy against itself.
+/// After every move the policy is updated to minimize the cross-entropy
+/// between its move probabilities and the distribution of MCTS samples over
+/// the moves from the root. The moves in self-play are sampled from the
+/// same distribution. The moves that end the game don't contribute to the
+/// gradient, since `FeaturePolicy` scores them by their payoff.
+pub struct PolicyTrainer<G, E>
+where
+  G: Game,
+  E: FeatureExtractor<G::State>,
+{
+  game: &'static G,
+  policy: FeaturePolicy<G, E>,
+  evaluator_spec: EvaluatorSpec,
+  evaluator: Box<Evaluator<G::State>>,
+  mcts_samples: u64,
+  regularization: f32,
+  optimizer: AdamOptimizer,
+  steps: u64,
+}
+
+impl<G, E> PolicyTrainer<G, E>
+where
+  G: Game,
+  E: FeatureExtractor<G::State>,
+{
+  pub fn new(
+    game: &'static G,
+    policy: FeaturePolicy<G, E>,
+    evaluator_spec: EvaluatorSpec,
+    mcts_samples: u64,
+    regularization: f32,
+    alpha: f32,
+  ) -> Self {
+    let len = policy.params().len();
+    let evaluator = create_evaluator(game, &evaluator_spec);
+    PolicyTrainer {
+      game,
+      policy,
+      evaluator_spec,
+      evaluator,
+      mcts_samples,
+      regularization,
+      optimizer: AdamOptimizer::new(len, alpha),
+      steps: 0,
+    }
+  }
+
+  // Runs MCTS and returns the distribution of the samples over the moves.
+  fn search(
+    &self,
+    state: &G::State,
+  ) -> Vec<(<G::State as State>::Move, f32)> {
+    let agent = MctsAgent::new(
+      &self.policy,
+      &self.evaluator,
+      Some(self.mcts_samples),
+      None,
+    );
+    let report = agent.select_move_with_report(state).unwrap();
+    let total = report.samples() as f32;
+    report
+      .moves()
+      .iter()
+      .map(|stats| (stats.m, stats.samples as f32 / total))
+      .collect()
+  }
+}
+
+impl<G, E> Trainer<G> for PolicyTrainer<G, E>
+where
+  G: Game,
+  E: FeatureExtractor<G::State>,
+{
+  fn train(&mut self, steps: u64, time_limit: Duration) {
+    let mut rng = SmallRng::from_entropy();
+    let mut last_report = Instant::now();
+
+    let deadline = if time_limit != Duration::new(0, 0) {
+      Some(Instant::now() + time_limit)
+    } else {
+      None
+    };
+
+    for _step in 0..steps {
+      if let Some(d) = deadline {
+        if Instant::now() >= d {
+          break;
+        }
+      }
+
+      let mut state = self.game.new_game();
+
+      while !state.is_terminal() {
+        if state.is_chance() {
+          let m = state.get_random_move(&mut rng).unwrap();
+          state.play(m).unwrap();
+          continue;
+        }
+        let target = self.search(&state);
+        let gradient =
+          self.policy.gradient(&state, &target, self.regularization);
+        self.optimizer.gradient_step(self.policy.mut_params(), &gradient);
+        self.steps += 1;
+
+        let mut x: f32 = rng.gen_range(0.0, 1.0);
+        let mut selected = target[0].0;
+        for &(m, p) in target.iter() {
+          selected = m;
+          x -= p;
+          if x < 0.0 {
+            break;
+          }
+        }
+        state.play(selected).unwrap();
+      }
+
+      if Instant::now() - last_report > Duration::new(10, 0) {
+        println!("Steps: {}, params: {:?}", self.steps, self.policy.params());
+        last_report = Instant::now();
+      }
+    }
+  }
+
+  fn build_evaluator(&self) -> Box<Evaluator<G::State>> {
+    create_evaluator(self.game, &self.evaluator_spec)
+  }
+
+  fn build_agent_spec(&self) -> AgentSpec {
+    AgentSpec::Mcts {
+      policy: self.policy.spec(),
+      evaluator: self.evaluator_spec.clone(),
+      samples: self.mcts_samples,
+      time_per_move: 0.0,
+      selection: Selection::Puct,
+      exploration: 1.0,
+      fpu: None,
+      threads: 0,
+      name: String::new(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+
+use crate::def::Policy;
+use crate::games::{Hexapawn, HexapawnCompleteExtractor, Subtractor,
+                   SubtractorFeatureExtractor};
+use crate::spec::PolicySpec;
+
+use super::*;
+
+#[test]
+fn train_subtractor() {
+  let game = Subtractor::default(21, 4);
+  let policy = FeaturePolicy::zeros(game, SubtractorFeatureExtractor::new(4));
+
+  let mut trainer = PolicyTrainer::new(
+    game,
+    policy,
+    EvaluatorSpec::Terminal,
+    200,         // mcts_samples
+    0.001,       // regularization
+    0.1);        // AdamOptimizer alpha
+
+  trainer.train(20, Duration::new(0, 0));
+
+  // The policy should learn to leave a multiple of 4 to the opponent.
+  for i in 5..12 {
+    if i % 4 == 0 {
+      continue;
+    }
+    let state = Subtractor::new(i, 4).new_game();
+    let moves = trainer.policy.get_moves(&state);
+    let &(best, _) = moves
+      .iter()
+      .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+      .unwrap();
+    assert_eq!(i % 4, best, "{:?}", moves);
+  }
+
+  match trainer.build_agent_spec() {
+    AgentSpec::Mcts { policy: PolicySpec::Features { params, .. }, .. } => {
+      assert_eq!(trainer.policy.params(), &params[..])
+    }
+    _ => panic!(),
+  }
+}
+
+#[test]
+fn train_hexapawn() {
+  // Hexapawn positions often have winning moves, which the extractor can't
+  // handle.
+  let game = Hexapawn::default(3, 3);
+  let policy = FeaturePolicy::zeros(game, HexapawnCompleteExtractor::new(game));
+
+  let mut trainer = PolicyTrainer::new(
+    game,
+    policy,
+    EvaluatorSpec::Terminal,
+    50,          // mcts_samples
+    0.001,       // regularization
+    0.1);        // AdamOptimizer alpha
+
+  trainer.train(10, Duration::new(0, 0));
+  assert!(trainer.steps > 0);
+}
+
+}
@@ -98,6 +98,11 @@ where
       let mut state = self.game.new_game();
 
       while !state.is_terminal() {
+        if state.is_chance() {
+          let m = state.get_random_move(&mut rng).unwrap();
+          state.play(m).unwrap();
+          continue;
+        }
         let report = minimax_fixed_depth(&state, self, self.minimax_depth, discount);
         let score = if state.player() { report.score } else { -report.score };
         let gradient = self.regression.gradient1(
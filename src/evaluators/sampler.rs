@@ -25,27 +25,54 @@ impl SamplerEvaluator {
   }
 }
 
+impl SamplerEvaluator {
+  // Plays random moves until the end of the game. Returns the terminal state
+  // and the discount for the number of moves played.
+  fn playout<S: State>(&self, state: &S) -> (S, f64) {
+    let rng = &mut *self.rng.borrow_mut();
+    let mut playout_state = state.clone();
+    let mut moves = 0;
+    while let Some(m) = playout_state.get_random_move(rng) {
+      playout_state.play(m).unwrap();
+      moves += 1;
+    }
+    (playout_state, self.discount.powi(moves))
+  }
+}
+
 impl<S: State> Evaluator<S> for SamplerEvaluator {
   fn evaluate(&self, state: &S) -> f32 {
     if state.is_terminal() {
       return state.payoff().unwrap();
     }
-    let rng = &mut *self.rng.borrow_mut();
     let mut total_payoff: f64 = 0.0;
     for _ in 0..self.nsamples {
-      let mut playout_state = state.clone();
-      let mut moves = 0;
-      while let Some(m) = playout_state.get_random_move(rng) {
-        playout_state.play(m).unwrap();
-        moves += 1;
-      }
+      let (playout_state, discount) = self.playout(state);
       let payoff = playout_state.payoff().unwrap() as f64;
-      total_payoff += payoff * self.discount.powi(moves);
+      total_payoff += payoff * discount;
     }
 
     (total_payoff / self.nsamples as f64) as f32
   }
 
+  fn evaluate_players(&self, state: &S) -> Vec<f32> {
+    if state.is_terminal() {
+      return state.payoffs().unwrap();
+    }
+    let mut total_payoffs = vec![0.0f64; state.num_players()];
+    for _ in 0..self.nsamples {
+      let (playout_state, discount) = self.playout(state);
+      let payoffs = playout_state.payoffs().unwrap();
+      for (total, payoff) in total_payoffs.iter_mut().zip(payoffs) {
+        *total += payoff as f64 * discount;
+      }
+    }
+    total_payoffs
+      .iter()
+      .map(|total| (total / self.nsamples as f64) as f32)
+      .collect()
+  }
+
   fn spec(&self) -> EvaluatorSpec {
     EvaluatorSpec::Sampler {
       samples: self.nsamples,
@@ -19,6 +19,12 @@ impl<S: State> Evaluator<S> for TerminalEvaluator {
     }
   }
 
+  fn evaluate_players(&self, state: &S) -> Vec<f32> {
+    state
+      .payoffs()
+      .unwrap_or_else(|| vec![0.0; state.num_players()])
+  }
+
   fn spec(&self) -> EvaluatorSpec {
     EvaluatorSpec::Terminal
   }
@@ -0,0 +1,223 @@
+use crate::def::{FeatureExtractor, Game, Policy, State};
+use crate::spec::PolicySpec;
+
+/// The logit of a move that wins the game. Losing moves get the opposite
+/// value, drawing moves get zero.
+const TERMINAL_LOGIT: f32 = 100.0;
+
+/// A softmax policy over linear functions of the features. The weight of a
+/// move is `exp(params · features)`, where the features are extracted from
+/// the position after the move, i.e. from the point of view of the opponent.
+/// The moves that end the game aren't passed to the extractor. They get zero
+/// features and a logit of `TERMINAL_LOGIT` times the payoff of the player
+/// making the move, so that a winning move dominates all the others.
+pub struct FeaturePolicy<G, E>
+where
+  G: Game,
+  E: FeatureExtractor<G::State>,
+{
+  _game: &'static G,
+  extractor: E,
+  params: Vec<f32>,
+}
+
+impl<G, E> FeaturePolicy<G, E>
+where
+  G: Game,
+  E: FeatureExtractor<G::State>,
+{
+  pub fn new(game: &'static G, extractor: E, params: &[f32]) -> Self {
+    assert_eq!(extractor.nfeatures(), params.len());
+    FeaturePolicy {
+      _game: game,
+      extractor,
+      params: params.to_vec(),
+    }
+  }
+
+  /// The policy with all the parameters set to zero, which gives all the moves
+  /// equal probabilities.
+  pub fn zeros(game: &'static G, extractor: E) -> Self {
+    let params = vec![0.0; extractor.nfeatures()];
+    Self::new(game, extractor, &params)
+  }
+
+  pub fn params(&self) -> &[f32] {
+    &self.params
+  }
+
+  pub fn mut_params(&mut self) -> &mut [f32] {
+    &mut self.params
+  }
+
+  pub fn spec(&self) -> PolicySpec {
+    PolicySpec::Features {
+      extractor: self.extractor.spec(),
+      params: self.params.clone(),
+    }
+  }
+
+  /// Gradient of the cross-entropy between `target` and the move
+  /// probabilities of the policy in the given state, with L2 regularization.
+  /// The moves missing from `target` have target probability 0.
+  pub fn gradient(
+    &self,
+    state: &G::State,
+    target: &[(<G::State as State>::Move, f32)],
+    regularization: f32,
+  ) -> Vec<f32> {
+    let mut grad: Vec<f32> =
+      self.params.iter().map(|p| 2.0 * regularization * p).collect();
+    for (m, f, p) in self.evaluate_moves(state) {
+      let t = target
+        .iter()
+        .find(|&&(tm, _)| tm == m)
+        .map_or(0.0, |&(_, t)| t);
+      for (g, x) in grad.iter_mut().zip(f.iter()) {
+        *g += (p - t) * x;
+      }
+    }
+    grad
+  }
+
+  // Returns all the moves with their features and probabilities.
+  fn evaluate_moves(
+    &self,
+    state: &G::State,
+  ) -> Vec<(<G::State as State>::Move, Vec<f32>, f32)> {
+    let player = state.player();
+    let moves: Vec<_> = state.iter_moves().collect();
+    let (features, logits): (Vec<Vec<f32>>, Vec<f32>) = moves
+      .iter()
+      .map(|&m| {
+        let mut state = state.clone();
+        state.play(m).unwrap();
+        // Extractors expect non-terminal states, so the moves that end the
+        // game are scored by their payoff.
+        if let Some(payoff) = state.payoff() {
+          let payoff = if player { payoff } else { -payoff };
+          return (vec![0.0; self.params.len()], TERMINAL_LOGIT * payoff);
+        }
+        let f = self.extractor.extract(&state);
+        let logit = f.iter().zip(self.params.iter()).map(|(f, p)| f * p).sum();
+        (f, logit)
+      })
+      .unzip();
+    let max_logit = logits.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+    let weights: Vec<f32> =
+      logits.iter().map(|l| (l - max_logit).exp()).collect();
+    let total: f32 = weights.iter().sum();
+    moves
+      .into_iter()
+      .zip(features)
+      .zip(weights)
+      .map(|((m, f), w)| (m, f, w / total))
+      .collect()
+  }
+}
+
+impl<G, E> Policy<G::State> for FeaturePolicy<G, E>
+where
+  G: Game,
+  E: FeatureExtractor<G::State>,
+{
+  fn get_moves(
+    &self,
+    state: &G::State,
+  ) -> Vec<(<G::State as State>::Move, f32)> {
+    self
+      .evaluate_moves(state)
+      .into_iter()
+      .map(|(m, _, p)| (m, p))
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod test {
+
+  use super::*;
+  use crate::games::{Hexapawn, HexapawnCompleteExtractor, Subtractor,
+                     SubtractorFeatureExtractor};
+
+  #[test]
+  fn subtractor() {
+    let game = Subtractor::default(21, 4);
+    let state = game.new_game();
+
+    let policy = FeaturePolicy::zeros(game, SubtractorFeatureExtractor::new(4));
+    for (_, w) in policy.get_moves(&state) {
+      assert!((w - 1. / 3.).abs() < 1e-6);
+    }
+
+    // Prefer leaving a multiple of 4 to the opponent.
+    let policy = FeaturePolicy::new(
+      game,
+      SubtractorFeatureExtractor::new(4),
+      &[0.0, 0.0, 0.0, 5.0],
+    );
+    let moves = policy.get_moves(&state);
+    assert_eq!(3, moves.len());
+    let total: f32 = moves.iter().map(|&(_, w)| w).sum();
+    assert!((total - 1.0).abs() < 1e-6);
+    for (m, w) in moves {
+      if m == 1 {
+        assert!(w > 0.9);
+      } else {
+        assert!(w < 0.05);
+      }
+    }
+  }
+
+  #[test]
+  fn gradient() {
+    let game = Subtractor::default(21, 4);
+    let state = game.new_game();
+    let mut policy =
+      FeaturePolicy::zeros(game, SubtractorFeatureExtractor::new(4));
+    let target = [(1, 1.0)];
+
+    // Gradient descent should make the target move the most likely.
+    for _ in 0..100 {
+      let grad = policy.gradient(&state, &target, 0.0);
+      for (p, g) in policy.mut_params().iter_mut().zip(grad.iter()) {
+        *p -= g;
+      }
+    }
+    for (m, w) in policy.get_moves(&state) {
+      if m == 1 {
+        assert!(w > 0.9, "{}", w);
+      }
+    }
+  }
+
+  #[test]
+  fn winning_move() {
+    let game = Hexapawn::default(3, 3);
+    let mut state = game.new_game();
+    for m in &["b1-b2", "a3xb2", "a1-a2"] {
+      let m = state.parse_move(m).unwrap();
+      state.play(m).unwrap();
+    }
+    // Black wins with both b2-b1 and b2xc1.
+    let winning = state.parse_move("b2-b1").unwrap();
+
+    let policy =
+      FeaturePolicy::zeros(game, HexapawnCompleteExtractor::new(game));
+    let moves = policy.get_moves(&state);
+    assert_eq!(4, moves.len());
+    for &(m, w) in moves.iter() {
+      let mut child = state.clone();
+      child.play(m).unwrap();
+      if child.payoff() == Some(-1.0) {
+        assert!((w - 0.5).abs() < 0.01, "{}", w);
+      } else {
+        assert!(w < 0.01, "{}", w);
+      }
+    }
+
+    let grad = policy.gradient(&state, &[(winning, 1.0)], 0.0);
+    assert_eq!(policy.params().len(), grad.len());
+  }
+
+}  // mod test
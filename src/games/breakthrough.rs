@@ -0,0 +1,225 @@
+//! Breakthrough, a generalisation of Hexapawn. Both players start with two
+//! rows of pawns, that move one square forward, straight or diagonally, and
+//! take only diagonally. The player, who reaches the last row, wins.
+
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::def::{FeatureExtractor, Game, Regression, State};
+use crate::games::cached_instance;
+use crate::spec::FeatureExtractorSpec;
+use super::hexapawn::{HexapawnCell, HexapawnState};
+
+lazy_static! {
+  static ref INSTANCE_6_6: Breakthrough = Breakthrough::new(6, 6);
+  static ref INSTANCE_8_8: Breakthrough = Breakthrough::new(8, 8);
+  static ref INSTANCES: Mutex<HashMap<(u32, u32), &'static Breakthrough>> =
+    Mutex::new(HashMap::new());
+}
+
+pub struct Breakthrough {
+  width: u32,
+  height: u32,
+}
+
+impl Breakthrough {
+  pub fn new(width: u32, height: u32) -> Breakthrough {
+    assert!(width >= 2 && height >= 5);
+    Breakthrough { width, height }
+  }
+
+  pub fn default(width: u32, height: u32) -> &'static Breakthrough {
+    match (width, height) {
+      (6, 6) => &*INSTANCE_6_6,
+      (8, 8) => &*INSTANCE_8_8,
+      _ => cached_instance(&INSTANCES, (width, height), || {
+        Breakthrough::new(width, height)
+      }),
+    }
+  }
+}
+
+impl Game for Breakthrough {
+  type State = HexapawnState;
+
+  fn new_game(&self) -> HexapawnState {
+    HexapawnState::new(self.width, self.height, 2, true)
+  }
+}
+
+/// Piece-square tables: the number of pawns of the acting player and of the
+/// opponent on every square, counting the rows from the owner's side. The
+/// columns are folded, since the board is symmetric.
+#[derive(Clone)]
+pub struct BreakthroughFeatureExtractor {
+  width: u32,
+  height: u32,
+}
+
+impl BreakthroughFeatureExtractor {
+  pub fn new(game: &Breakthrough) -> Self {
+    BreakthroughFeatureExtractor {
+      width: game.width,
+      height: game.height,
+    }
+  }
+
+  fn half_width(&self) -> u32 {
+    self.width.div_ceil(2)
+  }
+
+  fn table_len(&self) -> usize {
+    (self.height * self.half_width()) as usize
+  }
+}
+
+impl FeatureExtractor<HexapawnState> for BreakthroughFeatureExtractor {
+  fn nfeatures(&self) -> usize {
+    1 + 2 * self.table_len()
+  }
+
+  fn extract(&self, state: &HexapawnState) -> Vec<f32> {
+    let player = state.player();
+    let mut features = vec![0.0; self.nfeatures()];
+    features[0] = 1.0;
+    for (point, &cell) in state.board.iter().enumerate() {
+      if cell == HexapawnCell::Empty {
+        continue;
+      }
+      let owner = cell.is_player(true);
+      let (x, y) = state.board.point_to_xy(point);
+      let row = if owner { y } else { self.height - 1 - y };
+      let col = x.min(self.width - 1 - x);
+      let table = if owner == player { 0 } else { self.table_len() };
+      features[1 + table + (row * self.half_width() + col) as usize] += 1.0;
+    }
+    features
+  }
+
+  fn spec(&self) -> FeatureExtractorSpec {
+    FeatureExtractorSpec::Breakthrough
+  }
+
+  fn report<R: Regression>(&self, regression: &R) {
+    let b = regression.params();
+    println!("bias: {:>6.3}", b[0]);
+    for (name, table) in [("self", 0), ("other", self.table_len())].iter() {
+      println!("{}:", name);
+      for row in (0..self.height).rev() {
+        for col in 0..self.half_width() {
+          let i = 1 + table + (row * self.half_width() + col) as usize;
+          print!("{:+6.2} ", b[i]);
+        }
+        println!();
+      }
+    }
+    println!();
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use rand::FromEntropy;
+  use rand::rngs::SmallRng;
+
+  use super::*;
+
+  fn play(state: &mut HexapawnState, moves: &str) {
+    for m in moves.split_whitespace() {
+      let m = state.parse_move(m).unwrap();
+      state.play(m).unwrap();
+    }
+  }
+
+  #[test]
+  fn init() {
+    let state = Breakthrough::default(8, 8).new_game();
+    assert_eq!(Some(HexapawnCell::White), state.board.get_a("a1"));
+    assert_eq!(Some(HexapawnCell::White), state.board.get_a("h2"));
+    assert_eq!(Some(HexapawnCell::Black), state.board.get_a("a7"));
+    assert_eq!(Some(HexapawnCell::Black), state.board.get_a("h8"));
+    assert_eq!(Some(HexapawnCell::Empty), state.board.get_a("d3"));
+    // Only the front row can move: three moves each, two at the edges.
+    assert_eq!(22, state.iter_moves().count());
+  }
+
+  #[test]
+  fn steps_and_takes() {
+    let mut state = Breakthrough::new(6, 6).new_game();
+    assert!(state.parse_move("c2xd3").is_err());
+    assert!(state.parse_move("c2-c4").is_err());
+    play(&mut state, "c2-d3 c5-c4");
+    // Straight moves can't take.
+    assert!(state.parse_move("c1-c2").is_ok());
+    assert!(state.parse_move("d3-c4").is_err());
+    assert!(state.parse_move("d3xd4").is_err());
+    let m = state.parse_move("d3xc4").unwrap();
+    assert_eq!("d3xc4", m.to_string());
+    let hash = state.hash();
+    state.play(m).unwrap();
+    assert_eq!(Some(HexapawnCell::White), state.board.get_a("c4"));
+    state.undo(m).unwrap();
+    assert_eq!(Some(HexapawnCell::Black), state.board.get_a("c4"));
+    assert_eq!(hash, state.hash());
+  }
+
+  #[test]
+  fn win() {
+    let mut state = Breakthrough::new(6, 6).new_game();
+    play(&mut state, "a2-a3 f5-f4 a3-a4 f4-f3 a4xb5 f3xe2");
+    assert!(!state.is_terminal());
+    play(&mut state, "b5xa6");
+    assert!(state.is_terminal());
+    assert_eq!(Some(1.0), state.payoff());
+  }
+
+  #[test]
+  fn random_games() {
+    let mut rng = SmallRng::from_entropy();
+    let game = Breakthrough::new(5, 5);
+    for _ in 0..10 {
+      let mut state = game.new_game();
+      let mut moves = Vec::new();
+      while let Some(m) = state.get_random_move(&mut rng) {
+        state.play(m).unwrap();
+        moves.push(m);
+      }
+      assert!(state.payoff().is_some());
+      for &m in moves.iter().rev() {
+        state.undo(m).unwrap();
+      }
+      assert_eq!(game.new_game().hash(), state.hash());
+    }
+  }
+
+  #[test]
+  fn extractor() {
+    let game = Breakthrough::default(6, 6);
+    let extractor = BreakthroughFeatureExtractor::new(game);
+    assert_eq!(37, extractor.nfeatures());
+    let mut state = game.new_game();
+    let mut expected = vec![0.0; 37];
+    expected[0] = 1.0;
+    for i in 0..6 {
+      expected[1 + i] = 2.0;
+      expected[19 + i] = 2.0;
+    }
+    assert_eq!(expected, extractor.extract(&state));
+
+    // Black is to move, so the white pawn moves in the opponent's table. The
+    // folded columns of c2 and d3 are the same.
+    play(&mut state, "c2-d3");
+    expected[19 + 3 + 2] = 1.0;
+    expected[19 + 6 + 2] = 1.0;
+    assert_eq!(expected, extractor.extract(&state));
+  }
+
+  #[test]
+  fn default_sizes() {
+    let game = Breakthrough::default(7, 5);
+    assert!(std::ptr::eq(game, Breakthrough::default(7, 5)));
+    assert_eq!(19, game.new_game().iter_moves().count());
+  }
+
+}  // mod test
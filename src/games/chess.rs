@@ -0,0 +1,1108 @@
+//! Chess with the complete rules: castling, en passant, promotion,
+//! stalemate, the fifty-move rule and threefold repetition.
+//!
+//! The first player is white. The positions are set up from FEN, and the
+//! moves are written in the UCI notation: the start and the destination
+//! squares and the promotion piece if any, e.g. "e2e4", "e1g1" (castling) or
+//! "e7e8q".
+
+use lazy_static::lazy_static;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::fmt;
+
+use crate::board::{point_to_a, Board, Cell};
+use crate::def::{FeatureExtractor, Game, Regression, State};
+use crate::spec::FeatureExtractorSpec;
+use crate::status::Status;
+use crate::zobrist;
+
+pub const START_FEN: &str =
+  "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+const SIZE: u32 = 8;
+
+// Castling rights.
+const WHITE_KINGSIDE: u8 = 1;
+const WHITE_QUEENSIDE: u8 = 2;
+const BLACK_KINGSIDE: u8 = 4;
+const BLACK_QUEENSIDE: u8 = 8;
+
+const KNIGHT_OFFSETS: [(i32, i32); 8] = [
+  (1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2),
+];
+const KING_OFFSETS: [(i32, i32); 8] = [
+  (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1),
+];
+const ROOK_DIRECTIONS: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const BISHOP_DIRECTIONS: [(i32, i32); 4] =
+  [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+lazy_static! {
+  static ref INSTANCE: Chess = Chess::new();
+}
+
+/// Chess starting from the given position.
+pub struct Chess {
+  start: ChessState,
+}
+
+impl Chess {
+  pub fn new() -> Chess {
+    Chess::from_fen(START_FEN).unwrap()
+  }
+
+  pub fn from_fen(fen: &str) -> Result<Chess, &'static str> {
+    Ok(Chess {
+      start: ChessState::from_fen(fen)?,
+    })
+  }
+
+  pub fn default() -> &'static Chess {
+    &*INSTANCE
+  }
+}
+
+impl Game for Chess {
+  type State = ChessState;
+
+  fn new_game(&self) -> ChessState {
+    self.start.clone()
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Piece {
+  Pawn,
+  Knight,
+  Bishop,
+  Rook,
+  Queen,
+  King,
+}
+
+impl Piece {
+  fn index(self) -> usize {
+    self as usize
+  }
+
+  fn letter(self) -> char {
+    match self {
+      Piece::Pawn => 'p',
+      Piece::Knight => 'n',
+      Piece::Bishop => 'b',
+      Piece::Rook => 'r',
+      Piece::Queen => 'q',
+      Piece::King => 'k',
+    }
+  }
+
+  fn from_letter(c: char) -> Option<Piece> {
+    match c.to_ascii_lowercase() {
+      'p' => Some(Piece::Pawn),
+      'n' => Some(Piece::Knight),
+      'b' => Some(Piece::Bishop),
+      'r' => Some(Piece::Rook),
+      'q' => Some(Piece::Queen),
+      'k' => Some(Piece::King),
+      _ => None,
+    }
+  }
+
+  fn value(self) -> f32 {
+    match self {
+      Piece::Pawn => 1.0,
+      Piece::Knight | Piece::Bishop => 3.0,
+      Piece::Rook => 5.0,
+      Piece::Queen => 9.0,
+      Piece::King => 0.0,
+    }
+  }
+}
+
+/// A move from a square to a square, with the piece a pawn is promoted to.
+/// Castling is the move of the king by two squares.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChessMove {
+  from: u8,
+  to: u8,
+  promotion: Option<Piece>,
+}
+
+impl ChessMove {
+  fn new(from: usize, to: usize, promotion: Option<Piece>) -> ChessMove {
+    ChessMove {
+      from: from as u8,
+      to: to as u8,
+      promotion,
+    }
+  }
+}
+
+impl fmt::Display for ChessMove {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "{}{}",
+      point_to_a(self.from as usize, SIZE),
+      point_to_a(self.to as usize, SIZE)
+    )?;
+    if let Some(piece) = self.promotion {
+      write!(f, "{}", piece.letter())?;
+    }
+    Ok(())
+  }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ChessCell {
+  Empty,
+  // The piece and whether it's white.
+  Piece(Piece, bool),
+}
+
+impl ChessCell {
+  fn is(self, piece: Piece, white: bool) -> bool {
+    self == ChessCell::Piece(piece, white)
+  }
+
+  fn owner(self) -> Option<bool> {
+    match self {
+      ChessCell::Empty => None,
+      ChessCell::Piece(_, white) => Some(white),
+    }
+  }
+
+  fn key(self, square: usize) -> u64 {
+    match self {
+      ChessCell::Empty => 0,
+      ChessCell::Piece(piece, white) => {
+        zobrist::point_key(piece.index() * 64 + square, white)
+      }
+    }
+  }
+}
+
+impl Cell for ChessCell {
+  fn empty() -> ChessCell {
+    ChessCell::Empty
+  }
+
+  fn is_empty(self) -> bool {
+    self == ChessCell::Empty
+  }
+
+  fn ascii(self) -> char {
+    match self {
+      ChessCell::Empty => '.',
+      ChessCell::Piece(piece, true) => piece.letter().to_ascii_uppercase(),
+      ChessCell::Piece(piece, false) => piece.letter(),
+    }
+  }
+
+  fn unicode(self) -> char {
+    match self {
+      ChessCell::Empty => '·',
+      ChessCell::Piece(piece, white) => {
+        let symbols = if white { "♙♘♗♖♕♔" } else { "♟♞♝♜♛♚" };
+        symbols.chars().nth(piece.index()).unwrap()
+      }
+    }
+  }
+}
+
+// The en passant file is hashed as a pawn on the squares after the board.
+fn en_passant_key(square: usize) -> u64 {
+  zobrist::point_key(6 * 64 + square % 8, true)
+}
+
+fn castling_key(castling: u8) -> u64 {
+  (0..4)
+    .filter(|&i| castling & (1 << i) != 0)
+    .fold(0, |key, i| key ^ zobrist::flag_key(i))
+}
+
+// The square at the offset in files and ranks from the given one.
+fn offset(square: usize, df: i32, dr: i32) -> Option<usize> {
+  let f = (square % 8) as i32 + df;
+  let r = (square / 8) as i32 + dr;
+  if (0..8).contains(&f) && (0..8).contains(&r) {
+    Some((r * 8 + f) as usize)
+  } else {
+    None
+  }
+}
+
+fn forward(white: bool) -> i32 {
+  if white { 1 } else { -1 }
+}
+
+// The castling rights lost when a piece moves from or to the square.
+fn castling_lost(square: usize) -> u8 {
+  match square {
+    0 => WHITE_QUEENSIDE,
+    4 => WHITE_KINGSIDE | WHITE_QUEENSIDE,
+    7 => WHITE_KINGSIDE,
+    56 => BLACK_QUEENSIDE,
+    60 => BLACK_KINGSIDE | BLACK_QUEENSIDE,
+    63 => BLACK_KINGSIDE,
+    _ => 0,
+  }
+}
+
+// Whether a piece of `white` attacks the square, on the board given by the
+// function from the squares to their contents.
+fn attacked_on<F: Fn(usize) -> ChessCell>(
+  cell: F,
+  square: usize,
+  white: bool,
+) -> bool {
+  let pawn_rank = -forward(white);
+  for &df in [-1, 1].iter() {
+    if let Some(s) = offset(square, df, pawn_rank) {
+      if cell(s).is(Piece::Pawn, white) {
+        return true;
+      }
+    }
+  }
+  for &(df, dr) in KNIGHT_OFFSETS.iter() {
+    if let Some(s) = offset(square, df, dr) {
+      if cell(s).is(Piece::Knight, white) {
+        return true;
+      }
+    }
+  }
+  for &(df, dr) in KING_OFFSETS.iter() {
+    if let Some(s) = offset(square, df, dr) {
+      if cell(s).is(Piece::King, white) {
+        return true;
+      }
+    }
+  }
+  for &(directions, slider) in [
+    (&ROOK_DIRECTIONS, Piece::Rook),
+    (&BISHOP_DIRECTIONS, Piece::Bishop),
+  ]
+  .iter()
+  {
+    for &(df, dr) in directions.iter() {
+      let mut s = square;
+      while let Some(next) = offset(s, df, dr) {
+        s = next;
+        match cell(s) {
+          ChessCell::Empty => continue,
+          c => {
+            if c.is(slider, white) || c.is(Piece::Queen, white) {
+              return true;
+            }
+            break;
+          }
+        }
+      }
+    }
+  }
+  false
+}
+
+// The squares, from which and to which the rook moves in castling.
+fn castling_rook(king_from: usize, king_to: usize) -> (usize, usize) {
+  if king_to > king_from {
+    (king_from + 3, king_from + 1)
+  } else {
+    (king_from - 4, king_from - 1)
+  }
+}
+
+// What is needed to undo a move.
+#[derive(Clone, Debug)]
+struct Record {
+  m: ChessMove,
+  captured: Option<(usize, ChessCell)>,
+  castling: u8,
+  en_passant: Option<usize>,
+  halfmove: u32,
+  hash: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChessState {
+  board: Board<ChessCell>,
+  status: Status,
+  castling: u8,
+  // The square passed by a pawn in the last double step, if an enemy pawn
+  // can capture en passant.
+  en_passant: Option<usize>,
+  // Plies since the last capture or pawn move.
+  halfmove: u32,
+  history: Vec<Record>,
+  // Hashes of all the positions of the game, including the current one.
+  hashes: Vec<u64>,
+  hash: u64,
+}
+
+impl ChessState {
+  /// Parses a position in the Forsyth-Edwards notation. The move number may
+  /// be omitted.
+  pub fn from_fen(fen: &str) -> Result<ChessState, &'static str> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    if fields.len() < 4 || fields.len() > 6 {
+      return Err("Wrong number of FEN fields.");
+    }
+    let mut state = ChessState {
+      board: Board::new_empty(SIZE, SIZE),
+      status: Status::new(),
+      castling: 0,
+      en_passant: None,
+      halfmove: 0,
+      history: Vec::new(),
+      hashes: Vec::new(),
+      hash: 0,
+    };
+
+    let ranks: Vec<&str> = fields[0].split('/').collect();
+    if ranks.len() != 8 {
+      return Err("Wrong number of ranks in FEN.");
+    }
+    for (i, rank) in ranks.iter().enumerate() {
+      let r = 7 - i;
+      let mut f = 0;
+      for c in rank.chars() {
+        if let Some(empty) = c.to_digit(10) {
+          f += empty as usize;
+        } else {
+          let piece = Piece::from_letter(c).ok_or("Unknown piece in FEN.")?;
+          if f >= 8 {
+            return Err("Too many squares in FEN rank.");
+          }
+          state.put(r * 8 + f, ChessCell::Piece(piece, c.is_ascii_uppercase()));
+          f += 1;
+        }
+      }
+      if f != 8 {
+        return Err("Wrong number of squares in FEN rank.");
+      }
+    }
+    for &white in [true, false].iter() {
+      let kings =
+        state.board.iter().filter(|c| c.is(Piece::King, white)).count();
+      if kings != 1 {
+        return Err("Each side must have one king.");
+      }
+    }
+
+    match fields[1] {
+      "w" => {}
+      "b" => {
+        state.status.switch_player();
+        state.hash ^= zobrist::player_key();
+      }
+      _ => return Err("Wrong side to move in FEN."),
+    }
+
+    if fields[2] != "-" {
+      for c in fields[2].chars() {
+        state.castling |= match c {
+          'K' => WHITE_KINGSIDE,
+          'Q' => WHITE_QUEENSIDE,
+          'k' => BLACK_KINGSIDE,
+          'q' => BLACK_QUEENSIDE,
+          _ => return Err("Wrong castling rights in FEN."),
+        };
+      }
+    }
+    state.hash ^= castling_key(state.castling);
+
+    if fields[3] != "-" {
+      let square = state
+        .board
+        .parse_point(fields[3])
+        .ok_or("Wrong en passant square in FEN.")?;
+      state.en_passant = Some(square);
+      state.hash ^= en_passant_key(square);
+    }
+    if fields.len() > 4 {
+      state.halfmove =
+        fields[4].parse().map_err(|_| "Wrong halfmove clock in FEN.")?;
+    }
+
+    if state.in_check(!state.player()) {
+      return Err("The side not to move is in check.");
+    }
+    state.hashes.push(state.hash);
+    Ok(state)
+  }
+
+  fn cell(&self, square: usize) -> ChessCell {
+    self.board.get(square).unwrap()
+  }
+
+  fn put(&mut self, square: usize, c: ChessCell) {
+    self.hash ^= self.cell(square).key(square) ^ c.key(square);
+    self.board.set(square, c);
+  }
+
+  // Whether a piece of `white` attacks the square.
+  fn attacked(&self, square: usize, white: bool) -> bool {
+    attacked_on(|s| self.cell(s), square, white)
+  }
+
+  // The cell on the square after the move of the player to move, found
+  // without making the move.
+  fn cell_after(&self, m: ChessMove, square: usize) -> ChessCell {
+    let white = self.player();
+    let (from, to) = (m.from as usize, m.to as usize);
+    let moving = self.cell(from);
+    if square == to {
+      return match m.promotion {
+        Some(piece) => ChessCell::Piece(piece, white),
+        None => moving,
+      };
+    }
+    if square == from {
+      return ChessCell::Empty;
+    }
+    if moving.is(Piece::Pawn, white)
+      && Some(to) == self.en_passant
+      && offset(to, 0, -forward(white)) == Some(square)
+    {
+      return ChessCell::Empty;
+    }
+    if moving.is(Piece::King, white) && (to as i32 - from as i32).abs() == 2 {
+      let (rook_from, rook_to) = castling_rook(from, to);
+      if square == rook_from {
+        return ChessCell::Empty;
+      }
+      if square == rook_to {
+        return self.cell(rook_from);
+      }
+    }
+    self.cell(square)
+  }
+
+  // Whether the pseudo-legal move of the player to move leaves its king in
+  // check.
+  fn is_legal(&self, m: ChessMove, king: usize) -> bool {
+    let king = if m.from as usize == king { m.to as usize } else { king };
+    !attacked_on(|s| self.cell_after(m, s), king, !self.player())
+  }
+  fn king_square(&self, white: bool) -> usize {
+    (0..64).find(|&s| self.cell(s).is(Piece::King, white)).unwrap()
+  }
+
+  fn in_check(&self, white: bool) -> bool {
+    self.attacked(self.king_square(white), !white)
+  }
+
+  fn add_pawn_moves(&self, from: usize, to: usize, moves: &mut Vec<ChessMove>) {
+    let rank = to / 8;
+    if rank == 0 || rank == 7 {
+      for &piece in
+        [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight].iter()
+      {
+        moves.push(ChessMove::new(from, to, Some(piece)));
+      }
+    } else {
+      moves.push(ChessMove::new(from, to, None));
+    }
+  }
+
+  // The moves of `white`, that may leave its own king in check.
+  fn pseudo_moves(&self, white: bool) -> Vec<ChessMove> {
+    let mut moves = Vec::new();
+    for from in 0..64 {
+      let piece = match self.cell(from) {
+        ChessCell::Piece(piece, w) if w == white => piece,
+        _ => continue,
+      };
+      let target_ok =
+        |s: usize| self.cell(s).owner() != Some(white);
+      match piece {
+        Piece::Pawn => {
+          let dir = forward(white);
+          if let Some(to) = offset(from, 0, dir) {
+            if self.cell(to) == ChessCell::Empty {
+              self.add_pawn_moves(from, to, &mut moves);
+              let start_rank = if white { 1 } else { 6 };
+              if from / 8 == start_rank {
+                let to2 = offset(to, 0, dir).unwrap();
+                if self.cell(to2) == ChessCell::Empty {
+                  moves.push(ChessMove::new(from, to2, None));
+                }
+              }
+            }
+          }
+          for &df in [-1, 1].iter() {
+            if let Some(to) = offset(from, df, dir) {
+              if self.cell(to).owner() == Some(!white)
+                || self.en_passant == Some(to)
+              {
+                self.add_pawn_moves(from, to, &mut moves);
+              }
+            }
+          }
+        }
+        Piece::Knight | Piece::King => {
+          let offsets = if piece == Piece::Knight {
+            &KNIGHT_OFFSETS
+          } else {
+            &KING_OFFSETS
+          };
+          for &(df, dr) in offsets.iter() {
+            if let Some(to) = offset(from, df, dr) {
+              if target_ok(to) {
+                moves.push(ChessMove::new(from, to, None));
+              }
+            }
+          }
+        }
+        Piece::Bishop | Piece::Rook | Piece::Queen => {
+          let directions: &[(i32, i32)] = match piece {
+            Piece::Bishop => &BISHOP_DIRECTIONS,
+            Piece::Rook => &ROOK_DIRECTIONS,
+            _ => &KING_OFFSETS,
+          };
+          for &(df, dr) in directions.iter() {
+            let mut to = from;
+            while let Some(next) = offset(to, df, dr) {
+              to = next;
+              if target_ok(to) {
+                moves.push(ChessMove::new(from, to, None));
+              }
+              if self.cell(to) != ChessCell::Empty {
+                break;
+              }
+            }
+          }
+        }
+      }
+    }
+    self.add_castling(white, &mut moves);
+    moves
+  }
+
+  fn add_castling(&self, white: bool, moves: &mut Vec<ChessMove>) {
+    let (kingside, queenside, king) = if white {
+      (WHITE_KINGSIDE, WHITE_QUEENSIDE, 4)
+    } else {
+      (BLACK_KINGSIDE, BLACK_QUEENSIDE, 60)
+    };
+    if self.castling & (kingside | queenside) == 0
+      || self.attacked(king, !white)
+    {
+      return;
+    }
+    let empty = |squares: &[usize]| {
+      squares.iter().all(|&s| self.cell(s) == ChessCell::Empty)
+    };
+    if self.castling & kingside != 0
+      && empty(&[king + 1, king + 2])
+      && !self.attacked(king + 1, !white)
+    {
+      // The destination of the king is checked with the other moves.
+      moves.push(ChessMove::new(king, king + 2, None));
+    }
+    if self.castling & queenside != 0
+      && empty(&[king - 1, king - 2, king - 3])
+      && !self.attacked(king - 1, !white)
+    {
+      moves.push(ChessMove::new(king, king - 2, None));
+    }
+  }
+
+  // Plays the move without checking it and without deciding, whether the
+  // game is over.
+  fn make(&mut self, m: ChessMove) -> Record {
+    let white = self.player();
+    let (from, to) = (m.from as usize, m.to as usize);
+    let record = Record {
+      m,
+      captured: None,
+      castling: self.castling,
+      en_passant: self.en_passant,
+      halfmove: self.halfmove,
+      hash: self.hash,
+    };
+    let mut record = record;
+    let moving = self.cell(from);
+    let is_pawn = moving.is(Piece::Pawn, white);
+
+    let captured_square = if is_pawn && Some(to) == self.en_passant {
+      offset(to, 0, -forward(white)).unwrap()
+    } else {
+      to
+    };
+    if self.cell(captured_square) != ChessCell::Empty {
+      record.captured = Some((captured_square, self.cell(captured_square)));
+      self.put(captured_square, ChessCell::Empty);
+    }
+
+    self.put(from, ChessCell::Empty);
+    let placed = match m.promotion {
+      Some(piece) => ChessCell::Piece(piece, white),
+      None => moving,
+    };
+    self.put(to, placed);
+
+    if moving.is(Piece::King, white) && (to as i32 - from as i32).abs() == 2 {
+      let (rook_from, rook_to) = castling_rook(from, to);
+      let rook = self.cell(rook_from);
+      self.put(rook_from, ChessCell::Empty);
+      self.put(rook_to, rook);
+    }
+
+    self.hash ^= castling_key(self.castling);
+    self.castling &= !(castling_lost(from) | castling_lost(to));
+    self.hash ^= castling_key(self.castling);
+
+    if let Some(square) = self.en_passant {
+      self.hash ^= en_passant_key(square);
+    }
+    self.en_passant = None;
+    if is_pawn && (to as i32 - from as i32).abs() == 16 {
+      let enemy_pawn = [-1, 1].iter().any(|&df| match offset(to, df, 0) {
+        Some(s) => self.cell(s).is(Piece::Pawn, !white),
+        None => false,
+      });
+      if enemy_pawn {
+        let passed = (from + to) / 2;
+        self.en_passant = Some(passed);
+        self.hash ^= en_passant_key(passed);
+      }
+    }
+
+    if is_pawn || record.captured.is_some() {
+      self.halfmove = 0;
+    } else {
+      self.halfmove += 1;
+    }
+    self.hash ^= zobrist::player_key();
+    self.status.switch_player();
+    record
+  }
+
+  fn unmake(&mut self, record: Record) {
+    self.status.switch_player();
+    let white = self.player();
+    let m = record.m;
+    let (from, to) = (m.from as usize, m.to as usize);
+    let moved = match m.promotion {
+      Some(_) => ChessCell::Piece(Piece::Pawn, white),
+      None => self.cell(to),
+    };
+    self.board.set(to, ChessCell::Empty);
+    self.board.set(from, moved);
+    if let Some((square, c)) = record.captured {
+      self.board.set(square, c);
+    }
+    if moved.is(Piece::King, white) && (to as i32 - from as i32).abs() == 2 {
+      let (rook_from, rook_to) = castling_rook(from, to);
+      self.board.set(rook_from, self.cell(rook_to));
+      self.board.set(rook_to, ChessCell::Empty);
+    }
+    self.castling = record.castling;
+    self.en_passant = record.en_passant;
+    self.halfmove = record.halfmove;
+    self.hash = record.hash;
+  }
+
+  fn legal_moves(&self) -> Vec<ChessMove> {
+    let white = self.player();
+    let king = self.king_square(white);
+    let mut moves = self.pseudo_moves(white);
+    moves.retain(|&m| self.is_legal(m, king));
+    moves
+  }
+
+  fn has_legal_moves(&self) -> bool {
+    let white = self.player();
+    let king = self.king_square(white);
+    self.pseudo_moves(white).into_iter().any(|m| self.is_legal(m, king))
+  }
+
+  fn is_repetition(&self) -> bool {
+    // Only the positions since the last irreversible move may repeat.
+    let since =
+      (self.hashes.len() - 1).saturating_sub(self.halfmove as usize);
+    let count = self.hashes[since..]
+      .iter()
+      .filter(|&&h| h == self.hash)
+      .count();
+    count >= 3
+  }
+}
+
+impl State for ChessState {
+  type Move = ChessMove;
+
+  fn player(&self) -> bool {
+    self.status.player()
+  }
+
+  fn is_terminal(&self) -> bool {
+    self.status.is_terminal()
+  }
+
+  fn payoff(&self) -> Option<f32> {
+    self.status.payoff()
+  }
+
+  fn iter_moves<'s>(&'s self) -> Box<Iterator<Item = ChessMove> + 's> {
+    if self.is_terminal() {
+      return Box::new(None.into_iter());
+    }
+    Box::new(self.legal_moves().into_iter())
+  }
+
+  fn get_random_move<R: Rng>(&self, rng: &mut R) -> Option<ChessMove> {
+    let moves: Vec<_> = self.iter_moves().collect();
+    moves.choose(rng).cloned()
+  }
+
+  fn play(&mut self, m: ChessMove) -> Result<(), &'static str> {
+    if self.is_terminal() {
+      return Err("Trying to make a move in a terminal state.");
+    }
+    let white = self.player();
+    if !self.pseudo_moves(white).contains(&m)
+      || !self.is_legal(m, self.king_square(white))
+    {
+      return Err("Illegal move.");
+    }
+    let record = self.make(m);
+    self.history.push(record);
+    self.hashes.push(self.hash);
+
+    if !self.has_legal_moves() {
+      if self.in_check(!white) {
+        self.status.set_winner(white);
+      } else {
+        self.status.set_draw();
+      }
+    } else if self.halfmove >= 100 || self.is_repetition() {
+      self.status.set_draw();
+    }
+
+    Ok(())
+  }
+
+  fn undo(&mut self, m: ChessMove) -> Result<(), &'static str> {
+    match self.history.last() {
+      Some(record) if record.m == m => {}
+      _ => return Err("Can't undo move"),
+    }
+    let record = self.history.pop().unwrap();
+    self.hashes.pop();
+    self.status.undo_terminal();
+    self.unmake(record);
+    Ok(())
+  }
+
+  fn parse_move(&self, move_str: &str) -> Result<ChessMove, &'static str> {
+    let s = move_str.to_lowercase();
+    if !s.is_ascii() || s.len() < 4 || s.len() > 5 {
+      return Err("Error parsing chess move.");
+    }
+    let from = self.board.parse_point(&s[0..2]);
+    let to = self.board.parse_point(&s[2..4]);
+    let promotion = match s[4..].chars().next() {
+      None => None,
+      Some(c) => match Piece::from_letter(c) {
+        Some(Piece::Pawn) | Some(Piece::King) | None => {
+          return Err("Error parsing chess move.")
+        }
+        piece => piece,
+      },
+    };
+    let m = match (from, to) {
+      (Some(from), Some(to)) => ChessMove::new(from, to, promotion),
+      _ => return Err("Error parsing chess move."),
+    };
+    if self.iter_moves().any(|legal| legal == m) {
+      Ok(m)
+    } else {
+      Err("Illegal move.")
+    }
+  }
+
+  fn hash(&self) -> Option<u64> {
+    Some(self.hash)
+  }
+}
+
+impl fmt::Display for ChessState {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.board.format(false))
+  }
+}
+
+/// Features from the point of view of the acting player: bias, the material
+/// of the player and of the opponent (pawn 1, knight and bishop 3, rook 5,
+/// queen 9), and the numbers of their moves (mobility), not checking whether
+/// the moves leave the king in check.
+#[derive(Clone)]
+pub struct ChessFeatureExtractor {}
+
+impl ChessFeatureExtractor {
+  pub fn new() -> Self {
+    ChessFeatureExtractor {}
+  }
+}
+
+impl FeatureExtractor<ChessState> for ChessFeatureExtractor {
+  fn nfeatures(&self) -> usize {
+    5
+  }
+
+  fn extract(&self, state: &ChessState) -> Vec<f32> {
+    let white = state.player();
+    let material = |white| {
+      state
+        .board
+        .iter()
+        .map(|&c| match c {
+          ChessCell::Piece(piece, w) if w == white => piece.value(),
+          _ => 0.0,
+        })
+        .sum::<f32>()
+    };
+
+    vec![
+      1.0,
+      material(white),
+      material(!white),
+      state.pseudo_moves(white).len() as f32,
+      state.pseudo_moves(!white).len() as f32,
+    ]
+  }
+
+  fn spec(&self) -> FeatureExtractorSpec {
+    FeatureExtractorSpec::Chess
+  }
+
+  fn report<R: Regression>(&self, regression: &R) {
+    let b = regression.params();
+    println!("bias: {:>6.3}", b[0]);
+    println!("material: self {:>6.3}, other {:>6.3}", b[1], b[2]);
+    println!("mobility: self {:>6.3}, other {:>6.3}\n", b[3], b[4]);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use rand::FromEntropy;
+  use rand::rngs::SmallRng;
+
+  use super::*;
+
+  const KIWIPETE: &str =
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+  fn perft(state: &mut ChessState, depth: u32) -> u64 {
+    if depth == 0 {
+      return 1;
+    }
+    let moves = state.legal_moves();
+    if depth == 1 {
+      return moves.len() as u64;
+    }
+    moves
+      .into_iter()
+      .map(|m| {
+        let record = state.make(m);
+        let nodes = perft(state, depth - 1);
+        state.unmake(record);
+        nodes
+      })
+      .sum()
+  }
+
+  fn check_perft(fen: &str, expected: &[u64]) {
+    let mut state = ChessState::from_fen(fen).unwrap();
+    let hash = state.hash;
+    for (depth, &nodes) in expected.iter().enumerate() {
+      assert_eq!(nodes, perft(&mut state, depth as u32 + 1), "{}", fen);
+    }
+    assert_eq!(hash, state.hash);
+  }
+
+  fn play(state: &mut ChessState, moves: &str) {
+    for m in moves.split_whitespace() {
+      let m = state.parse_move(m).unwrap();
+      state.play(m).unwrap();
+    }
+  }
+
+  #[test]
+  fn perft_start() {
+    check_perft(START_FEN, &[20, 400, 8902]);
+  }
+
+  #[test]
+  fn perft_kiwipete() {
+    check_perft(KIWIPETE, &[48, 2039, 97862]);
+  }
+
+  #[test]
+  fn perft_endgame() {
+    check_perft("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+                &[14, 191, 2812]);
+  }
+
+  #[test]
+  fn perft_promotions() {
+    check_perft(
+      "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+      &[6, 264, 9467],
+    );
+    check_perft(
+      "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+      &[44, 1486],
+    );
+  }
+
+  #[test]
+  fn fen_errors() {
+    assert!(ChessState::from_fen("8/8/8/8/8/8/8/8 w - -").is_err());
+    assert!(ChessState::from_fen("4k3/8/8/8/8/8/8/4K3 x - -").is_err());
+    assert!(ChessState::from_fen("4k3/8/8/8/8/8/8/4K2 w - -").is_err());
+    assert!(ChessState::from_fen("4k3/8/8/8/8/8/8/4K3 w X -").is_err());
+    // Black is in check, but it's white's turn.
+    assert!(ChessState::from_fen("4k3/8/8/8/8/8/8/4R1K1 w - -").is_err());
+    assert!(ChessState::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").is_ok());
+  }
+
+  #[test]
+  fn castling() {
+    let mut state = ChessState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq -")
+      .unwrap();
+    play(&mut state, "e1g1");
+    assert!(state.cell(5).is(Piece::Rook, true));
+    assert!(state.cell(6).is(Piece::King, true));
+    play(&mut state, "e8c8");
+    assert!(state.cell(59).is(Piece::Rook, false));
+    assert_eq!(0, state.castling);
+
+    // The king may not castle out of, through or into check.
+    let state = ChessState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq -")
+      .unwrap();
+    let mut through = state.clone();
+    through.put(45, ChessCell::Piece(Piece::Rook, false)); // f6
+    assert!(through.parse_move("e1g1").is_err());
+    assert!(through.parse_move("e1c1").is_ok());
+    let mut into = state.clone();
+    into.put(42, ChessCell::Piece(Piece::Rook, false)); // c6
+    assert!(into.parse_move("e1c1").is_err());
+    // b1 may be attacked, the king doesn't pass it.
+    let mut b_file = state.clone();
+    b_file.put(41, ChessCell::Piece(Piece::Rook, false)); // b6
+    assert!(b_file.parse_move("e1c1").is_ok());
+    let mut check = state.clone();
+    check.put(44, ChessCell::Piece(Piece::Rook, false)); // e6
+    assert!(check.parse_move("e1g1").is_err());
+  }
+
+  #[test]
+  fn en_passant() {
+    let mut state = Chess::new().new_game();
+    play(&mut state, "e2e4 a7a6 e4e5");
+    // No black pawn can capture, so the square is not recorded.
+    play(&mut state, "b7b5");
+    assert_eq!(None, state.en_passant);
+    play(&mut state, "a2a3 d7d5");
+    assert_eq!(state.board.parse_point("d6"), state.en_passant);
+    let hash = state.hash();
+    play(&mut state, "e5d6");
+    assert_eq!(ChessCell::Empty, state.cell(35)); // d5
+    let m = state.history.last().unwrap().m;
+    assert_eq!("e5d6", m.to_string());
+    state.undo(m).unwrap();
+    assert!(state.cell(35).is(Piece::Pawn, false));
+    assert_eq!(hash, state.hash());
+  }
+
+  #[test]
+  fn promotion() {
+    let mut state = ChessState::from_fen("8/4P3/8/8/8/k7/8/K7 w - -").unwrap();
+    assert!(state.parse_move("e7e8").is_err());
+    assert!(state.parse_move("e7e8k").is_err());
+    play(&mut state, "e7e8n");
+    assert!(state.cell(60).is(Piece::Knight, true));
+    assert_eq!("e7e8n", state.history[0].m.to_string());
+  }
+
+  #[test]
+  fn checkmate() {
+    let mut state = Chess::new().new_game();
+    play(&mut state, "f2f3 e7e5 g2g4");
+    assert!(!state.is_terminal());
+    play(&mut state, "d8h4");
+    assert_eq!(Some(-1.0), state.payoff());
+    assert_eq!(0, state.iter_moves().count());
+  }
+
+  #[test]
+  fn stalemate() {
+    let mut state = ChessState::from_fen("k7/8/1Q6/8/8/8/8/7K w - -").unwrap();
+    play(&mut state, "b6d6");
+    assert!(!state.is_terminal());
+    let mut state = ChessState::from_fen("k7/8/8/8/3Q4/8/8/7K w - -").unwrap();
+    play(&mut state, "d4b6");
+    assert_eq!(Some(0.0), state.payoff());
+  }
+
+  #[test]
+  fn fifty_moves() {
+    let mut state =
+      ChessState::from_fen("k7/8/8/8/8/8/8/1R5K w - - 98 80").unwrap();
+    play(&mut state, "b1c1");
+    assert!(!state.is_terminal());
+    play(&mut state, "a8b8");
+    assert_eq!(Some(0.0), state.payoff());
+  }
+
+  #[test]
+  fn repetition() {
+    let mut state = Chess::new().new_game();
+    play(&mut state, "g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1");
+    assert!(!state.is_terminal());
+    play(&mut state, "f6g8");
+    assert_eq!(Some(0.0), state.payoff());
+  }
+
+  #[test]
+  fn random_games() {
+    let mut rng = SmallRng::from_entropy();
+    let game = Chess::new();
+    for _ in 0..3 {
+      let mut state = game.new_game();
+      let mut moves = Vec::new();
+      while let Some(m) = state.get_random_move(&mut rng) {
+        state.play(m).unwrap();
+        moves.push(m);
+      }
+      assert!(state.payoff().is_some());
+      for &m in moves.iter().rev() {
+        state.undo(m).unwrap();
+      }
+      assert_eq!(game.new_game().hash(), state.hash());
+      assert_eq!(game.new_game().board.iter().collect::<Vec<_>>(),
+                 state.board.iter().collect::<Vec<_>>());
+    }
+  }
+
+  #[test]
+  fn features() {
+    let mut state = Chess::new().new_game();
+    let extractor = ChessFeatureExtractor::new();
+    assert_eq!(
+      vec![1.0, 39.0, 39.0, 20.0, 20.0],
+      extractor.extract(&state)
+    );
+    play(&mut state, "e2e4");
+    assert_eq!(
+      vec![1.0, 39.0, 39.0, 20.0, 30.0],
+      extractor.extract(&state)
+    );
+  }
+
+}  // mod test
@@ -0,0 +1,458 @@
+use lazy_static::lazy_static;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+use crate::board::{col_letter, Board, Cell};
+use crate::def::{FeatureExtractor, Game, Regression, State};
+use crate::games::cached_instance;
+use crate::spec::FeatureExtractorSpec;
+use crate::status::Status;
+use crate::zobrist;
+
+// Number of stones in a row needed to win.
+const WIN_LEN: u32 = 4;
+
+// Directions of the lines: horizontal, vertical and two diagonals.
+const DIRECTIONS: [(i32, i32); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+lazy_static! {
+  static ref INSTANCE_7_6: ConnectFour = ConnectFour::new(7, 6);
+  static ref INSTANCES: Mutex<HashMap<(u32, u32), &'static ConnectFour>> =
+    Mutex::new(HashMap::new());
+}
+
+pub struct ConnectFour {
+  width: u32,
+  height: u32,
+}
+
+impl ConnectFour {
+  pub fn new(width: u32, height: u32) -> ConnectFour {
+    assert!(width >= 1 && height >= 1);
+    ConnectFour { width, height }
+  }
+
+  pub fn default(width: u32, height: u32) -> &'static ConnectFour {
+    match (width, height) {
+      (7, 6) => &*INSTANCE_7_6,
+      _ => cached_instance(&INSTANCES, (width, height), || {
+        ConnectFour::new(width, height)
+      }),
+    }
+  }
+}
+
+impl Game for ConnectFour {
+  type State = ConnectFourState;
+
+  fn new_game(&self) -> ConnectFourState {
+    ConnectFourState::new(self.width, self.height)
+  }
+}
+
+/// A move is the column, in which the stone is dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ConnectFourMove(pub u32);
+
+impl fmt::Display for ConnectFourMove {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", col_letter(self.0))
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct ConnectFourState {
+  board: Board<ConnectFourCell>,
+  // Number of stones in each column.
+  heights: Vec<u32>,
+  status: Status,
+  hash: u64,
+}
+
+impl ConnectFourState {
+  fn new(width: u32, height: u32) -> ConnectFourState {
+    ConnectFourState {
+      board: Board::new_empty(width, height),
+      heights: vec![0; width as usize],
+      status: Status::new(),
+      hash: 0,
+    }
+  }
+
+  fn cell(&self, x: i32, y: i32) -> Option<ConnectFourCell> {
+    if x < 0 || y < 0 {
+      return None;
+    }
+    let (x, y) = (x as u32, y as u32);
+    if x >= self.board.width || y >= self.board.height {
+      return None;
+    }
+    self.board.get_xy(x, y)
+  }
+
+  // Whether the stone at (x, y) is a part of a line of `WIN_LEN` stones.
+  fn is_win(&self, x: u32, y: u32) -> bool {
+    let c = self.board.get_xy(x, y).unwrap();
+    let (x, y) = (x as i32, y as i32);
+    for &(dx, dy) in DIRECTIONS.iter() {
+      let mut len = 1;
+      for &sign in [-1, 1].iter() {
+        let mut i = 1;
+        while self.cell(x + sign * i * dx, y + sign * i * dy) == Some(c) {
+          len += 1;
+          i += 1;
+        }
+      }
+      if len >= WIN_LEN {
+        return true;
+      }
+    }
+    false
+  }
+
+  // Number of cells, that are still empty.
+  fn empty_cells(&self) -> u32 {
+    self.board.width * self.board.height - self.heights.iter().sum::<u32>()
+  }
+}
+
+impl State for ConnectFourState {
+  type Move = ConnectFourMove;
+
+  fn player(&self) -> bool {
+    self.status.player()
+  }
+
+  fn is_terminal(&self) -> bool {
+    self.status.is_terminal()
+  }
+
+  fn payoff(&self) -> Option<f32> {
+    self.status.payoff()
+  }
+
+  fn iter_moves<'s>(&'s self) -> Box<Iterator<Item = ConnectFourMove> + 's> {
+    if self.is_terminal() {
+      return Box::new(None.into_iter());
+    }
+    let height = self.board.height;
+    Box::new(
+      self
+        .heights
+        .iter()
+        .enumerate()
+        .filter(move |&(_, &h)| h < height)
+        .map(|(x, _)| ConnectFourMove(x as u32)),
+    )
+  }
+
+  fn get_random_move<R: Rng>(&self, rng: &mut R) -> Option<ConnectFourMove> {
+    let moves: Vec<_> = self.iter_moves().collect();
+    moves.choose(rng).cloned()
+  }
+
+  fn play(&mut self, m: ConnectFourMove) -> Result<(), &'static str> {
+    if self.is_terminal() {
+      return Err("Trying to make a move in a terminal state.");
+    }
+    let ConnectFourMove(x) = m;
+    if x >= self.board.width {
+      return Err("Column is outside of the board.");
+    }
+    let y = self.heights[x as usize];
+    if y >= self.board.height {
+      return Err("Column is full.");
+    }
+
+    let player = self.player();
+    self.board.set_xy(x, y, ConnectFourCell::player(player));
+    self.heights[x as usize] += 1;
+    self.hash ^= zobrist::point_key(self.board.xy_to_point(x, y), player)
+      ^ zobrist::player_key();
+
+    if self.is_win(x, y) {
+      self.status.set_winner(player);
+    } else if self.empty_cells() == 0 {
+      self.status.set_draw();
+    }
+    self.status.switch_player();
+
+    Ok(())
+  }
+
+  fn undo(&mut self, m: ConnectFourMove) -> Result<(), &'static str> {
+    let ConnectFourMove(x) = m;
+    if x >= self.board.width || self.heights[x as usize] == 0 {
+      return Err("Can't undo move");
+    }
+    let y = self.heights[x as usize] - 1;
+    let player = !self.player();
+    if self.board.get_xy(x, y) != Some(ConnectFourCell::player(player)) {
+      return Err("Can't undo move");
+    }
+
+    self.board.set_xy(x, y, ConnectFourCell::Empty);
+    self.heights[x as usize] -= 1;
+    self.hash ^= zobrist::point_key(self.board.xy_to_point(x, y), player)
+      ^ zobrist::player_key();
+    self.status.undo_terminal();
+    self.status.switch_player();
+
+    Ok(())
+  }
+
+  fn parse_move(&self, move_str: &str) -> Result<ConnectFourMove, &'static str> {
+    let x = (0..self.board.width)
+      .find(|&x| col_letter(x).to_string() == move_str.to_lowercase())
+      .ok_or("Error parsing Connect Four move.")?;
+    if self.heights[x as usize] >= self.board.height {
+      return Err("Column is full.");
+    }
+    Ok(ConnectFourMove(x))
+  }
+
+  fn hash(&self) -> Option<u64> {
+    Some(self.hash)
+  }
+}
+
+impl fmt::Display for ConnectFourState {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.board.format(false))
+  }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ConnectFourCell {
+  Empty,
+  Red,
+  Yellow,
+}
+
+impl ConnectFourCell {
+  fn player(player: bool) -> ConnectFourCell {
+    if player {
+      ConnectFourCell::Red
+    } else {
+      ConnectFourCell::Yellow
+    }
+  }
+}
+
+impl Cell for ConnectFourCell {
+  fn empty() -> ConnectFourCell {
+    ConnectFourCell::Empty
+  }
+
+  fn is_empty(self) -> bool {
+    self == ConnectFourCell::Empty
+  }
+
+  fn ascii(self) -> char {
+    match self {
+      ConnectFourCell::Empty => '.',
+      ConnectFourCell::Red => 'X',
+      ConnectFourCell::Yellow => 'O',
+    }
+  }
+
+  fn unicode(self) -> char {
+    match self {
+      ConnectFourCell::Empty => '·',
+      ConnectFourCell::Red => '●',
+      ConnectFourCell::Yellow => '○',
+    }
+  }
+}
+
+/// Counts the threats: the windows of `WIN_LEN` cells in a row, containing
+/// the stones of only one player. The features are, from the point of view
+/// of the acting player:
+///  - bias,
+///  - numbers of the player's windows with 1, 2 and 3 stones,
+///  - numbers of the opponent's windows with 1, 2 and 3 stones,
+///  - numbers of the player's and the opponent's windows with 3 stones, where
+///    the remaining cell can be played immediately.
+#[derive(Clone)]
+pub struct ConnectFourThreatExtractor {}
+
+impl ConnectFourThreatExtractor {
+  pub fn new() -> Self {
+    ConnectFourThreatExtractor {}
+  }
+}
+
+impl FeatureExtractor<ConnectFourState> for ConnectFourThreatExtractor {
+  fn nfeatures(&self) -> usize {
+    9
+  }
+
+  fn extract(&self, state: &ConnectFourState) -> Vec<f32> {
+    let player = ConnectFourCell::player(state.player());
+    let mut features = vec![0.0; 9];
+    features[0] = 1.0;
+
+    let (width, height) = (state.board.width as i32, state.board.height as i32);
+    let len = WIN_LEN as i32;
+    for y in 0..height {
+      for x in 0..width {
+        for &(dx, dy) in DIRECTIONS.iter() {
+          let end = (x + (len - 1) * dx, y + (len - 1) * dy);
+          if state.cell(end.0, end.1).is_none() {
+            continue;
+          }
+          let mut own = 0;
+          let mut other = 0;
+          let mut empty = (0, 0);
+          for i in 0..len {
+            let (cx, cy) = (x + i * dx, y + i * dy);
+            match state.cell(cx, cy).unwrap() {
+              ConnectFourCell::Empty => empty = (cx, cy),
+              c if c == player => own += 1,
+              _ => other += 1,
+            }
+          }
+          let playable = state.heights[empty.0 as usize] == empty.1 as u32;
+          if own > 0 && other == 0 {
+            features[own] += 1.0;
+            if own == 3 && playable {
+              features[7] += 1.0;
+            }
+          } else if other > 0 && own == 0 {
+            features[3 + other] += 1.0;
+            if other == 3 && playable {
+              features[8] += 1.0;
+            }
+          }
+        }
+      }
+    }
+
+    features
+  }
+
+  fn spec(&self) -> FeatureExtractorSpec {
+    FeatureExtractorSpec::ConnectFourThreats
+  }
+
+  fn report<R: Regression>(&self, regression: &R) {
+    let b = regression.params();
+    println!("bias: {:>6.3}", b[0]);
+    println!("self: {:>6.3} {:>6.3} {:>6.3}, immediate {:>6.3}",
+             b[1], b[2], b[3], b[7]);
+    println!("other: {:>6.3} {:>6.3} {:>6.3}, immediate {:>6.3}\n",
+             b[4], b[5], b[6], b[8]);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn play(state: &mut ConnectFourState, moves: &str) {
+    for m in moves.split_whitespace() {
+      let m = state.parse_move(m).unwrap();
+      state.play(m).unwrap();
+    }
+  }
+
+  #[test]
+  fn vertical_win() {
+    let game = ConnectFour::new(7, 6);
+    let mut state = game.new_game();
+    assert_eq!(7, state.iter_moves().count());
+
+    play(&mut state, "a b a b a b");
+    assert!(!state.is_terminal());
+    play(&mut state, "a");
+    assert!(state.is_terminal());
+    assert_eq!(Some(1.0), state.payoff());
+    assert_eq!(0, state.iter_moves().count());
+
+    state.undo(ConnectFourMove(0)).unwrap();
+    assert!(!state.is_terminal());
+    assert!(state.player());
+  }
+
+  #[test]
+  fn default_sizes() {
+    let game = ConnectFour::default(8, 7);
+    assert!(std::ptr::eq(game, ConnectFour::default(8, 7)));
+    let mut state = game.new_game();
+    assert_eq!(8, state.iter_moves().count());
+    play(&mut state, "h a h a h a");
+    assert!(!state.is_terminal());
+    play(&mut state, "h");
+    assert_eq!(Some(1.0), state.payoff());
+  }
+
+  #[test]
+  fn diagonal_win() {
+    let game = ConnectFour::new(7, 6);
+    let mut state = game.new_game();
+    // X: a1 b2 c2 c3 d3 d4, O: b1 c1 d1 d2 g1.
+    play(&mut state, "a b b c c d c d d g");
+    assert!(!state.is_terminal());
+    play(&mut state, "d");
+    assert_eq!(Some(1.0), state.payoff());
+  }
+
+  #[test]
+  fn full_column() {
+    let game = ConnectFour::new(4, 2);
+    let mut state = game.new_game();
+    play(&mut state, "a a");
+    assert!(state.parse_move("a").is_err());
+    assert!(state.play(ConnectFourMove(0)).is_err());
+    assert_eq!(3, state.iter_moves().count());
+  }
+
+  #[test]
+  fn draw() {
+    let game = ConnectFour::new(4, 2);
+    let mut state = game.new_game();
+    play(&mut state, "a b c d b a d c");
+    assert!(state.is_terminal());
+    assert_eq!(Some(0.0), state.payoff());
+  }
+
+  #[test]
+  fn hash() {
+    let game = ConnectFour::new(7, 6);
+    let mut state1 = game.new_game();
+    let mut state2 = game.new_game();
+    play(&mut state1, "a b c");
+    play(&mut state2, "c b a");
+    assert_eq!(state1.hash(), state2.hash());
+
+    let hash = state1.hash();
+    play(&mut state1, "d");
+    assert_ne!(hash, state1.hash());
+    state1.undo(ConnectFourMove(3)).unwrap();
+    assert_eq!(hash, state1.hash());
+  }
+
+  #[test]
+  fn threats() {
+    let game = ConnectFour::new(7, 6);
+    let mut state = game.new_game();
+    let extractor = ConnectFourThreatExtractor::new();
+    // a1 is in 3 windows: horizontal, vertical and diagonal.
+    play(&mut state, "a");
+    assert_eq!(
+      vec![1.0, 0.0, 0.0, 0.0, 3.0, 0.0, 0.0, 0.0, 0.0],
+      extractor.extract(&state)
+    );
+
+    // X: a1 b1 c1, O: a2 b2. X threatens d1, and it's O's turn.
+    play(&mut state, "a b b c");
+    let features = extractor.extract(&state);
+    assert_eq!(1.0, features[6]);
+    assert_eq!(1.0, features[8]);
+    assert_eq!(0.0, features[3]);
+  }
+
+}  // mod test
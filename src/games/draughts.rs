@@ -0,0 +1,836 @@
+//! English and Russian draughts on the 8x8 board.
+//!
+//! The first player starts on the rows 1-3. Captures are mandatory, and a
+//! capture with several jumps is a single move, that has to be completed
+//! until no more pieces can be taken. Captured pieces are removed after the
+//! move, so that they can't be jumped twice.
+//!
+//! In English draughts men move and capture only forward, kings move one
+//! square in any direction, and a man, that reaches the last row during a
+//! capture, is crowned and the move ends. In Russian draughts men also
+//! capture backward, kings fly over any number of empty squares, and a man
+//! crowned during a capture goes on capturing as a king.
+//!
+//! A player without legal moves loses. The game is a draw after the same
+//! position occurs three times, or after a number of moves without captures
+//! and without moves of men (40 moves of each player in English, 15 in
+//! Russian draughts).
+
+use lazy_static::lazy_static;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::fmt;
+
+use crate::board::{point_to_a, Board, Cell};
+use crate::def::{FeatureExtractor, Game, Regression, State};
+use crate::spec::FeatureExtractorSpec;
+use crate::status::Status;
+use crate::zobrist;
+
+const SIZE: u32 = 8;
+
+// A piece may capture at most all the 12 pieces of the opponent.
+const MAX_PATH: usize = 13;
+
+const DIAGONALS: [(i32, i32); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+lazy_static! {
+  static ref ENGLISH_INSTANCE: Draughts = Draughts::new_english();
+  static ref RUSSIAN_INSTANCE: Draughts = Draughts::new_russian();
+}
+
+pub struct Draughts {
+  russian: bool,
+  // Number of plies without captures and moves of men, that end the game
+  // with a draw.
+  move_limit: u32,
+}
+
+impl Draughts {
+  pub fn new_english() -> Draughts {
+    Draughts {
+      russian: false,
+      move_limit: 80,
+    }
+  }
+
+  pub fn new_russian() -> Draughts {
+    Draughts {
+      russian: true,
+      move_limit: 30,
+    }
+  }
+
+  pub fn english() -> &'static Draughts {
+    &*ENGLISH_INSTANCE
+  }
+
+  pub fn russian() -> &'static Draughts {
+    &*RUSSIAN_INSTANCE
+  }
+}
+
+impl Game for Draughts {
+  type State = DraughtsState;
+
+  fn new_game(&self) -> DraughtsState {
+    DraughtsState::new(self.russian, self.move_limit)
+  }
+}
+
+/// A move is the path of the piece: the start point and the points, where it
+/// stops. For a capture every stop follows a jump over a piece of the
+/// opponent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DraughtsMove {
+  path: [u8; MAX_PATH],
+  len: u8,
+  capture: bool,
+}
+
+impl DraughtsMove {
+  fn new(path: &[usize], capture: bool) -> DraughtsMove {
+    let mut m = DraughtsMove {
+      path: [0; MAX_PATH],
+      len: path.len() as u8,
+      capture,
+    };
+    for (i, &p) in path.iter().enumerate() {
+      m.path[i] = p as u8;
+    }
+    m
+  }
+
+  fn path(&self) -> impl Iterator<Item = usize> + '_ {
+    self.path[..self.len as usize].iter().map(|&p| p as usize)
+  }
+
+  fn from(&self) -> usize {
+    self.path[0] as usize
+  }
+
+  fn to(&self) -> usize {
+    self.path[self.len as usize - 1] as usize
+  }
+}
+
+impl fmt::Display for DraughtsMove {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let separator = if self.capture { "x" } else { "-" };
+    let points: Vec<String> =
+      self.path().map(|p| point_to_a(p, SIZE)).collect();
+    write!(f, "{}", points.join(separator))
+  }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum DraughtsCell {
+  Empty,
+  Man(bool),
+  King(bool),
+}
+
+impl DraughtsCell {
+  fn player(self) -> Option<bool> {
+    match self {
+      DraughtsCell::Empty => None,
+      DraughtsCell::Man(player) | DraughtsCell::King(player) => Some(player),
+    }
+  }
+
+  fn is_king(self) -> bool {
+    matches!(self, DraughtsCell::King(_))
+  }
+
+  // Kings are hashed as stones on a second board after the first one.
+  fn key(self, point: usize) -> u64 {
+    match self {
+      DraughtsCell::Empty => 0,
+      DraughtsCell::Man(player) => zobrist::point_key(point, player),
+      DraughtsCell::King(player) => {
+        zobrist::point_key(point + (SIZE * SIZE) as usize, player)
+      }
+    }
+  }
+}
+
+impl Cell for DraughtsCell {
+  fn empty() -> DraughtsCell {
+    DraughtsCell::Empty
+  }
+
+  fn is_empty(self) -> bool {
+    self == DraughtsCell::Empty
+  }
+
+  fn ascii(self) -> char {
+    match self {
+      DraughtsCell::Empty => '.',
+      DraughtsCell::Man(true) => 'x',
+      DraughtsCell::King(true) => 'X',
+      DraughtsCell::Man(false) => 'o',
+      DraughtsCell::King(false) => 'O',
+    }
+  }
+
+  fn unicode(self) -> char {
+    match self {
+      DraughtsCell::Empty => '·',
+      DraughtsCell::Man(true) => '⛂',
+      DraughtsCell::King(true) => '⛃',
+      DraughtsCell::Man(false) => '⛀',
+      DraughtsCell::King(false) => '⛁',
+    }
+  }
+}
+
+fn forward(player: bool) -> i32 {
+  if player { 1 } else { -1 }
+}
+
+fn last_row(player: bool) -> u32 {
+  if player { SIZE - 1 } else { 0 }
+}
+
+fn cell(board: &Board<DraughtsCell>, x: i32, y: i32) -> Option<DraughtsCell> {
+  if x < 0 || y < 0 || x >= SIZE as i32 || y >= SIZE as i32 {
+    return None;
+  }
+  board.get_xy(x as u32, y as u32)
+}
+
+// What is needed to undo a move.
+#[derive(Clone, Debug)]
+struct Record {
+  m: DraughtsMove,
+  captured: Vec<(usize, DraughtsCell)>,
+  promoted: bool,
+  quiet: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct DraughtsState {
+  russian: bool,
+  move_limit: u32,
+  board: Board<DraughtsCell>,
+  status: Status,
+  history: Vec<Record>,
+  // Plies since the last capture or move of a man.
+  quiet: u32,
+  // Hashes of all the positions of the game, including the current one.
+  hashes: Vec<u64>,
+  hash: u64,
+}
+
+impl DraughtsState {
+  fn new(russian: bool, move_limit: u32) -> DraughtsState {
+    let mut state = DraughtsState {
+      russian,
+      move_limit,
+      board: Board::new_empty(SIZE, SIZE),
+      status: Status::new(),
+      history: Vec::new(),
+      quiet: 0,
+      hashes: Vec::new(),
+      hash: 0,
+    };
+    for y in 0..SIZE {
+      for x in 0..SIZE {
+        if (x + y) % 2 != 0 {
+          continue;
+        }
+        let point = state.board.xy_to_point(x, y);
+        if y < 3 {
+          state.put(point, DraughtsCell::Man(true));
+        } else if y >= SIZE - 3 {
+          state.put(point, DraughtsCell::Man(false));
+        }
+      }
+    }
+    state.hashes.push(state.hash);
+    state
+  }
+
+  fn put(&mut self, point: usize, c: DraughtsCell) {
+    let old = self.board.get(point).unwrap();
+    self.hash ^= old.key(point) ^ c.key(point);
+    self.board.set(point, c);
+  }
+
+  // All the single jumps of `piece` from `from`, as pairs of the captured
+  // point and the landing point. The pieces at `captured` are already taken
+  // in this move. A flying king has to land, where it can go on capturing,
+  // if there is such a point, unless `any_landing` is set.
+  fn jumps(
+    &self,
+    board: &Board<DraughtsCell>,
+    piece: DraughtsCell,
+    from: usize,
+    captured: &[usize],
+    any_landing: bool,
+  ) -> Vec<(usize, usize)> {
+    let player = piece.player().unwrap();
+    let flying = self.russian && piece.is_king();
+    let (x, y) = board.point_to_xy(from);
+    let mut jumps = Vec::new();
+    for &(dx, dy) in DIAGONALS.iter() {
+      if !self.russian && !piece.is_king() && dy != forward(player) {
+        continue;
+      }
+      let (mut cx, mut cy) = (x as i32 + dx, y as i32 + dy);
+      while flying && cell(board, cx, cy) == Some(DraughtsCell::Empty) {
+        cx += dx;
+        cy += dy;
+      }
+      match cell(board, cx, cy) {
+        Some(c) if c.player() == Some(!player) => {}
+        _ => continue,
+      }
+      let victim = board.xy_to_point(cx as u32, cy as u32);
+      if captured.contains(&victim) {
+        continue;
+      }
+
+      let mut landings = Vec::new();
+      let (mut lx, mut ly) = (cx + dx, cy + dy);
+      while cell(board, lx, ly) == Some(DraughtsCell::Empty) {
+        landings.push(board.xy_to_point(lx as u32, ly as u32));
+        if !flying {
+          break;
+        }
+        lx += dx;
+        ly += dy;
+      }
+      if flying && !any_landing {
+        let mut taken = captured.to_vec();
+        taken.push(victim);
+        let going_on: Vec<usize> = landings
+          .iter()
+          .cloned()
+          .filter(|&l| !self.jumps(board, piece, l, &taken, true).is_empty())
+          .collect();
+        if !going_on.is_empty() {
+          landings = going_on;
+        }
+      }
+      jumps.extend(landings.into_iter().map(|l| (victim, l)));
+    }
+    jumps
+  }
+
+  // Adds to `moves` all the captures, that continue the jumps in `path`.
+  fn extend_captures(
+    &self,
+    board: &Board<DraughtsCell>,
+    piece: DraughtsCell,
+    path: &mut Vec<usize>,
+    captured: &mut Vec<usize>,
+    moves: &mut Vec<DraughtsMove>,
+  ) {
+    let player = piece.player().unwrap();
+    let from = *path.last().unwrap();
+    let jumps = self.jumps(board, piece, from, captured, false);
+    if jumps.is_empty() {
+      if !captured.is_empty() {
+        moves.push(DraughtsMove::new(path, true));
+      }
+      return;
+    }
+    for (victim, landing) in jumps {
+      let crowned = !piece.is_king()
+        && board.point_to_xy(landing).1 == last_row(player);
+      path.push(landing);
+      captured.push(victim);
+      if crowned && !self.russian {
+        moves.push(DraughtsMove::new(path, true));
+      } else {
+        let piece = if crowned { DraughtsCell::King(player) } else { piece };
+        self.extend_captures(board, piece, path, captured, moves);
+      }
+      path.pop();
+      captured.pop();
+    }
+  }
+
+  fn captures(&self, from: usize) -> Vec<DraughtsMove> {
+    let piece = self.board.get(from).unwrap();
+    // The moving piece doesn't block its own way.
+    let mut board = self.board.clone();
+    board.set(from, DraughtsCell::Empty);
+    let mut moves = Vec::new();
+    self.extend_captures(
+      &board,
+      piece,
+      &mut vec![from],
+      &mut Vec::new(),
+      &mut moves,
+    );
+    moves
+  }
+
+  fn simple_moves(&self, from: usize) -> Vec<DraughtsMove> {
+    let piece = self.board.get(from).unwrap();
+    let player = piece.player().unwrap();
+    let flying = self.russian && piece.is_king();
+    let (x, y) = self.board.point_to_xy(from);
+    let mut moves = Vec::new();
+    for &(dx, dy) in DIAGONALS.iter() {
+      if !piece.is_king() && dy != forward(player) {
+        continue;
+      }
+      let (mut cx, mut cy) = (x as i32 + dx, y as i32 + dy);
+      while cell(&self.board, cx, cy) == Some(DraughtsCell::Empty) {
+        let to = self.board.xy_to_point(cx as u32, cy as u32);
+        moves.push(DraughtsMove::new(&[from, to], false));
+        if !flying {
+          break;
+        }
+        cx += dx;
+        cy += dy;
+      }
+    }
+    moves
+  }
+
+  fn legal_moves(&self) -> Vec<DraughtsMove> {
+    let player = self.player();
+    let pieces: Vec<usize> = (0..self.board.len())
+      .filter(|&p| self.board.get(p).unwrap().player() == Some(player))
+      .collect();
+    let captures: Vec<DraughtsMove> =
+      pieces.iter().flat_map(|&p| self.captures(p)).collect();
+    if !captures.is_empty() {
+      return captures;
+    }
+    pieces.iter().flat_map(|&p| self.simple_moves(p)).collect()
+  }
+
+  fn is_repetition(&self) -> bool {
+    // Only the positions since the last irreversible move may repeat.
+    let since = self.hashes.len() - 1 - self.quiet as usize;
+    let count = self.hashes[since..]
+      .iter()
+      .filter(|&&h| h == self.hash)
+      .count();
+    count >= 3
+  }
+}
+
+impl State for DraughtsState {
+  type Move = DraughtsMove;
+
+  fn player(&self) -> bool {
+    self.status.player()
+  }
+
+  fn is_terminal(&self) -> bool {
+    self.status.is_terminal()
+  }
+
+  fn payoff(&self) -> Option<f32> {
+    self.status.payoff()
+  }
+
+  fn iter_moves<'s>(&'s self) -> Box<Iterator<Item = DraughtsMove> + 's> {
+    if self.is_terminal() {
+      return Box::new(None.into_iter());
+    }
+    Box::new(self.legal_moves().into_iter())
+  }
+
+  fn get_random_move<R: Rng>(&self, rng: &mut R) -> Option<DraughtsMove> {
+    let moves: Vec<_> = self.iter_moves().collect();
+    moves.choose(rng).cloned()
+  }
+
+  fn play(&mut self, m: DraughtsMove) -> Result<(), &'static str> {
+    if self.is_terminal() {
+      return Err("Trying to make a move in a terminal state.");
+    }
+    if !self.legal_moves().contains(&m) {
+      return Err("Illegal move.");
+    }
+    let player = self.player();
+    let piece = self.board.get(m.from()).unwrap();
+    self.put(m.from(), DraughtsCell::Empty);
+
+    let mut captured = Vec::new();
+    if m.capture {
+      let path: Vec<usize> = m.path().collect();
+      for jump in path.windows(2) {
+        let (x0, y0) = self.board.point_to_xy(jump[0]);
+        let (x1, y1) = self.board.point_to_xy(jump[1]);
+        let dx = if x1 > x0 { 1 } else { -1 };
+        let dy = if y1 > y0 { 1 } else { -1 };
+        let (mut x, mut y) = (x0 as i32 + dx, y0 as i32 + dy);
+        while cell(&self.board, x, y) == Some(DraughtsCell::Empty) {
+          x += dx;
+          y += dy;
+        }
+        let victim = self.board.xy_to_point(x as u32, y as u32);
+        captured.push((victim, self.board.get(victim).unwrap()));
+      }
+      for &(victim, _) in captured.iter() {
+        self.put(victim, DraughtsCell::Empty);
+      }
+    }
+
+    let promoted = !piece.is_king()
+      && m
+        .path()
+        .any(|p| self.board.point_to_xy(p).1 == last_row(player));
+    let piece = if promoted { DraughtsCell::King(player) } else { piece };
+    self.put(m.to(), piece);
+
+    self.history.push(Record {
+      m,
+      captured,
+      promoted,
+      quiet: self.quiet,
+    });
+    if m.capture || !piece.is_king() || promoted {
+      self.quiet = 0;
+    } else {
+      self.quiet += 1;
+    }
+    self.hash ^= zobrist::player_key();
+    self.hashes.push(self.hash);
+    self.status.switch_player();
+
+    if self.legal_moves().is_empty() {
+      self.status.set_winner(player);
+    } else if self.quiet >= self.move_limit || self.is_repetition() {
+      self.status.set_draw();
+    }
+
+    Ok(())
+  }
+
+  fn undo(&mut self, m: DraughtsMove) -> Result<(), &'static str> {
+    match self.history.last() {
+      Some(record) if record.m == m => {}
+      _ => return Err("Can't undo move"),
+    }
+    let record = self.history.pop().unwrap();
+    let player = !self.player();
+
+    let piece = self.board.get(m.to()).unwrap();
+    self.put(m.to(), DraughtsCell::Empty);
+    for &(victim, c) in record.captured.iter() {
+      self.put(victim, c);
+    }
+    let piece = if record.promoted { DraughtsCell::Man(player) } else { piece };
+    self.put(m.from(), piece);
+
+    self.quiet = record.quiet;
+    self.hash ^= zobrist::player_key();
+    self.hashes.pop();
+    self.status.undo_terminal();
+    self.status.switch_player();
+
+    Ok(())
+  }
+
+  fn parse_move(&self, move_str: &str) -> Result<DraughtsMove, &'static str> {
+    let capture = move_str.contains('x');
+    let path = move_str
+      .split(&['x', '-'][..])
+      .map(|a| self.board.parse_point(a).ok_or("Error parsing draughts move."))
+      .collect::<Result<Vec<usize>, _>>()?;
+    if path.len() < 2 || path.len() > MAX_PATH {
+      return Err("Error parsing draughts move.");
+    }
+    let m = DraughtsMove::new(&path, capture);
+    if self.iter_moves().any(|legal| legal == m) {
+      Ok(m)
+    } else {
+      Err("Illegal move.")
+    }
+  }
+
+  fn hash(&self) -> Option<u64> {
+    Some(self.hash)
+  }
+}
+
+impl fmt::Display for DraughtsState {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.board.format(false))
+  }
+}
+
+/// Features from the point of view of the acting player: bias, numbers of
+/// the player's and the opponent's men and kings, and the total advancement
+/// of their men, i.e. the number of rows the men have moved forward.
+#[derive(Clone)]
+pub struct DraughtsFeatureExtractor {}
+
+impl DraughtsFeatureExtractor {
+  pub fn new() -> Self {
+    DraughtsFeatureExtractor {}
+  }
+}
+
+impl FeatureExtractor<DraughtsState> for DraughtsFeatureExtractor {
+  fn nfeatures(&self) -> usize {
+    7
+  }
+
+  fn extract(&self, state: &DraughtsState) -> Vec<f32> {
+    let player = state.player();
+    let count = |c| state.board.iter().filter(|&&x| x == c).count() as f32;
+    let advancement = |player| {
+      (0..state.board.len())
+        .filter(|&p| state.board.get(p) == Some(DraughtsCell::Man(player)))
+        .map(|p| {
+          let y = state.board.point_to_xy(p).1;
+          if player { y } else { SIZE - 1 - y }
+        })
+        .sum::<u32>() as f32
+    };
+
+    vec![
+      1.0,
+      count(DraughtsCell::Man(player)),
+      count(DraughtsCell::Man(!player)),
+      count(DraughtsCell::King(player)),
+      count(DraughtsCell::King(!player)),
+      advancement(player),
+      advancement(!player),
+    ]
+  }
+
+  fn spec(&self) -> FeatureExtractorSpec {
+    FeatureExtractorSpec::Draughts
+  }
+
+  fn report<R: Regression>(&self, regression: &R) {
+    let b = regression.params();
+    println!("bias: {:>6.3}", b[0]);
+    println!("men: self {:>6.3}, other {:>6.3}", b[1], b[2]);
+    println!("kings: self {:>6.3}, other {:>6.3}", b[3], b[4]);
+    println!("advancement: self {:>6.3}, other {:>6.3}\n", b[5], b[6]);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use rand::FromEntropy;
+  use rand::rngs::SmallRng;
+
+  use super::*;
+
+  fn play(state: &mut DraughtsState, moves: &str) {
+    for m in moves.split_whitespace() {
+      let m = state.parse_move(m).unwrap();
+      state.play(m).unwrap();
+    }
+  }
+
+  // Undoes the move, that is no longer legal in the position.
+  fn undo(state: &mut DraughtsState, s: &str) {
+    let path: Vec<usize> = s
+      .split(&['x', '-'][..])
+      .map(|a| state.board.parse_point(a).unwrap())
+      .collect();
+    state.undo(DraughtsMove::new(&path, s.contains('x'))).unwrap();
+  }
+
+  fn sorted_moves(state: &DraughtsState) -> Vec<String> {
+    let mut moves: Vec<String> =
+      state.iter_moves().map(|m| m.to_string()).collect();
+    moves.sort();
+    moves
+  }
+
+  // A position with only the given pieces: lowercase for men, uppercase for
+  // kings, 'x' for the first player and 'o' for the second one.
+  fn custom_state(
+    game: &Draughts,
+    pieces: &[(&str, char)],
+    player: bool,
+  ) -> DraughtsState {
+    let mut state = game.new_game();
+    for p in 0..state.board.len() {
+      state.put(p, DraughtsCell::Empty);
+    }
+    for &(a, c) in pieces {
+      let point = state.board.parse_point(a).unwrap();
+      let piece = match c {
+        'x' => DraughtsCell::Man(true),
+        'X' => DraughtsCell::King(true),
+        'o' => DraughtsCell::Man(false),
+        'O' => DraughtsCell::King(false),
+        _ => unreachable!(),
+      };
+      state.put(point, piece);
+    }
+    if !player {
+      state.status.switch_player();
+      state.hash ^= zobrist::player_key();
+    }
+    state.hashes = vec![state.hash];
+    state
+  }
+
+  #[test]
+  fn opening() {
+    let mut state = Draughts::english().new_game();
+    assert_eq!(
+      vec!["a3-b4", "c3-b4", "c3-d4", "e3-d4", "e3-f4", "g3-f4", "g3-h4"],
+      sorted_moves(&state)
+    );
+    let hash = state.hash();
+    play(&mut state, "c3-d4 f6-e5");
+    // The capture is mandatory.
+    assert_eq!(vec!["d4xf6"], sorted_moves(&state));
+    assert!(state.parse_move("a3-b4").is_err());
+    play(&mut state, "d4xf6");
+    assert_eq!(None, state.board.get_a("e5").unwrap().player());
+
+    for m in ["d4xf6", "f6-e5", "c3-d4"].iter() {
+      undo(&mut state, m);
+    }
+    assert_eq!(hash, state.hash());
+    assert_eq!(Some(DraughtsCell::Man(false)), state.board.get_a("f6"));
+  }
+
+  #[test]
+  fn multi_jump() {
+    let pieces = [("c3", 'x'), ("d4", 'o'), ("f6", 'o'), ("d6", 'o')];
+    let state = custom_state(Draughts::english(), &pieces, true);
+    assert_eq!(vec!["c3xe5xc7", "c3xe5xg7"], sorted_moves(&state));
+
+    // Men capture backward only in Russian draughts.
+    let pieces = [("e5", 'x'), ("d4", 'o'), ("h8", 'o')];
+    let state = custom_state(Draughts::english(), &pieces, true);
+    assert_eq!(vec!["e5-d6", "e5-f6"], sorted_moves(&state));
+    let state = custom_state(Draughts::russian(), &pieces, true);
+    assert_eq!(vec!["e5xc3"], sorted_moves(&state));
+  }
+
+  #[test]
+  fn promotion() {
+    // In English draughts the move ends with the promotion.
+    let pieces = [("b6", 'x'), ("c7", 'o'), ("e7", 'o'), ("h2", 'o')];
+    let mut state = custom_state(Draughts::english(), &pieces, true);
+    assert_eq!(vec!["b6xd8"], sorted_moves(&state));
+    play(&mut state, "b6xd8");
+    assert_eq!(Some(DraughtsCell::King(true)), state.board.get_a("d8"));
+    assert!(!state.is_terminal());
+
+    // In Russian draughts the new king goes on capturing.
+    state = custom_state(Draughts::russian(), &pieces, true);
+    assert_eq!(
+      vec!["b6xd8xf6", "b6xd8xg5", "b6xd8xh4"],
+      sorted_moves(&state)
+    );
+    play(&mut state, "b6xd8xf6");
+    assert_eq!(Some(DraughtsCell::King(true)), state.board.get_a("f6"));
+    undo(&mut state, "b6xd8xf6");
+    assert_eq!(Some(DraughtsCell::Man(true)), state.board.get_a("b6"));
+  }
+
+  #[test]
+  fn flying_king() {
+    let pieces = [("a1", 'X'), ("h8", 'o')];
+    let state = custom_state(Draughts::russian(), &pieces, true);
+    assert_eq!(6, state.iter_moves().count());
+    let state = custom_state(Draughts::english(), &pieces, true);
+    assert_eq!(vec!["a1-b2"], sorted_moves(&state));
+
+    // The king may land on e5, f6 or g7, but has to stop at f6 to capture
+    // the piece on e7.
+    let pieces = [("a1", 'X'), ("d4", 'o'), ("e7", 'o')];
+    let state = custom_state(Draughts::russian(), &pieces, true);
+    assert_eq!(vec!["a1xf6xd8"], sorted_moves(&state));
+    let pieces = [("a1", 'X'), ("d4", 'o'), ("a8", 'o')];
+    let state = custom_state(Draughts::russian(), &pieces, true);
+    assert_eq!(
+      vec!["a1xe5", "a1xf6", "a1xg7", "a1xh8"],
+      sorted_moves(&state)
+    );
+  }
+
+  #[test]
+  fn no_moves_lose() {
+    let pieces = [("c3", 'x'), ("d4", 'o')];
+    let mut state = custom_state(Draughts::english(), &pieces, true);
+    play(&mut state, "c3xe5");
+    assert_eq!(Some(1.0), state.payoff());
+
+    // A blocked man can't move.
+    let pieces = [("a1", 'x'), ("b2", 'o'), ("c3", 'o'), ("h8", 'o')];
+    let mut state = custom_state(Draughts::english(), &pieces, false);
+    play(&mut state, "h8-g7");
+    assert_eq!(Some(-1.0), state.payoff());
+  }
+
+  #[test]
+  fn repetition() {
+    let pieces = [("a1", 'X'), ("h8", 'O')];
+    let mut state = custom_state(Draughts::english(), &pieces, true);
+    play(&mut state, "a1-b2 h8-g7 b2-a1 g7-h8 a1-b2 h8-g7 b2-a1");
+    assert!(!state.is_terminal());
+    play(&mut state, "g7-h8");
+    assert_eq!(Some(0.0), state.payoff());
+    undo(&mut state, "g7-h8");
+    assert!(!state.is_terminal());
+  }
+
+  #[test]
+  fn move_limit() {
+    let pieces = [("c1", 'X'), ("h8", 'O'), ("h2", 'o')];
+    let mut state = custom_state(Draughts::russian(), &pieces, true);
+    play(&mut state, "c1-a3 h8-g7");
+    assert_eq!(2, state.quiet);
+
+    let mut state = custom_state(Draughts::russian(), &pieces, true);
+    state.quiet = 29;
+    play(&mut state, "c1-a3");
+    assert_eq!(Some(0.0), state.payoff());
+    // A move of a man resets the counter.
+    let mut state = custom_state(Draughts::russian(), &pieces, false);
+    state.quiet = 29;
+    play(&mut state, "h2-g1");
+    assert_eq!(0, state.quiet);
+    assert!(!state.is_terminal());
+  }
+
+  #[test]
+  fn random_games() {
+    let mut rng = SmallRng::from_entropy();
+    for game in [Draughts::english(), Draughts::russian()].iter() {
+      for _ in 0..10 {
+        let mut state = game.new_game();
+        let mut moves = Vec::new();
+        while let Some(m) = state.get_random_move(&mut rng) {
+          state.play(m).unwrap();
+          moves.push(m);
+        }
+        assert!(state.payoff().is_some());
+        for &m in moves.iter().rev() {
+          state.undo(m).unwrap();
+        }
+        assert_eq!(game.new_game().hash(), state.hash());
+        assert_eq!(1, state.hashes.len());
+      }
+    }
+  }
+
+  #[test]
+  fn features() {
+    let mut state = Draughts::english().new_game();
+    let extractor = DraughtsFeatureExtractor::new();
+    assert_eq!(
+      vec![1.0, 12.0, 12.0, 0.0, 0.0, 12.0, 12.0],
+      extractor.extract(&state)
+    );
+    play(&mut state, "c3-d4");
+    assert_eq!(
+      vec![1.0, 12.0, 12.0, 0.0, 0.0, 12.0, 13.0],
+      extractor.extract(&state)
+    );
+  }
+
+}  // mod test
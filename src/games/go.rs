@@ -0,0 +1,633 @@
+//! Go with the Tromp-Taylor rules: positional superko, no suicide, and area
+//! scoring with komi. The game ends after two consecutive passes.
+//!
+//! Random play in Go can go on for very long, so `get_random_move` never
+//! fills an eye of the player, and the game also ends, scored as it is, after
+//! `3 * size * size` moves.
+
+use lazy_static::lazy_static;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+use crate::board::{col_letter, Board, Cell};
+use crate::def::{Game, State};
+use crate::games::cached_instance;
+use crate::status::Status;
+use crate::zobrist;
+
+pub const DEFAULT_KOMI: f32 = 7.5;
+
+lazy_static! {
+  static ref INSTANCE_5: Go = Go::new(5, DEFAULT_KOMI);
+  static ref INSTANCE_9: Go = Go::new(9, DEFAULT_KOMI);
+  static ref INSTANCE_13: Go = Go::new(13, DEFAULT_KOMI);
+  static ref INSTANCE_19: Go = Go::new(19, DEFAULT_KOMI);
+  static ref INSTANCES: Mutex<HashMap<u32, &'static Go>> =
+    Mutex::new(HashMap::new());
+}
+
+/// Black is the first player, the komi is added to the score of white.
+pub struct Go {
+  size: u32,
+  komi: f32,
+}
+
+impl Go {
+  pub fn new(size: u32, komi: f32) -> Go {
+    assert!((5..=19).contains(&size), "Go board size must be 5 to 19.");
+    Go { size, komi }
+  }
+
+  pub fn default(size: u32) -> &'static Go {
+    match size {
+      5 => &*INSTANCE_5,
+      9 => &*INSTANCE_9,
+      13 => &*INSTANCE_13,
+      19 => &*INSTANCE_19,
+      _ => cached_instance(&INSTANCES, size, || Go::new(size, DEFAULT_KOMI)),
+    }
+  }
+}
+
+impl Game for Go {
+  type State = GoState;
+
+  fn new_game(&self) -> GoState {
+    GoState::new(self.size, self.komi)
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GoMove {
+  Place(u32, u32),
+  Pass,
+}
+
+#[derive(Clone, Debug)]
+struct Record {
+  m: GoMove,
+  // The stones of the opponent removed by the move.
+  captured: Vec<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GoState {
+  board: Board<GoCell>,
+  status: Status,
+  komi: f32,
+  history: Vec<Record>,
+  // The hash of the stones on the board, without the player to move.
+  board_hash: u64,
+  // How many times each board position has occurred, for the superko.
+  positions: HashMap<u64, u32>,
+}
+
+impl GoState {
+  fn new(size: u32, komi: f32) -> GoState {
+    let mut positions = HashMap::new();
+    positions.insert(0, 1);
+    GoState {
+      board: Board::new_empty(size, size),
+      status: Status::new(),
+      komi,
+      history: Vec::new(),
+      board_hash: 0,
+      positions,
+    }
+  }
+
+  fn max_moves(&self) -> usize {
+    3 * self.board.len()
+  }
+
+  fn cell(&self, point: usize) -> GoCell {
+    self.board.get(point).unwrap()
+  }
+
+  fn neighbours(&self, point: usize) -> Vec<usize> {
+    let (x, y) = self.board.point_to_xy(point);
+    let (width, height) = (self.board.width, self.board.height);
+    let mut neighbours = Vec::with_capacity(4);
+    if x > 0 {
+      neighbours.push(point - 1);
+    }
+    if x + 1 < width {
+      neighbours.push(point + 1);
+    }
+    if y > 0 {
+      neighbours.push(point - width as usize);
+    }
+    if y + 1 < height {
+      neighbours.push(point + width as usize);
+    }
+    neighbours
+  }
+
+  // The stones of the group at the point and the number of its liberties.
+  fn group(&self, point: usize) -> (Vec<usize>, usize) {
+    let color = self.cell(point);
+    let mut visited = vec![false; self.board.len()];
+    let mut stones = vec![point];
+    let mut liberties = 0;
+    visited[point] = true;
+    let mut i = 0;
+    while i < stones.len() {
+      for n in self.neighbours(stones[i]) {
+        if visited[n] {
+          continue;
+        }
+        visited[n] = true;
+        match self.cell(n) {
+          GoCell::Empty => liberties += 1,
+          c if c == color => stones.push(n),
+          _ => {}
+        }
+      }
+      i += 1;
+    }
+    (stones, liberties)
+  }
+
+  // The stones captured by placing a stone of the player to move at the point,
+  // or None if the placement is illegal.
+  fn try_place(&self, point: usize) -> Option<Vec<usize>> {
+    if self.board.get(point) != Some(GoCell::Empty) {
+      return None;
+    }
+    let own = GoCell::player(self.player());
+    let mut captured = Vec::new();
+    let mut has_liberty = false;
+    for n in self.neighbours(point) {
+      match self.cell(n) {
+        GoCell::Empty => has_liberty = true,
+        c if c == own => {
+          // The placed stone takes one liberty of the group.
+          if self.group(n).1 > 1 {
+            has_liberty = true;
+          }
+        }
+        _ => {
+          if !captured.contains(&n) {
+            let (stones, liberties) = self.group(n);
+            if liberties == 1 {
+              captured.extend(stones);
+            }
+          }
+        }
+      }
+    }
+    if !has_liberty && captured.is_empty() {
+      return None;
+    }
+
+    let player = self.player();
+    let hash = captured.iter().fold(
+      self.board_hash ^ zobrist::point_key(point, player),
+      |hash, &p| hash ^ zobrist::point_key(p, !player),
+    );
+    if self.positions.contains_key(&hash) {
+      return None;
+    }
+    Some(captured)
+  }
+
+  fn set_stone(&mut self, point: usize, cell: GoCell) {
+    if let GoCell::Stone(player) = self.cell(point) {
+      self.board_hash ^= zobrist::point_key(point, player);
+    }
+    if let GoCell::Stone(player) = cell {
+      self.board_hash ^= zobrist::point_key(point, player);
+    }
+    self.board.set(point, cell);
+  }
+
+  // Whether the point is an eye of the player: all the neighbours are the
+  // player's stones, and the opponent doesn't hold enough diagonal points to
+  // make the eye false.
+  fn is_eye(&self, point: usize, player: bool) -> bool {
+    let own = GoCell::player(player);
+    if self.cell(point) != GoCell::Empty
+      || self.neighbours(point).iter().any(|&n| self.cell(n) != own)
+    {
+      return false;
+    }
+    let (x, y) = self.board.point_to_xy(point);
+    let (x, y) = (x as i32, y as i32);
+    let size = self.board.width as i32;
+    let mut diagonals = 0;
+    let mut opponent = 0;
+    for &(dx, dy) in [(1, 1), (1, -1), (-1, 1), (-1, -1)].iter() {
+      let (cx, cy) = (x + dx, y + dy);
+      if cx < 0 || cy < 0 || cx >= size || cy >= size {
+        continue;
+      }
+      diagonals += 1;
+      if self.board.get_xy(cx as u32, cy as u32)
+        == Some(GoCell::player(!player))
+      {
+        opponent += 1;
+      }
+    }
+    if diagonals == 4 {
+      opponent < 2
+    } else {
+      opponent == 0
+    }
+  }
+
+  /// The area of black minus the area of white and the komi. The area of a
+  /// player are the stones and the empty points reaching only the stones of
+  /// the player.
+  pub fn score(&self) -> f32 {
+    let mut score = 0;
+    let mut visited = vec![false; self.board.len()];
+    for point in 0..self.board.len() {
+      match self.cell(point) {
+        GoCell::Stone(true) => score += 1,
+        GoCell::Stone(false) => score -= 1,
+        GoCell::Empty if !visited[point] => {
+          let mut region = vec![point];
+          let (mut black, mut white) = (false, false);
+          visited[point] = true;
+          let mut i = 0;
+          while i < region.len() {
+            for n in self.neighbours(region[i]) {
+              match self.cell(n) {
+                GoCell::Stone(true) => black = true,
+                GoCell::Stone(false) => white = true,
+                GoCell::Empty if !visited[n] => {
+                  visited[n] = true;
+                  region.push(n);
+                }
+                GoCell::Empty => {}
+              }
+            }
+            i += 1;
+          }
+          match (black, white) {
+            (true, false) => score += region.len() as i32,
+            (false, true) => score -= region.len() as i32,
+            _ => {}
+          }
+        }
+        GoCell::Empty => {}
+      }
+    }
+    score as f32 - self.komi
+  }
+
+  // Number of consecutive passes at the end of the game.
+  fn passes(&self) -> usize {
+    self
+      .history
+      .iter()
+      .rev()
+      .take_while(|r| r.m == GoMove::Pass)
+      .count()
+  }
+
+  fn update_status(&mut self) {
+    if self.passes() >= 2 || self.history.len() >= self.max_moves() {
+      let score = self.score();
+      if score == 0.0 {
+        self.status.set_draw();
+      } else {
+        self.status.set_winner(score > 0.0);
+      }
+    }
+  }
+}
+
+impl State for GoState {
+  type Move = GoMove;
+
+  fn player(&self) -> bool {
+    self.status.player()
+  }
+
+  fn is_terminal(&self) -> bool {
+    self.status.is_terminal()
+  }
+
+  fn payoff(&self) -> Option<f32> {
+    self.status.payoff()
+  }
+
+  fn iter_moves<'s>(&'s self) -> Box<Iterator<Item = GoMove> + 's> {
+    if self.is_terminal() {
+      return Box::new(None.into_iter());
+    }
+    Box::new(
+      (0..self.board.len())
+        .filter(move |&p| self.try_place(p).is_some())
+        .map(move |p| {
+          let (x, y) = self.board.point_to_xy(p);
+          GoMove::Place(x, y)
+        })
+        .chain(Some(GoMove::Pass)),
+    )
+  }
+
+  /// A random legal placement, that doesn't fill an eye of the player, or a
+  /// pass, if there is none.
+  fn get_random_move<R: Rng>(&self, rng: &mut R) -> Option<GoMove> {
+    if self.is_terminal() {
+      return None;
+    }
+    let player = self.player();
+    let mut points: Vec<usize> = (0..self.board.len())
+      .filter(|&p| self.cell(p) == GoCell::Empty)
+      .collect();
+    points.shuffle(rng);
+    let point = points
+      .into_iter()
+      .find(|&p| !self.is_eye(p, player) && self.try_place(p).is_some());
+    Some(point.map_or(GoMove::Pass, |p| {
+      let (x, y) = self.board.point_to_xy(p);
+      GoMove::Place(x, y)
+    }))
+  }
+
+  fn play(&mut self, m: GoMove) -> Result<(), &'static str> {
+    if self.is_terminal() {
+      return Err("Trying to make a move in a terminal state.");
+    }
+    let player = self.player();
+    let captured = match m {
+      GoMove::Pass => Vec::new(),
+      GoMove::Place(x, y) => {
+        if x >= self.board.width || y >= self.board.height {
+          return Err("Move is outside of the board.");
+        }
+        let point = self.board.xy_to_point(x, y);
+        let captured = self.try_place(point).ok_or("Illegal move.")?;
+        self.set_stone(point, GoCell::player(player));
+        for &p in captured.iter() {
+          self.set_stone(p, GoCell::Empty);
+        }
+        *self.positions.entry(self.board_hash).or_insert(0) += 1;
+        captured
+      }
+    };
+    self.history.push(Record { m, captured });
+    self.status.switch_player();
+    self.update_status();
+
+    Ok(())
+  }
+
+  fn undo(&mut self, m: GoMove) -> Result<(), &'static str> {
+    match self.history.last() {
+      Some(record) if record.m == m => {}
+      _ => return Err("Can't undo move"),
+    }
+    let record = self.history.pop().unwrap();
+    self.status.undo_terminal();
+    self.status.switch_player();
+    if let GoMove::Place(x, y) = m {
+      let point = self.board.xy_to_point(x, y);
+      let count = self.positions.get_mut(&self.board_hash).unwrap();
+      *count -= 1;
+      if *count == 0 {
+        self.positions.remove(&self.board_hash);
+      }
+      let opponent = GoCell::player(!self.player());
+      self.set_stone(point, GoCell::Empty);
+      for &p in record.captured.iter() {
+        self.set_stone(p, opponent);
+      }
+    }
+
+    Ok(())
+  }
+
+  fn parse_move(&self, move_str: &str) -> Result<GoMove, &'static str> {
+    let m = if move_str.to_lowercase() == "pass" {
+      GoMove::Pass
+    } else {
+      let point = self
+        .board
+        .parse_point(move_str)
+        .ok_or("Error parsing Go move.")?;
+      let (x, y) = self.board.point_to_xy(point);
+      GoMove::Place(x, y)
+    };
+    if self.is_terminal() {
+      return Err("Trying to make a move in a terminal state.");
+    }
+    match m {
+      GoMove::Place(x, y)
+        if self.try_place(self.board.xy_to_point(x, y)).is_none() =>
+      {
+        Err("Illegal move.")
+      }
+      _ => Ok(m),
+    }
+  }
+
+  fn hash(&self) -> Option<u64> {
+    let mut hash = self.board_hash;
+    if !self.player() {
+      hash ^= zobrist::player_key();
+    }
+    // After a pass, another pass ends the game.
+    match self.passes() {
+      0 => {}
+      1 => hash ^= zobrist::flag_key(0),
+      _ => hash ^= zobrist::flag_key(1),
+    }
+    Some(hash)
+  }
+}
+
+impl fmt::Display for GoState {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.board.format(false))
+  }
+}
+
+impl fmt::Display for GoMove {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      GoMove::Place(x, y) => write!(f, "{}{}", col_letter(*x), y + 1),
+      GoMove::Pass => write!(f, "pass"),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum GoCell {
+  Empty,
+  // Black if set.
+  Stone(bool),
+}
+
+impl GoCell {
+  fn player(player: bool) -> GoCell {
+    GoCell::Stone(player)
+  }
+}
+
+impl Cell for GoCell {
+  fn empty() -> GoCell {
+    GoCell::Empty
+  }
+
+  fn is_empty(self) -> bool {
+    self == GoCell::Empty
+  }
+
+  fn ascii(self) -> char {
+    match self {
+      GoCell::Empty => '.',
+      GoCell::Stone(true) => 'X',
+      GoCell::Stone(false) => 'O',
+    }
+  }
+
+  fn unicode(self) -> char {
+    match self {
+      GoCell::Empty => '·',
+      GoCell::Stone(true) => '●',
+      GoCell::Stone(false) => '○',
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use rand::FromEntropy;
+  use rand::rngs::SmallRng;
+
+  use super::*;
+
+  fn play(state: &mut GoState, moves: &str) {
+    for m in moves.split_whitespace() {
+      let m = state.parse_move(m).unwrap();
+      state.play(m).unwrap();
+    }
+  }
+
+  #[test]
+  fn capture() {
+    let mut state = Go::new(5, 0.5).new_game();
+    play(&mut state, "b1 a1 c3 pass");
+    let hash = state.hash();
+    play(&mut state, "a2");
+    assert_eq!(GoCell::Empty, state.board.get_a("a1").unwrap());
+    assert_eq!(vec![0], state.history.last().unwrap().captured);
+
+    state.undo(GoMove::Place(0, 1)).unwrap();
+    assert_eq!(GoCell::Stone(false), state.board.get_a("a1").unwrap());
+    assert_eq!(hash, state.hash());
+  }
+
+  #[test]
+  fn hash_passes() {
+    // The same stones with black to move, with and without a pending pass.
+    let mut state1 = Go::new(5, 0.5).new_game();
+    play(&mut state1, "b1 c3");
+    let mut state2 = Go::new(5, 0.5).new_game();
+    play(&mut state2, "pass c3 b1 pass");
+    assert_eq!(state1.board_hash, state2.board_hash);
+    assert!(state1.player() && state2.player());
+    assert_ne!(state1.hash(), state2.hash());
+
+    let hash = state2.hash();
+    play(&mut state2, "pass");
+    assert!(state2.is_terminal());
+    state2.undo(GoMove::Pass).unwrap();
+    assert_eq!(hash, state2.hash());
+  }
+
+  #[test]
+  fn default_sizes() {
+    for size in 5..=19 {
+      let game = Go::default(size);
+      assert!(std::ptr::eq(game, Go::default(size)));
+      // All the points and a pass.
+      assert_eq!(size * size + 1, game.new_game().iter_moves().count() as u32);
+    }
+  }
+
+  #[test]
+  fn suicide() {
+    let mut state = Go::new(5, 0.5).new_game();
+    play(&mut state, "b1 pass a2");
+    assert!(state.parse_move("a1").is_err());
+    assert!(!state.iter_moves().any(|m| m == GoMove::Place(0, 0)));
+
+    // Taking the last liberty is fine, when it captures.
+    let mut state = Go::new(5, 0.5).new_game();
+    play(&mut state, "b1 c1 a2 b2 pass a1");
+    assert_eq!(GoCell::Empty, state.board.get_a("b1").unwrap());
+  }
+
+  #[test]
+  fn superko() {
+    let mut state = Go::new(5, 0.5).new_game();
+    // The ko around b2 and c2. Black takes at c2.
+    play(&mut state, "b1 c1 a2 b2 b3 c3 pass d2 c2");
+    assert_eq!(GoCell::Empty, state.board.get_a("b2").unwrap());
+    // Retaking at once would repeat the position.
+    assert!(state.parse_move("b2").is_err());
+    // After an exchange elsewhere the position is new.
+    play(&mut state, "e5 e4 b2");
+    assert_eq!(GoCell::Empty, state.board.get_a("c2").unwrap());
+  }
+
+  #[test]
+  fn scoring() {
+    let mut state = Go::new(5, 0.5).new_game();
+    // Black walls off the c column, white the d column.
+    play(&mut state, "c1 d1 c2 d2 c3 d3 c4 d4 c5 d5 pass");
+    assert!(!state.is_terminal());
+    play(&mut state, "pass");
+    // Black has 15 points, white 10 and the komi.
+    assert_eq!(4.5, state.score());
+    assert_eq!(Some(1.0), state.payoff());
+    assert_eq!(0, state.iter_moves().count());
+
+    state.undo(GoMove::Pass).unwrap();
+    assert!(!state.is_terminal());
+    let mut state = Go::new(5, 5.0).new_game();
+    play(&mut state, "c1 d1 c2 d2 c3 d3 c4 d4 c5 d5 pass pass");
+    assert_eq!(Some(0.0), state.payoff());
+  }
+
+  #[test]
+  fn eyes() {
+    let mut state = Go::new(5, 0.5).new_game();
+    play(&mut state, "b1 pass a2 pass b2");
+    assert!(state.is_eye(0, true));
+    assert!(!state.is_eye(0, false));
+    assert!(!state.is_eye(6, true));
+  }
+
+  #[test]
+  fn random_games() {
+    let mut rng = SmallRng::from_entropy();
+    let game = Go::new(9, DEFAULT_KOMI);
+    for _ in 0..5 {
+      let mut state = game.new_game();
+      let mut moves = Vec::new();
+      while let Some(m) = state.get_random_move(&mut rng) {
+        state.play(m).unwrap();
+        moves.push(m);
+      }
+      assert!(moves.len() <= state.max_moves());
+      let score = state.score();
+      assert_eq!(Some(score.signum()), state.payoff());
+
+      for &m in moves.iter().rev() {
+        state.undo(m).unwrap();
+      }
+      assert_eq!(game.new_game().hash(), state.hash());
+      assert_eq!(1, state.positions.len());
+    }
+  }
+
+}  // mod test
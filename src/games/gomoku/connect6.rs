@@ -0,0 +1,339 @@
+//! Connect6 on the 19x19 board.
+//!
+//! Black places one stone on the first turn, and then the players place two
+//! stones per turn. Six or more stones in a row win. The rules of placing the
+//! stones and detecting the lines are those of free-style Gomoku with the win
+//! length of six.
+
+use lazy_static::lazy_static;
+use rand::Rng;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::def::{FeatureExtractor, Game, Regression, State};
+use crate::spec::FeatureExtractorSpec;
+use super::gomoku::{Gomoku, GomokuState, PointState, BOARD_LEN, MAX_SIZE};
+use super::gomoku_move::GomokuMove;
+use super::line_features::GomokuLineFeatureExtractor;
+
+const WIN_LEN: u32 = 6;
+
+lazy_static! {
+  static ref CONNECT6_INSTANCE: Connect6 = Connect6::new();
+  static ref EXTRACTOR_INSTANCES: Vec<Connect6LineFeatureExtractor> = vec![
+    Connect6LineFeatureExtractor::new(1),
+    Connect6LineFeatureExtractor::new(2),
+    Connect6LineFeatureExtractor::new(3),
+  ];
+}
+
+pub struct Connect6 {
+  gomoku: Gomoku,
+}
+
+impl Connect6 {
+  pub fn new() -> Connect6 {
+    Connect6 {
+      gomoku: Gomoku::new_sized(MAX_SIZE, WIN_LEN),
+    }
+  }
+
+  pub fn default() -> &'static Connect6 {
+    &*CONNECT6_INSTANCE
+  }
+}
+
+impl Game for Connect6 {
+  type State = Connect6State;
+
+  fn new_game(&self) -> Connect6State {
+    Connect6State {
+      gomoku: self.gomoku.new_game(),
+    }
+  }
+}
+
+/// The stones placed in one turn. The second stone is missing only on the
+/// first turn, or if there is a single empty point left. The stones are kept
+/// in the order of the points, so that a pair has a single representation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Connect6Move(GomokuMove, Option<GomokuMove>);
+
+impl Connect6Move {
+  pub fn new(first: GomokuMove, second: Option<GomokuMove>) -> Connect6Move {
+    match second {
+      Some(second) if second.0 < first.0 => {
+        Connect6Move(second, Some(first))
+      }
+      _ => Connect6Move(first, second),
+    }
+  }
+
+  fn points(&self) -> Vec<usize> {
+    let mut points = vec![(self.0).0];
+    if let Some(second) = self.1 {
+      points.push(second.0);
+    }
+    points
+  }
+}
+
+impl fmt::Display for Connect6Move {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self.1 {
+      Some(second) => write!(f, "{} {}", self.0, second),
+      None => write!(f, "{}", self.0),
+    }
+  }
+}
+
+#[derive(Clone)]
+pub struct Connect6State {
+  gomoku: GomokuState,
+}
+
+impl Connect6State {
+  fn empty_points(&self) -> Vec<usize> {
+    (0..BOARD_LEN)
+      .filter(|&p| {
+        self.gomoku.gomoku().on_board(p)
+          && self.gomoku.board[p] == PointState::Empty
+      })
+      .collect()
+  }
+
+  // Number of the stones to be placed in this turn.
+  fn stones_per_turn(&self) -> usize {
+    if self.gomoku.board.iter().all(|&p| p == PointState::Empty) {
+      1
+    } else {
+      self.empty_points().len().min(2)
+    }
+  }
+}
+
+// Iterates over all the pairs of the empty points.
+struct PairIterator {
+  empty: Vec<usize>,
+  i: usize,
+  j: usize,
+}
+
+impl Iterator for PairIterator {
+  type Item = Connect6Move;
+
+  fn next(&mut self) -> Option<Connect6Move> {
+    if self.j >= self.empty.len() {
+      self.i += 1;
+      self.j = self.i + 1;
+    }
+    if self.j >= self.empty.len() {
+      return None;
+    }
+    let m = Connect6Move(
+      GomokuMove(self.empty[self.i]),
+      Some(GomokuMove(self.empty[self.j])),
+    );
+    self.j += 1;
+    Some(m)
+  }
+}
+
+impl State for Connect6State {
+  type Move = Connect6Move;
+
+  fn player(&self) -> bool {
+    self.gomoku.player()
+  }
+
+  fn is_terminal(&self) -> bool {
+    self.gomoku.is_terminal()
+  }
+
+  fn payoff(&self) -> Option<f32> {
+    self.gomoku.payoff()
+  }
+
+  fn iter_moves<'s>(&'s self) -> Box<Iterator<Item = Connect6Move> + 's> {
+    if self.is_terminal() {
+      return Box::new(None.into_iter());
+    }
+    let empty = self.empty_points();
+    if self.stones_per_turn() == 1 {
+      Box::new(
+        empty
+          .into_iter()
+          .map(|p| Connect6Move(GomokuMove(p), None)),
+      )
+    } else {
+      Box::new(PairIterator { empty, i: 0, j: 1 })
+    }
+  }
+
+  fn get_random_move<R: Rng>(&self, rng: &mut R) -> Option<Connect6Move> {
+    let first = self.gomoku.get_random_move(rng)?;
+    if self.stones_per_turn() == 1 {
+      return Some(Connect6Move(first, None));
+    }
+    loop {
+      let second = self.gomoku.get_random_move(rng)?;
+      if second != first {
+        return Some(Connect6Move::new(first, Some(second)));
+      }
+    }
+  }
+
+  fn play(&mut self, m: Connect6Move) -> Result<(), &'static str> {
+    if m.points().len() != self.stones_per_turn() {
+      return Err("Wrong number of stones.");
+    }
+    self.gomoku.play_stones(&m.points())
+  }
+
+  fn undo(&mut self, m: Connect6Move) -> Result<(), &'static str> {
+    self.gomoku.undo_stones(&m.points())
+  }
+
+  fn parse_move(&self, move_str: &str) -> Result<Connect6Move, &'static str> {
+    let stones = move_str
+      .split_whitespace()
+      .map(GomokuMove::from_str)
+      .collect::<Result<Vec<_>, _>>()?;
+    let m = match stones.as_slice() {
+      [first] => Connect6Move::new(*first, None),
+      [first, second] => Connect6Move::new(*first, Some(*second)),
+      _ => return Err("Error parsing Connect6 move."),
+    };
+    self.clone().play(m)?;
+    Ok(m)
+  }
+
+  fn hash(&self) -> Option<u64> {
+    self.gomoku.hash()
+  }
+}
+
+impl fmt::Display for Connect6State {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.gomoku)
+  }
+}
+
+/// The line features of `GomokuLineFeatureExtractor` with the lines of up to
+/// five stones.
+#[derive(Clone)]
+pub struct Connect6LineFeatureExtractor {
+  lines: GomokuLineFeatureExtractor,
+}
+
+impl Connect6LineFeatureExtractor {
+  pub fn new(min_len: usize) -> Connect6LineFeatureExtractor {
+    Connect6LineFeatureExtractor {
+      lines: GomokuLineFeatureExtractor::new_with_win_len(
+        min_len,
+        MAX_SIZE,
+        WIN_LEN as usize,
+      ),
+    }
+  }
+
+  pub fn default(min_len: usize) -> &'static Connect6LineFeatureExtractor {
+    &EXTRACTOR_INSTANCES[min_len - 1]
+  }
+}
+
+impl FeatureExtractor<Connect6State> for Connect6LineFeatureExtractor {
+  fn nfeatures(&self) -> usize {
+    self.lines.nfeatures()
+  }
+
+  fn extract(&self, state: &Connect6State) -> Vec<f32> {
+    self.lines.extract_lines(&state.gomoku)
+  }
+
+  fn spec(&self) -> FeatureExtractorSpec {
+    FeatureExtractorSpec::Connect6Lines(self.lines.min_len() as u32)
+  }
+
+  fn report<R: Regression>(&self, regression: &R) {
+    self.lines.report_lines(regression)
+  }
+}
+
+#[cfg(test)]
+mod test {
+
+  use rand::FromEntropy;
+  use rand::rngs::SmallRng;
+
+  use super::*;
+
+  fn play(state: &mut Connect6State, moves: &[&str]) {
+    for m in moves {
+      let m = state.parse_move(m).unwrap();
+      state.play(m).unwrap();
+    }
+  }
+
+  #[test]
+  fn turns() {
+    let mut state = Connect6::default().new_game();
+    assert_eq!(361, state.iter_moves().count());
+    assert!(state.parse_move("K10 L11").is_err());
+    play(&mut state, &["K10"]);
+    assert!(!state.player());
+    assert_eq!(360 * 359 / 2, state.iter_moves().count());
+    assert!(state.parse_move("L11").is_err());
+    assert!(state.parse_move("K10 L11").is_err());
+    assert!(state.parse_move("L11 L11").is_err());
+
+    let m = state.parse_move("L12 L11").unwrap();
+    assert_eq!(m, state.parse_move("L11 L12").unwrap());
+    assert_eq!("L11 L12", m.to_string());
+    state.play(m).unwrap();
+    assert!(state.player());
+  }
+
+  #[test]
+  fn six_wins() {
+    let mut state = Connect6::default().new_game();
+    play(&mut state, &["J10", "A1 A2", "K10 L10", "A3 A4", "M10 N10"]);
+    // Five in a row don't win.
+    play(&mut state, &["A5 A7"]);
+    assert!(!state.is_terminal());
+    play(&mut state, &["O10 C1"]);
+    assert_eq!(Some(1.0), state.payoff());
+    assert_eq!(0, state.iter_moves().count());
+  }
+
+  #[test]
+  fn random_games() {
+    let mut rng = SmallRng::from_entropy();
+    for _ in 0..5 {
+      let mut state = Connect6::default().new_game();
+      let mut moves = Vec::new();
+      while let Some(m) = state.get_random_move(&mut rng) {
+        state.play(m).unwrap();
+        moves.push(m);
+      }
+      assert!(state.payoff().is_some());
+      for &m in moves.iter().rev() {
+        state.undo(m).unwrap();
+      }
+      assert_eq!(Connect6::default().new_game().hash(), state.hash());
+    }
+  }
+
+  #[test]
+  fn features() {
+    let extractor = Connect6LineFeatureExtractor::default(1);
+    assert_eq!(41, extractor.nfeatures());
+    let mut state = Connect6::default().new_game();
+    play(&mut state, &["J10", "A1 A2", "K10 L10", "A3 C3", "M10 N10"]);
+    let features = extractor.extract(&state);
+    assert_eq!(1.0, features[40]);
+    let five = extractor.lines.encode(5, true, false, false).unwrap();
+    assert_eq!(1.0, features[five]);
+  }
+
+}  // mod test
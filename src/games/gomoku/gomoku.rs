@@ -1,35 +1,72 @@
 use lazy_static::lazy_static;
 use rand;
+use std::collections::HashMap;
 use std::fmt;
 use std::str::FromStr;
+use std::sync::Mutex;
 
 use crate::def::{Game, State};
+use crate::games::cached_instance;
 use super::gomoku_move::GomokuMove;
+use super::renju;
 use super::util;
 use crate::status::Status;
+use crate::zobrist;
 
-pub const SIZE: u32 = 19;
-pub const BOARD_LEN: usize = (SIZE as usize) * (SIZE as usize);
+/// The maximal size of the board. The points are numbered row by row with
+/// this stride on the boards of any size, so that the moves can be parsed and
+/// displayed without knowing the size.
+pub const MAX_SIZE: u32 = 19;
+pub const BOARD_LEN: usize = (MAX_SIZE as usize) * (MAX_SIZE as usize);
 
 lazy_static! {
   static ref GOMOKU_INSTANCE: Gomoku = Gomoku::new();
+  static ref GOMOKU_15_INSTANCE: Gomoku = Gomoku::new_sized(15, 5);
+  static ref GOMOKU_9_INSTANCE: Gomoku = Gomoku::new_sized(9, 5);
+  static ref TICTACTOE_INSTANCE: Gomoku = Gomoku::new_sized(3, 3);
+  static ref RENJU_INSTANCE: Gomoku = Gomoku::new_renju();
+  static ref SIZED_INSTANCES: Mutex<HashMap<(u32, u32), &'static Gomoku>> =
+    Mutex::new(HashMap::new());
 }
 
 #[derive(Clone, Copy)]
-struct LinesMargins {
-  delta: usize,
-  start: usize,
-  end: usize
+pub(super) struct LinesMargins {
+  pub delta: usize,
+  pub start: usize,
+  pub end: usize
 }
 
+/// Free-style Gomoku on a square board, where `win_len` or more stones in a
+/// row win, or Renju. In Renju black wins only with exactly five stones in a
+/// row and is not allowed to make double-threes, double-fours and overlines.
+#[derive(Clone, Copy, Debug)]
 pub struct Gomoku {
-  lines_margins: [[LinesMargins; 4]; BOARD_LEN]
+  size: u32,
+  win_len: u32,
+  renju: bool
 }
 
 impl Gomoku {
   pub fn new() -> Gomoku {
+    Gomoku::new_sized(MAX_SIZE, 5)
+  }
+
+  pub fn new_sized(size: u32, win_len: u32) -> Gomoku {
+    assert!(size <= MAX_SIZE);
+    assert!(win_len >= 1 && win_len <= size);
+    Gomoku {
+      size,
+      win_len,
+      renju: false
+    }
+  }
+
+  /// Renju on the standard 15x15 board.
+  pub fn new_renju() -> Gomoku {
     Gomoku {
-      lines_margins: Self::create_lines_margins()
+      size: 15,
+      win_len: 5,
+      renju: true
     }
   }
 
@@ -37,15 +74,76 @@ impl Gomoku {
     &*GOMOKU_INSTANCE
   }
 
-  fn move_till_margin(p: usize, dx: i32, dy: i32) -> usize {
+  pub fn default_sized(size: u32, win_len: u32) -> &'static Gomoku {
+    match (size, win_len) {
+      (19, 5) => &*GOMOKU_INSTANCE,
+      (15, 5) => &*GOMOKU_15_INSTANCE,
+      (9, 5) => &*GOMOKU_9_INSTANCE,
+      (3, 3) => &*TICTACTOE_INSTANCE,
+      _ => cached_instance(&SIZED_INSTANCES, (size, win_len), || {
+        Gomoku::new_sized(size, win_len)
+      }),
+    }
+  }
+
+  pub fn renju() -> &'static Gomoku {
+    &*RENJU_INSTANCE
+  }
+
+  pub fn size(&self) -> u32 {
+    self.size
+  }
+
+  pub fn win_len(&self) -> u32 {
+    self.win_len
+  }
+
+  /// Whether the point is on the board, rather than in the unused part of
+  /// the `MAX_SIZE` x `MAX_SIZE` numbering.
+  pub fn on_board(&self, point: usize) -> bool {
+    let (x, y) = util::point_to_xy(point);
+    point < BOARD_LEN && x < self.size && y < self.size
+  }
+
+  // The segments of the four lines through the point, that are scanned to
+  // find `win_len` in a row. In Renju they are one point longer on each side
+  // to tell five from an overline.
+  pub(super) fn lines_margins(&self, point: usize) -> [LinesMargins; 4] {
+    let reach = if self.renju { self.win_len } else { self.win_len - 1 };
+    let stride = MAX_SIZE as usize;
+    [
+      LinesMargins{
+        delta: 1,
+        start: self.move_till_margin(point, -1, 0, reach),
+        end: self.move_till_margin(point, 1, 0, reach)
+      },
+      LinesMargins{
+        delta: stride,
+        start: self.move_till_margin(point, 0, -1, reach),
+        end: self.move_till_margin(point, 0, 1, reach)
+      },
+      LinesMargins{
+        delta: stride - 1,
+        start: self.move_till_margin(point, 1, -1, reach),
+        end: self.move_till_margin(point, -1, 1, reach)
+      },
+      LinesMargins{
+        delta: stride + 1,
+        start: self.move_till_margin(point, -1, -1, reach),
+        end: self.move_till_margin(point, 1, 1, reach)
+      },
+    ]
+  }
+
+  fn move_till_margin(&self, p: usize, dx: i32, dy: i32, reach: u32) -> usize {
     let (xu, yu) = util::point_to_xy(p);
     let mut x = xu as i32;
     let mut y = yu as i32;
-    for _ in 0..4 {
+    for _ in 0..reach {
       if dx < 0 && x == 0 ||
-         dx > 0 && x == SIZE as i32 - 1 ||
+         dx > 0 && x == self.size as i32 - 1 ||
          dy < 0 && y == 0 ||
-         dy > 0 && y == SIZE as i32 - 1 {
+         dy > 0 && y == self.size as i32 - 1 {
         break;
       }
       x += dx;
@@ -54,43 +152,13 @@ impl Gomoku {
 
     util::xy_to_point(x as u32, y as u32)
   }
-
-  fn create_lines_margins() -> [[LinesMargins; 4]; BOARD_LEN] {
-    let mut margins: [[LinesMargins; 4]; BOARD_LEN] =
-        [[LinesMargins{delta: 0, start: 0, end: 0}; 4]; BOARD_LEN];
-
-    for p in 0..BOARD_LEN {
-      margins[p][0] = LinesMargins{
-          delta: 1,
-          start: Gomoku::move_till_margin(p, -1, 0),
-          end: Gomoku::move_till_margin(p, 1, 0)
-      };
-      margins[p][1] = LinesMargins{
-          delta: SIZE as usize,
-          start: Gomoku::move_till_margin(p, 0, -1),
-          end: Gomoku::move_till_margin(p, 0, 1)
-      };
-      margins[p][2] = LinesMargins{
-          delta: SIZE as usize - 1,
-          start: Gomoku::move_till_margin(p, 1, -1),
-          end: Gomoku::move_till_margin(p, -1, 1)
-      };
-      margins[p][3] = LinesMargins{
-          delta: SIZE as usize + 1,
-          start: Gomoku::move_till_margin(p, -1, -1),
-          end: Gomoku::move_till_margin(p, 1, 1)
-      };
-    }
-
-    margins
-  }
 }
 
 impl Game for Gomoku {
   type State = GomokuState;
 
   fn new_game(&self) -> GomokuState {
-    GomokuState::new()
+    GomokuState::new(*self)
   }
 }
 
@@ -108,20 +176,26 @@ impl PointState {
 }
 
 pub struct GomokuState {
-  gomoku: &'static Gomoku,
+  gomoku: Gomoku,
   pub board: [PointState; BOARD_LEN],
-  status: Status
+  status: Status,
+  hash: u64
 }
 
 impl GomokuState {
-  fn new() -> GomokuState {
+  fn new(gomoku: Gomoku) -> GomokuState {
     GomokuState {
-      gomoku: Gomoku::default(),
+      gomoku,
       board: [PointState::Empty; BOARD_LEN],
-      status: Status::new()
+      status: Status::new(),
+      hash: 0
     }
   }
 
+  pub fn gomoku(&self) -> &Gomoku {
+    &self.gomoku
+  }
+
   #[cfg(test)]
   pub fn get(&self, p: usize) -> PointState {
     self.board[p]
@@ -137,9 +211,9 @@ impl GomokuState {
 
   fn player_won(&mut self, point: usize, player: PointState) -> bool {
     debug_assert!(self.board[point] == player);
-    let lines: &[LinesMargins; 4] = &self.gomoku.lines_margins[point];
+    let win_len = self.gomoku.win_len;
 
-    for line in lines {
+    for line in self.gomoku.lines_margins(point).iter() {
       let mut len = 1;
       let mut p = point;
 
@@ -160,7 +234,9 @@ impl GomokuState {
         len += 1;
       }
 
-      if len >= 5 {
+      // In Renju an overline doesn't win for black. It's forbidden anyway.
+      let black_renju = self.gomoku.renju && player == PointState::Black;
+      if len == win_len || len > win_len && !black_renju {
         return true;
       }
     }
@@ -168,14 +244,85 @@ impl GomokuState {
     false
   }
 
-  fn play_stone(&mut self, point: usize) {
+  // Whether the player to move may not play at the empty point.
+  fn is_forbidden(&self, point: usize) -> bool {
+    self.gomoku.renju && self.player()
+      && renju::is_forbidden(&self.gomoku, &self.board, point)
+  }
+
+  // Whether there is an empty point, that is not forbidden for black.
+  fn black_can_move(&self) -> bool {
+    (0..BOARD_LEN).any(|p| {
+      self.gomoku.on_board(p)
+        && self.board[p] == PointState::Empty
+        && !renju::is_forbidden(&self.gomoku, &self.board, p)
+    })
+  }
+
+  fn check_point(&self, point: usize) -> Result<(), &'static str> {
+    if !self.gomoku.on_board(point) {
+      Err("Point is outside of the board")
+    } else if self.board[point] != PointState::Empty {
+      Err("Position is taken")
+    } else {
+      Ok(())
+    }
+  }
+
+  /// Places several stones of the player to move and passes the turn, as in
+  /// Connect6, where the players place two stones per turn.
+  pub(super) fn play_stones(
+    &mut self,
+    points: &[usize],
+  ) -> Result<(), &'static str> {
+    if self.is_terminal() {
+      return Err("Trying to make a move in a terminal state.")
+    }
+    for (i, &point) in points.iter().enumerate() {
+      self.check_point(point)?;
+      if points[..i].contains(&point) {
+        return Err("Position is taken")
+      }
+    }
+    self.place_stones(points);
+    Ok(())
+  }
+
+  /// Removes the stones placed by `play_stones`.
+  pub(super) fn undo_stones(
+    &mut self,
+    points: &[usize],
+  ) -> Result<(), &'static str> {
+    let player = !self.player();
+    let stone = PointState::from_player(player);
+    let placed = |&p: &usize| self.gomoku.on_board(p) && self.board[p] == stone;
+    if !points.iter().all(placed) {
+      return Err("This wasn't the last move")
+    }
+    for &point in points {
+      self.hash ^= zobrist::point_key(point, player);
+      self.board[point] = PointState::Empty;
+    }
+    self.hash ^= zobrist::player_key();
+    self.status.switch_player();
+    self.status.undo_terminal();
+    Ok(())
+  }
+
+  fn place_stones(&mut self, points: &[usize]) {
     let player = self.player();
     let player_stone = PointState::from_player(player);
 
-    self.board[point] = player_stone;
-    if self.player_won(point, player_stone) {
+    for &point in points {
+      self.board[point] = player_stone;
+      self.hash ^= zobrist::point_key(point, player);
+    }
+    self.hash ^= zobrist::player_key();
+    if points.iter().any(|&p| self.player_won(p, player_stone)) {
       self.status.set_winner(player);
-    } else if self.board.iter().all(|&x| x != PointState::Empty) {
+    } else if (0..BOARD_LEN).all(|p| self.board[p] != PointState::Empty ||
+                                     !self.gomoku.on_board(p)) ||
+              self.gomoku.renju && !player && !self.black_can_move() {
       self.status.set_draw();
     }
     self.status.switch_player()
@@ -192,7 +339,9 @@ impl<'s> Iterator for GomokuMoveIterator<'s> {
 
   fn next(&mut self) -> Option<GomokuMove> {
     while self.point < BOARD_LEN &&
-          self.state.board[self.point] != PointState::Empty {
+          (self.state.board[self.point] != PointState::Empty ||
+           !self.state.gomoku.on_board(self.point) ||
+           self.state.is_forbidden(self.point)) {
       self.point += 1;
     }
     if self.point < BOARD_LEN {
@@ -214,25 +363,17 @@ impl State for GomokuState {
     }
 
     let GomokuMove(point) = gmove;
-
-    if self.board[point] == PointState::Empty {
-      self.play_stone(point);
-      Ok(())
+    self.check_point(point)?;
+    if self.is_forbidden(point) {
+      Err("Forbidden move")
     } else {
-      Err("Position is taken")
+      self.place_stones(&[point]);
+      Ok(())
     }
   }
 
   fn undo(&mut self, gmove: GomokuMove) -> Result<(), &'static str> {
-    let GomokuMove(point) = gmove;
-    if self.board[point] == PointState::Empty {
-      Err("This wasn't the last move")
-    } else {
-      self.board[point] = PointState::Empty;
-      self.status.switch_player();
-      self.status.undo_terminal();
-      Ok(())
-    }
+    self.undo_stones(&[gmove.0])
   }
 
   fn iter_moves<'s>(&'s self) -> Box<Iterator<Item = GomokuMove> + 's> {
@@ -244,9 +385,12 @@ impl State for GomokuState {
       return None
     }
 
+    let size = self.gomoku.size;
     loop {
-      let point: usize = (rng.next_u32() % BOARD_LEN as u32) as usize;
-      if self.board[point] == PointState::Empty {
+      let x = rng.next_u32() % size;
+      let y = rng.next_u32() % size;
+      let point = util::xy_to_point(x, y);
+      if self.board[point] == PointState::Empty && !self.is_forbidden(point) {
         return Some(GomokuMove(point))
       }
     }
@@ -265,7 +409,16 @@ impl State for GomokuState {
   }
 
   fn parse_move(&self, move_str: &str) -> Result<GomokuMove, &'static str> {
-    GomokuMove::from_str(move_str)
+    let m = GomokuMove::from_str(move_str)?;
+    if self.gomoku.on_board(m.0) {
+      Ok(m)
+    } else {
+      Err("Point is outside of the board")
+    }
+  }
+
+  fn hash(&self) -> Option<u64> {
+    Some(self.hash)
   }
 }
 
@@ -274,7 +427,8 @@ impl Clone for GomokuState {
     GomokuState {
       gomoku: self.gomoku,
       board: self.board,
-      status: self.status
+      status: self.status,
+      hash: self.hash
     }
   }
 }
@@ -282,15 +436,15 @@ impl Clone for GomokuState {
 impl fmt::Display for GomokuState {
   fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
     write!(f, "  ")?;
-    for x in 0..SIZE {
+    for x in 0..self.gomoku.size {
       write!(f, " {}", util::col_letter(x))?;
     }
     writeln!(f, "")?;
 
-    for y in (0..SIZE).rev() {
+    for y in (0..self.gomoku.size).rev() {
       write!(f, "{:2}", y + 1)?;
-      for x in 0..SIZE {
-        let i = (SIZE * y + x) as usize;
+      for x in 0..self.gomoku.size {
+        let i = util::xy_to_point(x, y);
         match self.board[i] {
           PointState::Empty => write!(f, " ."),
           PointState::Black => write!(f, " X"),
@@ -301,7 +455,7 @@ impl fmt::Display for GomokuState {
     }
 
     write!(f, "  ")?;
-    for x in 0..SIZE {
+    for x in 0..self.gomoku.size {
       write!(f, " {}", util::col_letter(x))?;
     }
     writeln!(f)?;
@@ -3,7 +3,7 @@ use std::str::FromStr;
 
 use super::util;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct GomokuMove(pub usize);
 
 impl FromStr for GomokuMove {
@@ -17,14 +17,6 @@ impl FromStr for GomokuMove {
   }
 }
 
-impl PartialEq for GomokuMove {
-  fn eq(&self, other: &GomokuMove) -> bool {
-    let &GomokuMove(a) = self;
-    let &GomokuMove(b) = other;
-    return a == b;
-  }
-}
-
 impl fmt::Display for GomokuMove {
   fn fmt(&self, formatter : &mut fmt::Formatter) -> fmt::Result {
     let &GomokuMove(point) = self;
@@ -37,7 +29,7 @@ impl fmt::Display for GomokuMove {
 mod test {
 
 use std::string::ToString;
-use super::super::gomoku::SIZE;
+use super::super::gomoku::MAX_SIZE;
 use super::*;
 
 #[test]
@@ -61,9 +53,9 @@ fn parse_legal() {
   assert_eq!(Ok(GomokuMove(342)), "A19".parse());
   assert_eq!(Ok(GomokuMove(9)), "K1".parse());
   assert_eq!(Ok(GomokuMove(11)), "M1".parse());
-  assert_eq!(Ok(GomokuMove(SIZE as usize)), "A2".parse());
-  assert_eq!(Ok(GomokuMove(SIZE as usize)), "a2".parse());
-  assert_eq!(Ok(GomokuMove(SIZE as usize * 10 + 2)), "C11".parse());
+  assert_eq!(Ok(GomokuMove(MAX_SIZE as usize)), "A2".parse());
+  assert_eq!(Ok(GomokuMove(MAX_SIZE as usize)), "a2".parse());
+  assert_eq!(Ok(GomokuMove(MAX_SIZE as usize * 10 + 2)), "C11".parse());
 }
 
 #[test]
@@ -71,8 +63,8 @@ fn to_string() {
   assert_eq!("A1", GomokuMove(0).to_string());
   assert_eq!("K1", GomokuMove(9).to_string());
   assert_eq!("M1", GomokuMove(11).to_string());
-  assert_eq!("A2", GomokuMove(SIZE as usize).to_string());
-  assert_eq!("C11", GomokuMove(SIZE as usize * 10 + 2).to_string());
+  assert_eq!("A2", GomokuMove(MAX_SIZE as usize).to_string());
+  assert_eq!("C11", GomokuMove(MAX_SIZE as usize * 10 + 2).to_string());
 }
 
 }
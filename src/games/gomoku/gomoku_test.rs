@@ -5,7 +5,7 @@ use std::str::FromStr;
 use crate::def::Game;
 use crate::def::State;
 use super::gomoku_move::GomokuMove;
-use super::gomoku::{Gomoku, GomokuState, PointState, BOARD_LEN, SIZE};
+use super::gomoku::{Gomoku, GomokuState, PointState, BOARD_LEN, MAX_SIZE};
 use super::util;
 
 pub fn run_game(moves_str: &str, result: f32) -> GomokuState {
@@ -88,8 +88,8 @@ fn game_borders() {
 fn game_draw() {
   let game = Gomoku::new();
   let mut state = game.new_game();
-  for x in 0..SIZE {
-    for y in 0..SIZE {
+  for x in 0..MAX_SIZE {
+    for y in 0..MAX_SIZE {
       let xx = match x % 4 {
         0 => x,
         1 => x + 1,
@@ -138,7 +138,7 @@ fn is_finished(state: &GomokuState) -> bool {
       for i in 1..5 {
         let xx = x + dx * i;
         let yy = y + dy * i;
-        if xx < 0 || xx >= SIZE as i32 || yy < 0 || yy >= SIZE as i32 ||
+        if xx < 0 || xx >= MAX_SIZE as i32 || yy < 0 || yy >= MAX_SIZE as i32 ||
            state.get(util::xy_to_point(xx as u32, yy as u32)) != pstate {
           break
         }
@@ -195,4 +195,82 @@ fn undo() {
   assert!(state.play(c7).is_ok());
   assert!(state.play(c11).is_ok());
   assert!(state.is_terminal());
-}
\ No newline at end of file
+}
+#[test]
+fn hash() {
+  let empty_hash = Gomoku::default().new_game().hash();
+  let mut state1 = run_game("c3 d4 e5", 0.0);
+  let state2 = run_game("e5 d4 c3", 0.0);
+  let state3 = run_game("c3 e5 d4", 0.0);
+  assert_eq!(state1.hash(), state2.hash());
+  assert_ne!(state1.hash(), state3.hash());
+  assert_ne!(state1.hash(), empty_hash);
+
+  state1.undo("e5".parse().unwrap()).unwrap();
+  state1.undo("d4".parse().unwrap()).unwrap();
+  state1.undo("c3".parse().unwrap()).unwrap();
+  assert_eq!(empty_hash, state1.hash());
+}
+
+#[test]
+fn tictactoe() {
+  let game = Gomoku::new_sized(3, 3);
+  let mut state = game.new_game();
+  assert_eq!(9, state.iter_moves().count());
+  assert!(state.parse_move("d1").is_err());
+  assert!(state.parse_move("a4").is_err());
+  assert!(state.play("d1".parse().unwrap()).is_err());
+
+  for m in "b2 a1 c3 a3 a2 c2 b1 b3".split(' ') {
+    state.play(m.parse().unwrap()).unwrap();
+    assert!(!state.is_terminal());
+  }
+  state.play("c1".parse().unwrap()).unwrap();
+  assert_eq!(Some(0.0), state.payoff());
+
+  let mut state = game.new_game();
+  for m in "a1 b1 b2 c1 c3".split(' ') {
+    state.play(m.parse().unwrap()).unwrap();
+  }
+  assert_eq!(Some(1.0), state.payoff());
+}
+
+#[test]
+fn small_board() {
+  let mut rng = SmallRng::from_entropy();
+  let game = Gomoku::new_sized(9, 5);
+  let mut state = game.new_game();
+  assert_eq!(81, state.iter_moves().count());
+  // The line near the edge of the board is not cut by the margins.
+  for m in "e1 a9 f1 b9 g1 c9 h1 d9".split(' ') {
+    state.play(m.parse().unwrap()).unwrap();
+  }
+  assert!(!state.is_terminal());
+  state.play("j1".parse().unwrap()).unwrap();
+  assert_eq!(Some(1.0), state.payoff());
+
+  for _ in 0..20 {
+    let mut state = game.new_game();
+    while let Some(m) = state.get_random_move(&mut rng) {
+      let (x, y) = util::point_to_xy(m.0);
+      assert!(x < 9 && y < 9);
+      state.play(m).unwrap();
+    }
+    assert!(state.is_terminal());
+  }
+}
+
+#[test]
+fn default_sized() {
+  let game = Gomoku::default_sized(7, 4);
+  assert_eq!(7, game.size());
+  assert_eq!(4, game.win_len());
+  assert!(std::ptr::eq(game, Gomoku::default_sized(7, 4)));
+  assert_eq!(49, game.new_game().iter_moves().count());
+}
+
+#[test]
+#[should_panic]
+fn zero_win_len() {
+  Gomoku::new_sized(9, 0);
+}
@@ -6,7 +6,7 @@ use lazy_static::lazy_static;
 use crate::def::{FeatureExtractor, Regression, State};
 use crate::spec::{FeatureExtractorSpec};
 
-use super::gomoku::{GomokuState, PointState, BOARD_LEN, SIZE};
+use super::gomoku::{GomokuState, PointState, MAX_SIZE};
 
 #[derive(Clone, Copy, Debug)]
 pub struct LineRange {
@@ -25,8 +25,12 @@ pub struct LineRange {
 ///  - whether there's an empty space on one or both sides of the line.
 #[derive(Clone)]
 pub struct GomokuLineFeatureExtractor {
+  // All the lines of the board, precomputed for its size.
   lines: Vec<LineRange>,
+  size: u32,
   min_len: usize,
+  // Lines of this length win the game, so only the shorter lines count.
+  win_len: usize,
 }
 
 lazy_static! {
@@ -39,10 +43,24 @@ lazy_static! {
 }
 
 impl GomokuLineFeatureExtractor {
+  /// The extractor for the standard 19x19 board with five in a row to win.
   pub fn new(min_len: usize) -> GomokuLineFeatureExtractor {
+    Self::new_with_win_len(min_len, MAX_SIZE, 5)
+  }
+
+  /// The extractor for the board of the given size, where `win_len` stones in
+  /// a row win.
+  pub fn new_with_win_len(
+    min_len: usize,
+    size: u32,
+    win_len: usize,
+  ) -> GomokuLineFeatureExtractor {
+    assert!(min_len < win_len);
     GomokuLineFeatureExtractor {
-      lines: Self::gen_lines(),
+      lines: Self::gen_lines(size),
+      size,
       min_len,
+      win_len,
     }
   }
 
@@ -52,83 +70,52 @@ impl GomokuLineFeatureExtractor {
   }
 
   #[cfg(test)]
-  pub fn gen_lines_for_test() -> Vec<LineRange> {
-    Self::gen_lines()
+  pub fn gen_lines_for_test(size: u32) -> Vec<LineRange> {
+    Self::gen_lines(size)
   }
 
-  fn gen_lines() -> Vec<LineRange> {
-    let size = SIZE as usize;
+  // All the lines on the board of the given size. `end` is the point after
+  // the last point of the line.
+  fn gen_lines(size: u32) -> Vec<LineRange> {
+    let stride = MAX_SIZE as i32;
+    let size = size as i32;
+    let on_board = |x: i32, y: i32| 0 <= x && x < size && 0 <= y && y < size;
     let mut lines = Vec::new();
 
-    // Iterate through points on the top side.
-    for p in 0..size {
-      // Vertical line.
-      lines.push(LineRange {
-        start: p,
-        step: size,
-        end: BOARD_LEN,
-        diagonal: false,
-      });
-      // Diagonal line to bottom-right.
-      lines.push(LineRange {
-        start: p,
-        step: size + 1,
-        end: (size - p) * size,
-        diagonal: true,
-      });
-      // Diagonal line to bottom-left.
-      lines.push(LineRange {
-        start: p,
-        step: size - 1,
-        end: (p + 1) * size - 1,
-        diagonal: true,
-      });
-    }
-
-    let mut p = 0;
-    while p < BOARD_LEN {
-      // Horizontal line.
-      lines.push(LineRange {
-        start: p,
-        step: 1,
-        end: p + size,
-        diagonal: false,
-      });
-      if p != 0 {
-        // Diagonal line to bottom-right.
-        lines.push(LineRange {
-          start: p,
-          step: size + 1,
-          end: BOARD_LEN,
-          diagonal: true,
-        });
+    for &(dx, dy) in &[(1, 0), (0, 1), (1, 1), (-1, 1)] {
+      for y in 0..size {
+        for x in 0..size {
+          // Every line starts at the point, that has no predecessor.
+          if on_board(x - dx, y - dy) {
+            continue;
+          }
+          let (mut ex, mut ey) = (x, y);
+          while on_board(ex + dx, ey + dy) {
+            ex += dx;
+            ey += dy;
+          }
+          let step = dy * stride + dx;
+          lines.push(LineRange {
+            start: (y * stride + x) as usize,
+            step: step as usize,
+            end: (ey * stride + ex + step) as usize,
+            diagonal: dx != 0 && dy != 0,
+          });
+        }
       }
-      p += size;
-    }
-
-    let mut p = 2 * size - 1;
-    while p < BOARD_LEN {
-      // Diagonal line to bottom-left.
-      lines.push(LineRange {
-        start: p,
-        step: size - 1,
-        end: BOARD_LEN,
-        diagonal: true,
-      });
-      p += size;
     }
 
     lines
   }
 
-  fn encode(
+  pub(super) fn encode(
     &self,
     len: usize,
     both_ends: bool,
     diagonal: bool,
     active_player: bool,
   ) -> Option<usize> {
-    assert!(len < 5);
+    assert!(len < self.win_len);
     if len < self.min_len {
       None
     } else {
@@ -142,6 +129,44 @@ impl GomokuLineFeatureExtractor {
     }
   }
 
+  pub(super) fn extract_lines(&self, state: &GomokuState) -> Vec<f32> {
+    // Length 1 to win_len - 1, 1 or 2 open ends, straight or diagonal,
+    let mut features = vec![0.0; self.nfeatures()];
+    features[self.nfeatures() - 1] = 1.0; // Bias
+
+    debug_assert_eq!(self.size, state.gomoku().size());
+    for line in self.lines.iter() {
+      self.process_single_line(state, line, &mut features);
+    }
+
+    features
+  }
+
+  pub(super) fn min_len(&self) -> usize {
+    self.min_len
+  }
+
+  pub(super) fn report_lines<R: Regression>(&self, regression: &R) {
+    let b = regression.params();
+    println!(
+      "closed straight / closed diagonal / open straight / open diagonal"
+    );
+    for &player in &[true, false] {
+      for len in self.min_len..self.win_len {
+        println!(
+          "{} {}: {:>6.3} {:>6.3} {:>6.3} {:>6.3}",
+          (if player { "self " } else { "other" }),
+          len,
+          b[self.encode(len, false, false, player).unwrap()],
+          b[self.encode(len, false, true, player).unwrap()],
+          b[self.encode(len, true, false, player).unwrap()],
+          b[self.encode(len, true, true, player).unwrap()]
+        );
+      }
+    }
+    println!("bias: {:.3}\n", b[self.nfeatures() - 1]);
+  }
+
   fn process_single_line(
     &self,
     state: &GomokuState,
@@ -202,19 +227,11 @@ impl GomokuLineFeatureExtractor {
 
 impl FeatureExtractor<GomokuState> for GomokuLineFeatureExtractor {
   fn nfeatures(&self) -> usize {
-    (5 - self.min_len) * 8 + 1
+    (self.win_len - self.min_len) * 8 + 1
   }
 
   fn extract(&self, state: &GomokuState) -> Vec<f32> {
-    // Length 1-4, 1 or 2 open ends, straight or diagonal,
-    let mut features = vec![0.0; self.nfeatures()];
-    features[self.nfeatures() - 1] = 1.0; // Bias
-
-    for line in self.lines.iter() {
-      self.process_single_line(state, line, &mut features);
-    }
-
-    features
+    self.extract_lines(state)
   }
 
   fn spec(&self) -> FeatureExtractorSpec {
@@ -222,24 +239,7 @@ impl FeatureExtractor<GomokuState> for GomokuLineFeatureExtractor {
   }
 
   fn report<R: Regression>(&self, regression: &R) {
-    let b = regression.params();
-    println!(
-      "closed straight / closed diagonal / open straight / open diagonal"
-    );
-    for &player in &[true, false] {
-      for len in self.min_len..5 {
-        println!(
-          "{} {}: {:>6.3} {:>6.3} {:>6.3} {:>6.3}",
-          (if player { "self " } else { "other" }),
-          len,
-          b[self.encode(len, false, false, player).unwrap()],
-          b[self.encode(len, false, true, player).unwrap()],
-          b[self.encode(len, true, false, player).unwrap()],
-          b[self.encode(len, true, true, player).unwrap()]
-        );
-      }
-    }
-    println!("bias: {:.3}\n", b[self.nfeatures() - 1]);
+    self.report_lines(regression)
   }
 }
 
@@ -286,8 +286,10 @@ mod test {
 
   #[test]
   fn gen_lines() {
-    let lines = GomokuLineFeatureExtractor::gen_lines_for_test();
+    let lines = GomokuLineFeatureExtractor::gen_lines_for_test(19);
     assert_eq!(19 + 19 + 37 + 37, lines.len());
+    let lines = GomokuLineFeatureExtractor::gen_lines_for_test(3);
+    assert_eq!(3 + 3 + 5 + 5, lines.len());
   }
 
   #[test]
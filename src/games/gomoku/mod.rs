@@ -1,11 +1,18 @@
+mod connect6;
 mod gomoku;
 mod gomoku_move;
 mod line_features;
+mod proximity_policy;
+mod renju;
+mod swap2;
 mod util;
 
 #[cfg(test)]
 mod gomoku_test;
 
+pub use self::connect6::{Connect6, Connect6LineFeatureExtractor};
 pub use self::gomoku::Gomoku;
 pub use self::gomoku::GomokuState;
 pub use self::line_features::GomokuLineFeatureExtractor;
+pub use self::proximity_policy::GomokuProximityPolicy;
+pub use self::swap2::GomokuSwap2;
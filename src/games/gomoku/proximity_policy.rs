@@ -0,0 +1,123 @@
+//! Policy that only proposes the moves close to the stones on the board.
+
+use crate::def::{Policy, State};
+
+use super::gomoku::{GomokuState, PointState, BOARD_LEN};
+use super::gomoku_move::GomokuMove;
+use super::util;
+
+/// Proposes the empty points within a given distance from the stones on the
+/// board. `weights[d - 1]` is the prior weight of a point at the distance `d`
+/// from the closest stone, where the distance is the maximum of the
+/// horizontal and vertical distances. The number of weights thus sets the
+/// maximal distance. If no empty point is close enough, as on the empty
+/// board, all the legal moves are proposed with equal weights.
+#[derive(Clone)]
+pub struct GomokuProximityPolicy {
+  weights: Vec<f32>,
+}
+
+impl GomokuProximityPolicy {
+  pub fn new(weights: &[f32]) -> Self {
+    assert!(!weights.is_empty());
+    GomokuProximityPolicy {
+      weights: weights.to_vec(),
+    }
+  }
+
+  // Distance from every point to the closest stone, capped at the number of
+  // weights + 1.
+  fn distances(&self, state: &GomokuState) -> Vec<usize> {
+    let max_dist = self.weights.len() as i32;
+    let size = state.gomoku().size() as i32;
+    let mut distances = vec![max_dist as usize + 1; BOARD_LEN];
+    for p in 0..BOARD_LEN {
+      if state.board[p] == PointState::Empty {
+        continue;
+      }
+      let (x, y) = util::point_to_xy(p);
+      for dy in -max_dist..(max_dist + 1) {
+        for dx in -max_dist..(max_dist + 1) {
+          let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+          if nx < 0 || ny < 0 || nx >= size || ny >= size {
+            continue;
+          }
+          let np = util::xy_to_point(nx as u32, ny as u32);
+          let dist = dx.abs().max(dy.abs()) as usize;
+          if dist < distances[np] {
+            distances[np] = dist;
+          }
+        }
+      }
+    }
+    distances
+  }
+}
+
+impl Policy<GomokuState> for GomokuProximityPolicy {
+  fn get_moves(&self, state: &GomokuState) -> Vec<(GomokuMove, f32)> {
+    let distances = self.distances(state);
+    let mut moves: Vec<(GomokuMove, f32)> = state
+      .iter_moves()
+      .filter_map(|GomokuMove(p)| {
+        let dist = distances[p];
+        if 1 <= dist && dist <= self.weights.len() {
+          Some((GomokuMove(p), self.weights[dist - 1]))
+        } else {
+          None
+        }
+      })
+      .collect();
+    if moves.is_empty() {
+      moves = state.iter_moves().map(|m| (m, 1.0)).collect();
+    }
+    let total: f32 = moves.iter().map(|&(_, w)| w).sum();
+    for m in moves.iter_mut() {
+      m.1 /= total;
+    }
+    moves
+  }
+}
+
+#[cfg(test)]
+mod test {
+
+  use super::*;
+  use crate::def::Game;
+  use crate::games::Gomoku;
+
+  #[test]
+  fn empty_board() {
+    let state = Gomoku::default().new_game();
+    let policy = GomokuProximityPolicy::new(&[2.0, 1.0]);
+    let moves = policy.get_moves(&state);
+    assert_eq!(361, moves.len());
+    assert!(moves.iter().all(|&(_, w)| (w - 1.0 / 361.0).abs() < 1e-6));
+  }
+
+  #[test]
+  fn one_stone() {
+    let mut state = Gomoku::default().new_game();
+    state.play(state.parse_move("K10").unwrap()).unwrap();
+    let policy = GomokuProximityPolicy::new(&[2.0, 1.0]);
+    let moves = policy.get_moves(&state);
+    assert_eq!(24, moves.len());
+    let total: f32 = moves.iter().map(|&(_, w)| w).sum();
+    assert!((total - 1.0).abs() < 1e-6);
+    // 8 points at distance 1 and 16 at distance 2.
+    let near = state.parse_move("L11").unwrap();
+    let far = state.parse_move("M12").unwrap();
+    let weight = |m| moves.iter().find(|&&(mm, _)| mm == m).unwrap().1;
+    assert!((weight(near) - 2.0 / 32.0).abs() < 1e-6);
+    assert!((weight(far) - 1.0 / 32.0).abs() < 1e-6);
+  }
+
+  #[test]
+  fn corner() {
+    let mut state = Gomoku::default().new_game();
+    state.play(state.parse_move("A1").unwrap()).unwrap();
+    let policy = GomokuProximityPolicy::new(&[1.0]);
+    assert_eq!(3, policy.get_moves(&state).len());
+  }
+
+}  // mod test
@@ -0,0 +1,191 @@
+//! Forbidden moves of black in Renju.
+//!
+//! A move of black is forbidden, unless it makes exactly five in a row, if it
+//! makes an overline (six or more in a row), two or more fours, or two or
+//! more threes. A four is a line, that becomes exactly five with one more
+//! stone. A three is a line, that becomes a straight four (a four with two
+//! ways to complete it) with one more stone. Unlike in the official rules, a
+//! three counts even if the move that makes it a straight four is itself
+//! forbidden. If all the empty points are forbidden for black, the game is a
+//! draw.
+
+use super::gomoku::{Gomoku, PointState};
+
+/// Whether it's forbidden for black to play at the empty `point`.
+pub fn is_forbidden(
+  gomoku: &Gomoku,
+  board: &[PointState],
+  point: usize,
+) -> bool {
+  let lines: Vec<(Vec<PointState>, usize)> = gomoku
+    .lines_margins(point)
+    .iter()
+    .map(|m| {
+      let cells = (m.start..=m.end)
+        .step_by(m.delta)
+        .map(|p| if p == point { PointState::Black } else { board[p] })
+        .collect();
+      (cells, (point - m.start) / m.delta)
+    })
+    .collect();
+
+  if lines.iter().any(|(cells, i)| run_len(cells, *i) == 5) {
+    return false;
+  }
+  if lines.iter().any(|(cells, i)| run_len(cells, *i) > 5) {
+    return true;
+  }
+  let fours: usize = lines.iter().map(|(cells, i)| fours(cells, *i)).sum();
+  if fours >= 2 {
+    return true;
+  }
+  let threes = lines.iter().filter(|(cells, i)| is_three(cells, *i)).count();
+  threes >= 2
+}
+
+// Length of the run of black stones through `i`.
+fn run_len(cells: &[PointState], i: usize) -> usize {
+  let before = cells[..i]
+    .iter()
+    .rev()
+    .take_while(|&&c| c == PointState::Black)
+    .count();
+  let after = cells[i..]
+    .iter()
+    .take_while(|&&c| c == PointState::Black)
+    .count();
+  before + after
+}
+
+// The empty points, that make exactly five through `i`.
+fn completions(cells: &mut [PointState], i: usize) -> Vec<usize> {
+  let from = i.saturating_sub(4);
+  let to = (i + 4).min(cells.len() - 1);
+  (from..=to)
+    .filter(|&j| {
+      if cells[j] != PointState::Empty {
+        return false;
+      }
+      cells[j] = PointState::Black;
+      let five = run_len(cells, i) == 5;
+      cells[j] = PointState::Empty;
+      five
+    })
+    .collect()
+}
+
+// Number of fours through `i`. A straight four has two completions, five
+// points apart, but counts as one four.
+fn fours(cells: &[PointState], i: usize) -> usize {
+  let completions = completions(&mut cells.to_vec(), i);
+  if completions.len() == 2 && completions[1] - completions[0] == 5 {
+    1
+  } else {
+    completions.len()
+  }
+}
+
+fn is_straight_four(cells: &mut [PointState], i: usize) -> bool {
+  let completions = completions(cells, i);
+  completions
+    .iter()
+    .any(|&a| completions.iter().any(|&b| b == a + 5))
+}
+
+fn is_three(cells: &[PointState], i: usize) -> bool {
+  if fours(cells, i) > 0 {
+    return false;
+  }
+  let mut cells = cells.to_vec();
+  let from = i.saturating_sub(3);
+  let to = (i + 3).min(cells.len() - 1);
+  (from..=to).any(|j| {
+    if cells[j] != PointState::Empty {
+      return false;
+    }
+    cells[j] = PointState::Black;
+    let straight = is_straight_four(&mut cells, i);
+    cells[j] = PointState::Empty;
+    straight
+  })
+}
+
+#[cfg(test)]
+mod test {
+
+  use rand::FromEntropy;
+  use rand::rngs::SmallRng;
+
+  use crate::def::{Game, State};
+  use super::super::gomoku::{Gomoku, GomokuState};
+
+  // Plays the moves alternately, and checks, whether black may play `point`.
+  fn forbidden(moves: &str, point: &str) -> bool {
+    let mut state: GomokuState = Gomoku::renju().new_game();
+    for m in moves.split_whitespace() {
+      state.play(m.parse().unwrap()).unwrap();
+    }
+    assert!(state.player());
+    let m = point.parse().unwrap();
+    let proposed = state.iter_moves().any(|legal| legal == m);
+    let forbidden = state.play(m).is_err();
+    assert_eq!(forbidden, !proposed);
+    forbidden
+  }
+
+  #[test]
+  fn double_three() {
+    // A single three is allowed.
+    assert!(!forbidden("K10 A1 L10 A3 K11 A5", "J10"));
+    assert!(!forbidden("K10 A1 L10 A3 K11 A5", "M10"));
+    // K10 makes open threes both horizontally and vertically.
+    assert!(forbidden("L10 A1 M10 A3 K11 A5 K12 A7", "K10"));
+  }
+
+  #[test]
+  fn double_four() {
+    assert!(!forbidden("K10 A1 L10 A3 M10 A5 K11 A7 K12 A9 K13 A11", "K14"));
+    // K10 makes fours both horizontally and vertically.
+    assert!(forbidden("L10 A1 M10 A3 N10 A5 K11 A7 K12 A9 K13 A11", "K10"));
+    // X.XXXX: J10 would make an overline, so it's a single four.
+    assert!(!forbidden("H10 A1 K10 A3 L10 A5 N10 A7", "M10"));
+    // X.XXX.X: a double four in one line.
+    assert!(forbidden("H10 A1 L10 A3 M10 A5 O10 A7", "K10"));
+  }
+
+  #[test]
+  fn overline() {
+    assert!(forbidden("F10 A1 G10 A3 H10 A5 K10 A7 L10 A9", "J10"));
+    assert!(!forbidden("F10 A1 G10 A3 H10 A5 J10 A7", "K10"));
+  }
+
+  #[test]
+  fn exact_five() {
+    let mut state = Gomoku::renju().new_game();
+    for m in "F10 A1 G10 A3 H10 A5 J10 A7 K10".split_whitespace() {
+      state.play(m.parse().unwrap()).unwrap();
+    }
+    assert_eq!(Some(1.0), state.payoff());
+
+    // White wins with an overline.
+    let mut state = Gomoku::renju().new_game();
+    let moves = "A1 F10 A3 G10 A5 H10 A7 K10 A9 L10 A11 J10";
+    for m in moves.split_whitespace() {
+      state.play(m.parse().unwrap()).unwrap();
+    }
+    assert_eq!(Some(-1.0), state.payoff());
+  }
+
+  #[test]
+  fn random_games() {
+    let mut rng = SmallRng::from_entropy();
+    for _ in 0..5 {
+      let mut state = Gomoku::renju().new_game();
+      while let Some(m) = state.get_random_move(&mut rng) {
+        state.play(m).unwrap();
+      }
+      assert!(state.payoff().is_some());
+    }
+  }
+
+}  // mod test
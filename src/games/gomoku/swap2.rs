@@ -0,0 +1,376 @@
+//! Gomoku with the Swap2 opening rule.
+//!
+//! The first player places three stones: black, white and black. The second
+//! player then either takes black, or takes white, or places two more stones,
+//! white and black, and leaves the choice of the color to the first player.
+//! After the colors are chosen, the game goes on as the usual Gomoku with
+//! white to move.
+
+use lazy_static::lazy_static;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::def::{Game, State};
+use crate::zobrist;
+use super::gomoku::{Gomoku, GomokuState};
+use super::gomoku_move::GomokuMove;
+
+// Number of stones placed by the first player in the opening.
+const OPENING_STONES: u32 = 3;
+// Number of stones after the second player has chosen to place two more.
+const EXTENDED_STONES: u32 = 5;
+
+lazy_static! {
+  static ref SWAP2_INSTANCE: GomokuSwap2 = GomokuSwap2::new();
+}
+
+pub struct GomokuSwap2 {}
+
+impl GomokuSwap2 {
+  pub fn new() -> GomokuSwap2 {
+    GomokuSwap2 {}
+  }
+
+  pub fn default() -> &'static GomokuSwap2 {
+    &*SWAP2_INSTANCE
+  }
+}
+
+impl Game for GomokuSwap2 {
+  type State = GomokuSwap2State;
+
+  fn new_game(&self) -> GomokuSwap2State {
+    GomokuSwap2State {
+      gomoku: Gomoku::default().new_game(),
+      stones: 0,
+      extended: false,
+      black: None,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Swap2Move {
+  /// A stone of the color, whose turn it is on the board.
+  Stone(GomokuMove),
+  /// The acting player takes black.
+  Black,
+  /// The acting player takes white.
+  White,
+  /// The second player places two more stones, white and black, and passes
+  /// the choice of the color to the first player.
+  PlaceTwo,
+}
+
+impl fmt::Display for Swap2Move {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Swap2Move::Stone(m) => write!(f, "{}", m),
+      Swap2Move::Black => write!(f, "black"),
+      Swap2Move::White => write!(f, "white"),
+      Swap2Move::PlaceTwo => write!(f, "place2"),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Phase {
+  // The first player places the first three stones.
+  Opening,
+  // The second player chooses the color or to place two more stones.
+  FirstChoice,
+  // The second player places two more stones.
+  Extension,
+  // The first player chooses the color.
+  SecondChoice,
+  // The usual game.
+  Game,
+}
+
+#[derive(Clone)]
+pub struct GomokuSwap2State {
+  gomoku: GomokuState,
+  stones: u32,
+  extended: bool,
+  // Which player has black, once the colors are chosen.
+  black: Option<bool>,
+}
+
+impl GomokuSwap2State {
+  fn phase(&self) -> Phase {
+    if self.black.is_some() {
+      Phase::Game
+    } else if self.stones < OPENING_STONES {
+      Phase::Opening
+    } else if !self.extended {
+      Phase::FirstChoice
+    } else if self.stones < EXTENDED_STONES {
+      Phase::Extension
+    } else {
+      Phase::SecondChoice
+    }
+  }
+
+  fn choices(&self) -> &'static [Swap2Move] {
+    match self.phase() {
+      Phase::FirstChoice =>
+        &[Swap2Move::Black, Swap2Move::White, Swap2Move::PlaceTwo],
+      Phase::SecondChoice => &[Swap2Move::Black, Swap2Move::White],
+      _ => &[],
+    }
+  }
+
+  // Number of stones on the board, when the colors were chosen.
+  fn opening_len(&self) -> u32 {
+    if self.extended {
+      EXTENDED_STONES
+    } else {
+      OPENING_STONES
+    }
+  }
+}
+
+impl State for GomokuSwap2State {
+  type Move = Swap2Move;
+
+  fn player(&self) -> bool {
+    match self.phase() {
+      Phase::Opening | Phase::SecondChoice => true,
+      Phase::FirstChoice | Phase::Extension => false,
+      // The stones of the inner game alternate between black (`true`) and
+      // white.
+      Phase::Game => self.black.unwrap() == self.gomoku.player(),
+    }
+  }
+
+  fn is_terminal(&self) -> bool {
+    self.gomoku.is_terminal()
+  }
+
+  fn payoff(&self) -> Option<f32> {
+    let payoff = self.gomoku.payoff()?;
+    if self.black == Some(false) {
+      Some(-payoff)
+    } else {
+      Some(payoff)
+    }
+  }
+
+  fn iter_moves<'s>(&'s self) -> Box<Iterator<Item = Swap2Move> + 's> {
+    if self.is_terminal() {
+      return Box::new(None.into_iter());
+    }
+    match self.phase() {
+      Phase::FirstChoice | Phase::SecondChoice =>
+        Box::new(self.choices().iter().cloned()),
+      _ => Box::new(self.gomoku.iter_moves().map(Swap2Move::Stone)),
+    }
+  }
+
+  fn get_random_move<R: Rng>(&self, rng: &mut R) -> Option<Swap2Move> {
+    match self.phase() {
+      Phase::FirstChoice | Phase::SecondChoice =>
+        self.choices().choose(rng).cloned(),
+      _ => self.gomoku.get_random_move(rng).map(Swap2Move::Stone),
+    }
+  }
+
+  fn play(&mut self, m: Swap2Move) -> Result<(), &'static str> {
+    let phase = self.phase();
+    match m {
+      Swap2Move::Stone(gmove) => {
+        match phase {
+          Phase::Opening | Phase::Extension | Phase::Game => {}
+          _ => return Err("The color has to be chosen first."),
+        }
+        self.gomoku.play(gmove)?;
+        self.stones += 1;
+      }
+      Swap2Move::Black | Swap2Move::White => {
+        if !self.choices().contains(&m) {
+          return Err("Choosing the color is not allowed now.");
+        }
+        let player = self.player();
+        self.black = Some(if m == Swap2Move::Black { player } else { !player });
+      }
+      Swap2Move::PlaceTwo => {
+        if phase != Phase::FirstChoice {
+          return Err("Placing two stones is only allowed after the opening.");
+        }
+        self.extended = true;
+      }
+    }
+    Ok(())
+  }
+
+  fn undo(&mut self, m: Swap2Move) -> Result<(), &'static str> {
+    match m {
+      Swap2Move::Stone(gmove) => {
+        let last_is_stone = match self.phase() {
+          Phase::Extension => self.stones > OPENING_STONES,
+          Phase::Game => self.stones > self.opening_len(),
+          _ => self.stones > 0,
+        };
+        if !last_is_stone {
+          return Err("Can't undo move");
+        }
+        self.gomoku.undo(gmove)?;
+        self.stones -= 1;
+      }
+      Swap2Move::Black | Swap2Move::White => {
+        if self.phase() != Phase::Game || self.stones != self.opening_len() {
+          return Err("Can't undo move");
+        }
+        self.black = None;
+      }
+      Swap2Move::PlaceTwo => {
+        if self.phase() != Phase::Extension || self.stones != OPENING_STONES {
+          return Err("Can't undo move");
+        }
+        self.extended = false;
+      }
+    }
+    Ok(())
+  }
+
+  fn parse_move(&self, move_str: &str) -> Result<Swap2Move, &'static str> {
+    let m = match move_str.to_lowercase().as_str() {
+      "black" => Swap2Move::Black,
+      "white" => Swap2Move::White,
+      "place2" => Swap2Move::PlaceTwo,
+      _ => Swap2Move::Stone(GomokuMove::from_str(move_str)?),
+    };
+    if self.iter_moves().any(|legal| legal == m) {
+      Ok(m)
+    } else {
+      Err("Illegal move.")
+    }
+  }
+
+  fn hash(&self) -> Option<u64> {
+    let mut hash = self.gomoku.hash()?;
+    if self.extended {
+      hash ^= zobrist::flag_key(0);
+    }
+    match self.black {
+      Some(true) => hash ^= zobrist::flag_key(1),
+      Some(false) => hash ^= zobrist::flag_key(2),
+      None => {}
+    }
+    Some(hash)
+  }
+}
+
+impl fmt::Display for GomokuSwap2State {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.gomoku)?;
+    match self.black {
+      Some(true) => writeln!(f, "Black: player 1"),
+      Some(false) => writeln!(f, "Black: player 2"),
+      None => writeln!(f, "Colors are not chosen yet"),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+
+  use rand::FromEntropy;
+  use rand::rngs::SmallRng;
+
+  use super::*;
+
+  fn play(state: &mut GomokuSwap2State, moves: &str) -> Vec<Swap2Move> {
+    moves
+      .split_whitespace()
+      .map(|m| {
+        let m = state.parse_move(m).unwrap();
+        state.play(m).unwrap();
+        m
+      })
+      .collect()
+  }
+
+  #[test]
+  fn take_black() {
+    let mut state = GomokuSwap2::default().new_game();
+    play(&mut state, "K10 K11 L10");
+    assert!(!state.player());
+    assert_eq!(3, state.iter_moves().count());
+    assert!(state.parse_move("M10").is_err());
+
+    play(&mut state, "black");
+    // The first player has white now, and it's white's turn.
+    assert!(state.player());
+    play(&mut state, "A1 M10 A2 N10 A3 O10");
+    assert!(state.is_terminal());
+    assert_eq!(Some(-1.0), state.payoff());
+  }
+
+  #[test]
+  fn take_white() {
+    let mut state = GomokuSwap2::default().new_game();
+    play(&mut state, "K10 K11 L10 white");
+    // The second player keeps white and moves.
+    assert!(!state.player());
+    play(&mut state, "M10 A1");
+    assert!(!state.player());
+  }
+
+  #[test]
+  fn place_two() {
+    let mut state = GomokuSwap2::default().new_game();
+    play(&mut state, "K10 K11 L10 place2");
+    assert!(!state.player());
+    assert!(state.parse_move("black").is_err());
+    play(&mut state, "M10 M11");
+    assert!(state.player());
+    assert_eq!(2, state.iter_moves().count());
+    assert!(state.parse_move("place2").is_err());
+
+    play(&mut state, "white");
+    // The second player has black, and it's white's turn.
+    assert!(state.player());
+    play(&mut state, "N11");
+    assert!(!state.player());
+  }
+
+  #[test]
+  fn undo() {
+    let mut state = GomokuSwap2::default().new_game();
+    let mut hashes = vec![state.hash()];
+    let mut moves = Vec::new();
+    for m in "K10 K11 L10 place2 M10 M11 black N11".split_whitespace() {
+      moves.extend(play(&mut state, m));
+      hashes.push(state.hash());
+    }
+    let mut sorted = hashes.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(hashes.len(), sorted.len());
+
+    for &m in moves.iter().rev() {
+      assert_eq!(hashes.pop().unwrap(), state.hash());
+      state.undo(m).unwrap();
+    }
+    assert_eq!(hashes.pop().unwrap(), state.hash());
+    assert!(state.undo(Swap2Move::PlaceTwo).is_err());
+  }
+
+  #[test]
+  fn random_games() {
+    let mut rng = SmallRng::from_entropy();
+    for _ in 0..10 {
+      let mut state = GomokuSwap2::default().new_game();
+      while let Some(m) = state.get_random_move(&mut rng) {
+        assert!(state.iter_moves().any(|legal| legal == m));
+        state.play(m).unwrap();
+      }
+      assert!(state.black.is_some());
+      assert!(state.payoff().is_some());
+    }
+  }
+
+}  // mod test
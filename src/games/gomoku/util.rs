@@ -1,13 +1,13 @@
 use std::char;
 
-use super::gomoku::SIZE;
+use super::gomoku::MAX_SIZE;
 
 pub fn xy_to_point(col: u32, row: u32) -> usize {
-  (row * SIZE + col) as usize
+  (row * MAX_SIZE + col) as usize
 }
 
 pub fn point_to_xy(point: usize) -> (u32, u32) {
-  (point as u32 % SIZE, point as u32 / SIZE)
+  (point as u32 % MAX_SIZE, point as u32 / MAX_SIZE)
 }
 
 pub fn col_letter(col: u32) -> char {
@@ -43,12 +43,12 @@ pub fn parse_point(s: &str) -> Option<usize> {
       _ => return None
     };
     row = row * 10 + digit;
-    if row > SIZE {
+    if row > MAX_SIZE {
       return None
     }
   }
 
-  if 1 <= row && col < SIZE {
+  if 1 <= row && col < MAX_SIZE {
     return Some(xy_to_point(col, row - 1));
   } else {
     return None
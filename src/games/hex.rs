@@ -0,0 +1,530 @@
+use lazy_static::lazy_static;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+use crate::board::{col_letter, Board, Cell};
+use crate::def::{Game, State};
+use crate::games::cached_instance;
+use crate::status::Status;
+use crate::zobrist;
+
+// Neighbours of a cell on the rhombic board.
+const NEIGHBOURS: [(i32, i32); 6] =
+  [(1, 0), (-1, 0), (0, 1), (0, -1), (1, -1), (-1, 1)];
+
+lazy_static! {
+  static ref INSTANCE_7: Hex = Hex::new(7, false);
+  static ref INSTANCE_11: Hex = Hex::new(11, false);
+  static ref INSTANCE_11_SWAP: Hex = Hex::new(11, true);
+  static ref INSTANCES: Mutex<HashMap<(u32, bool), &'static Hex>> =
+    Mutex::new(HashMap::new());
+}
+
+/// The first player connects the bottom and the top rows, the second one
+/// connects the leftmost and the rightmost columns. With the swap rule, the
+/// second player can take over the first stone of the opponent instead of
+/// making their first move. The stone is then reflected along the short
+/// diagonal, so that the sides of the players stay the same.
+pub struct Hex {
+  size: u32,
+  swap: bool,
+}
+
+impl Hex {
+  pub fn new(size: u32, swap: bool) -> Hex {
+    Hex { size, swap }
+  }
+
+  pub fn default(size: u32, swap: bool) -> &'static Hex {
+    match (size, swap) {
+      (7, false) => &*INSTANCE_7,
+      (11, false) => &*INSTANCE_11,
+      (11, true) => &*INSTANCE_11_SWAP,
+      _ => cached_instance(&INSTANCES, (size, swap), || Hex::new(size, swap)),
+    }
+  }
+}
+
+impl Game for Hex {
+  type State = HexState;
+
+  fn new_game(&self) -> HexState {
+    HexState::new(self.size, self.swap)
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HexMove {
+  Place(u32, u32),
+  Swap,
+}
+
+impl fmt::Display for HexMove {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      HexMove::Place(x, y) => write!(f, "{}{}", col_letter(*x), y + 1),
+      HexMove::Swap => write!(f, "swap"),
+    }
+  }
+}
+
+/// Union-find over the cells of the board and the four sides, that can be
+/// rolled back to an earlier state. There is no path compression, so that
+/// every union changes a single parent link, but the union by size keeps the
+/// trees shallow.
+#[derive(Clone, Debug)]
+struct UnionFind {
+  parent: Vec<usize>,
+  size: Vec<u32>,
+  // The (child, parent) links created by the unions, in order.
+  links: Vec<(usize, usize)>,
+}
+
+impl UnionFind {
+  fn new(len: usize) -> UnionFind {
+    UnionFind {
+      parent: (0..len).collect(),
+      size: vec![1; len],
+      links: Vec::new(),
+    }
+  }
+
+  fn find(&self, mut i: usize) -> usize {
+    while self.parent[i] != i {
+      i = self.parent[i];
+    }
+    i
+  }
+
+  fn union(&mut self, i: usize, j: usize) {
+    let (mut i, mut j) = (self.find(i), self.find(j));
+    if i == j {
+      return;
+    }
+    if self.size[i] > self.size[j] {
+      std::mem::swap(&mut i, &mut j);
+    }
+    self.parent[i] = j;
+    self.size[j] += self.size[i];
+    self.links.push((i, j));
+  }
+
+  // Number of unions made so far, to be passed to `rollback`.
+  fn mark(&self) -> usize {
+    self.links.len()
+  }
+
+  fn rollback(&mut self, mark: usize) {
+    while self.links.len() > mark {
+      let (i, j) = self.links.pop().unwrap();
+      self.parent[i] = i;
+      self.size[j] -= self.size[i];
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct HexState {
+  board: Board<HexCell>,
+  status: Status,
+  swap: bool,
+  uf: UnionFind,
+  // The union-find marks before every move played so far.
+  history: Vec<usize>,
+  hash: u64,
+}
+
+impl HexState {
+  fn new(size: u32, swap: bool) -> HexState {
+    let cells = (size * size) as usize;
+    HexState {
+      board: Board::new_empty(size, size),
+      status: Status::new(),
+      swap,
+      uf: UnionFind::new(cells + 4),
+      history: Vec::new(),
+      hash: 0,
+    }
+  }
+
+  // The union-find nodes of the sides: bottom and top for the first player,
+  // left and right for the second one.
+  fn sides(&self, player: bool) -> (usize, usize) {
+    let n = self.board.len();
+    if player {
+      (n, n + 1)
+    } else {
+      (n + 2, n + 3)
+    }
+  }
+
+  fn can_swap(&self) -> bool {
+    self.swap && self.history.len() == 1
+  }
+
+  // Puts a stone on the board and joins it with the neighbouring stones of the
+  // same color and the sides. Returns whether the player has won.
+  fn put_stone(&mut self, x: u32, y: u32, player: bool) -> bool {
+    let cell = HexCell::player(player);
+    let point = self.board.xy_to_point(x, y);
+    self.board.set(point, cell);
+    self.hash ^= zobrist::point_key(point, player);
+
+    let last = self.board.width - 1;
+    for &(dx, dy) in NEIGHBOURS.iter() {
+      let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+      if nx < 0 || ny < 0 || nx as u32 > last || ny as u32 > last {
+        continue;
+      }
+      let (nx, ny) = (nx as u32, ny as u32);
+      if self.board.get_xy(nx, ny) == Some(cell) {
+        self.uf.union(point, self.board.xy_to_point(nx, ny));
+      }
+    }
+    let (low, high) = self.sides(player);
+    let pos = if player { y } else { x };
+    if pos == 0 {
+      self.uf.union(point, low);
+    }
+    if pos == last {
+      self.uf.union(point, high);
+    }
+    self.uf.find(low) == self.uf.find(high)
+  }
+
+  fn remove_stone(&mut self, x: u32, y: u32, player: bool) {
+    let point = self.board.xy_to_point(x, y);
+    self.board.set(point, HexCell::Empty);
+    self.hash ^= zobrist::point_key(point, player);
+  }
+
+  // The only stone on the board, after the first move.
+  fn first_stone(&self) -> (u32, u32) {
+    let point = self.board.iter().position(|c| !c.is_empty()).unwrap();
+    self.board.point_to_xy(point)
+  }
+}
+
+impl State for HexState {
+  type Move = HexMove;
+
+  fn player(&self) -> bool {
+    self.status.player()
+  }
+
+  fn is_terminal(&self) -> bool {
+    self.status.is_terminal()
+  }
+
+  fn payoff(&self) -> Option<f32> {
+    self.status.payoff()
+  }
+
+  fn iter_moves<'s>(&'s self) -> Box<Iterator<Item = HexMove> + 's> {
+    if self.is_terminal() {
+      return Box::new(None.into_iter());
+    }
+    let swap = if self.can_swap() { Some(HexMove::Swap) } else { None };
+    Box::new(
+      self
+        .board
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.is_empty())
+        .map(move |(p, _)| {
+          let (x, y) = self.board.point_to_xy(p);
+          HexMove::Place(x, y)
+        })
+        .chain(swap),
+    )
+  }
+
+  fn get_random_move<R: Rng>(&self, rng: &mut R) -> Option<HexMove> {
+    let moves: Vec<_> = self.iter_moves().collect();
+    moves.choose(rng).cloned()
+  }
+
+  fn play(&mut self, m: HexMove) -> Result<(), &'static str> {
+    if self.is_terminal() {
+      return Err("Trying to make a move in a terminal state.");
+    }
+    let player = self.player();
+    let mark = self.uf.mark();
+    match m {
+      HexMove::Swap => {
+        if !self.can_swap() {
+          return Err("Swap is only allowed as the second move.");
+        }
+        let (x, y) = self.first_stone();
+        self.remove_stone(x, y, !player);
+        self.uf.rollback(0);
+        self.put_stone(y, x, player);
+      }
+      HexMove::Place(x, y) => {
+        if x >= self.board.width
+          || self.board.get_xy(x, y) != Some(HexCell::Empty)
+        {
+          return Err("Illegal move.");
+        }
+        if self.put_stone(x, y, player) {
+          self.status.set_winner(player);
+        }
+      }
+    }
+    self.history.push(mark);
+    self.hash ^= zobrist::player_key();
+    self.status.switch_player();
+
+    Ok(())
+  }
+
+  fn undo(&mut self, m: HexMove) -> Result<(), &'static str> {
+    let player = !self.player();
+    match m {
+      HexMove::Swap => {
+        if !self.swap || self.history.len() != 2 {
+          return Err("Can't undo move");
+        }
+        let (x, y) = self.first_stone();
+        if self.board.get_xy(x, y) != Some(HexCell::player(player)) {
+          return Err("Can't undo move");
+        }
+        self.history.pop();
+        self.remove_stone(x, y, player);
+        self.uf.rollback(0);
+        self.put_stone(y, x, !player);
+      }
+      HexMove::Place(x, y) => {
+        if x >= self.board.width
+          || self.board.get_xy(x, y) != Some(HexCell::player(player))
+        {
+          return Err("Can't undo move");
+        }
+        let mark = self.history.pop().ok_or("Can't undo move")?;
+        self.remove_stone(x, y, player);
+        self.uf.rollback(mark);
+      }
+    }
+    self.hash ^= zobrist::player_key();
+    self.status.undo_terminal();
+    self.status.switch_player();
+
+    Ok(())
+  }
+
+  fn parse_move(&self, move_str: &str) -> Result<HexMove, &'static str> {
+    if move_str.to_lowercase() == "swap" {
+      if self.can_swap() {
+        return Ok(HexMove::Swap);
+      }
+      return Err("Swap is only allowed as the second move.");
+    }
+    let point = self
+      .board
+      .parse_point(move_str)
+      .ok_or("Error parsing Hex move.")?;
+    if !self.board.get(point).unwrap().is_empty() {
+      return Err("Point is occupied.");
+    }
+    let (x, y) = self.board.point_to_xy(point);
+    Ok(HexMove::Place(x, y))
+  }
+
+  fn hash(&self) -> Option<u64> {
+    Some(self.hash)
+  }
+}
+
+impl fmt::Display for HexState {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let size = self.board.width;
+    write!(f, "{:width$}", "", width = size as usize + 1)?;
+    for x in 0..size {
+      write!(f, " {}", col_letter(x))?;
+    }
+    writeln!(f)?;
+    // Every row is shifted by a half of a cell from the row below.
+    for y in (0..size).rev() {
+      write!(f, "{:width$}{:>2}", "", y + 1, width = y as usize)?;
+      for x in 0..size {
+        write!(f, " {}", self.board.get_xy(x, y).unwrap().unicode())?;
+      }
+      writeln!(f, " {}", y + 1)?;
+    }
+    write!(f, "  ")?;
+    for x in 0..size {
+      write!(f, " {}", col_letter(x))?;
+    }
+    writeln!(f)
+  }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum HexCell {
+  Empty,
+  Red,
+  Blue,
+}
+
+impl HexCell {
+  fn player(player: bool) -> HexCell {
+    if player {
+      HexCell::Red
+    } else {
+      HexCell::Blue
+    }
+  }
+}
+
+impl Cell for HexCell {
+  fn empty() -> HexCell {
+    HexCell::Empty
+  }
+
+  fn is_empty(self) -> bool {
+    self == HexCell::Empty
+  }
+
+  fn ascii(self) -> char {
+    match self {
+      HexCell::Empty => '.',
+      HexCell::Red => 'X',
+      HexCell::Blue => 'O',
+    }
+  }
+
+  fn unicode(self) -> char {
+    match self {
+      HexCell::Empty => '·',
+      HexCell::Red => '●',
+      HexCell::Blue => '○',
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use rand::FromEntropy;
+  use rand::rngs::SmallRng;
+
+  use super::*;
+  use crate::agents::MctsAgent;
+  use crate::def::Agent;
+  use crate::evaluators::SamplerEvaluator;
+  use crate::equal_policy::EqualPolicy;
+
+  fn play(state: &mut HexState, moves: &str) {
+    for m in moves.split_whitespace() {
+      let m = state.parse_move(m).unwrap();
+      state.play(m).unwrap();
+    }
+  }
+
+  #[test]
+  fn vertical_win() {
+    let mut state = Hex::new(3, false).new_game();
+    assert_eq!(9, state.iter_moves().count());
+    // a1-a2 is not enough, a3 connects to the top.
+    play(&mut state, "a1 b1 a2 c1");
+    assert!(!state.is_terminal());
+    play(&mut state, "a3");
+    assert_eq!(Some(1.0), state.payoff());
+    assert_eq!(0, state.iter_moves().count());
+
+    state.undo(HexMove::Place(0, 2)).unwrap();
+    assert!(!state.is_terminal());
+    assert!(state.player());
+  }
+
+  #[test]
+  fn diagonal_connection() {
+    let mut state = Hex::new(3, false).new_game();
+    // c1-b2-a3 is a chain for O from the right to the left: b2 and c1 are
+    // neighbours, as are a3 and b2.
+    play(&mut state, "a1 c1 b1 b2 b3 a3");
+    assert_eq!(Some(-1.0), state.payoff());
+  }
+
+  #[test]
+  fn undo_unions() {
+    let mut state = Hex::new(3, false).new_game();
+    play(&mut state, "a1 b1 a2 c1");
+    let hash = state.hash();
+    let parent = state.uf.parent.clone();
+    play(&mut state, "a3");
+    state.undo(HexMove::Place(0, 2)).unwrap();
+    assert_eq!(hash, state.hash());
+    assert_eq!(parent, state.uf.parent);
+    let (bottom, top) = state.sides(true);
+    assert_ne!(state.uf.find(bottom), state.uf.find(top));
+  }
+
+  #[test]
+  fn default_sizes() {
+    let game = Hex::default(9, false);
+    assert!(std::ptr::eq(game, Hex::default(9, false)));
+    assert_eq!(81, game.new_game().iter_moves().count());
+
+    let mut state = Hex::default(7, true).new_game();
+    play(&mut state, "b4");
+    assert_eq!(49, state.iter_moves().count());
+  }
+
+  #[test]
+  fn swap() {
+    let mut state = Hex::new(5, true).new_game();
+    assert!(state.parse_move("swap").is_err());
+    play(&mut state, "b4");
+    assert_eq!(25, state.iter_moves().count());
+    let hash = state.hash();
+    play(&mut state, "swap");
+    assert!(state.player());
+    assert_eq!(Some(HexCell::Blue), state.board.get_a("d2"));
+    assert_eq!(Some(HexCell::Empty), state.board.get_a("b4"));
+    assert!(state.iter_moves().all(|m| m != HexMove::Swap));
+
+    state.undo(HexMove::Swap).unwrap();
+    assert_eq!(Some(HexCell::Red), state.board.get_a("b4"));
+    assert_eq!(hash, state.hash());
+
+    let mut state = Hex::new(5, false).new_game();
+    play(&mut state, "b4");
+    assert!(state.parse_move("swap").is_err());
+  }
+
+  #[test]
+  fn random_games() {
+    let mut rng = SmallRng::from_entropy();
+    let game = Hex::new(7, true);
+    for _ in 0..20 {
+      let mut state = game.new_game();
+      let mut moves = Vec::new();
+      while let Some(m) = state.get_random_move(&mut rng) {
+        state.play(m).unwrap();
+        moves.push(m);
+      }
+      // There are no draws in Hex.
+      assert!(state.payoff().unwrap() != 0.0);
+      for &m in moves.iter().rev() {
+        state.undo(m).unwrap();
+      }
+      assert_eq!(Some(0), state.hash());
+    }
+  }
+
+  #[test]
+  fn mcts_finds_win() {
+    let mut state = Hex::new(3, false).new_game();
+    play(&mut state, "b1 b3 b2 c2");
+    // a3 wins for X and would win for O too.
+    let policy = EqualPolicy::new();
+    let evaluator = SamplerEvaluator::new(1, 1.0);
+    let agent = MctsAgent::new(&policy, &evaluator, Some(200), None);
+    let m = agent.select_move(&state).unwrap().get_move();
+    assert_eq!(HexMove::Place(0, 2), m);
+  }
+
+}  // mod test
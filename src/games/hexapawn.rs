@@ -8,6 +8,7 @@ use crate::board::{point_to_a, Board, Cell};
 use crate::def::{FeatureExtractor, Game, Regression, State};
 use crate::spec::FeatureExtractorSpec;
 use crate::status::Status;
+use crate::zobrist;
 
 lazy_static! {
   static ref INSTANCE_3_3: Hexapawn = Hexapawn::new(3, 3);
@@ -39,27 +40,39 @@ impl Game for Hexapawn {
   type State = HexapawnState;
 
   fn new_game(&self) -> HexapawnState {
-    HexapawnState::new(self.width, self.height)
+    HexapawnState::new(self.width, self.height, 1, false)
   }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct HexapawnMove {
   width: u16,
   from: u16,
   to: u16,
+  take: bool,
 }
 
 impl HexapawnMove {
+  /// Creates a move, that is a take if it is diagonal, as in Hexapawn.
   fn new(from: usize, to: usize, width: u32) -> HexapawnMove {
+    let diagonal = from as u32 % width != to as u32 % width;
+    HexapawnMove::with_take(from, to, width, diagonal)
+  }
+
+  fn with_take(from: usize, to: usize, width: u32, take: bool) -> HexapawnMove {
     HexapawnMove {
       from: from as u16,
       to: to as u16,
       width: width as u16,
+      take,
     }
   }
 
   fn is_take(self) -> bool {
+    self.take
+  }
+
+  fn is_diagonal(self) -> bool {
     self.from % self.width != self.to % self.width
   }
 }
@@ -76,23 +89,42 @@ impl fmt::Display for HexapawnMove {
   }
 }
 
+/// The state of Hexapawn and of its generalisations like Breakthrough.
 #[derive(Clone, Debug)]
 pub struct HexapawnState {
-  board: Board<HexapawnCell>,
+  pub(super) board: Board<HexapawnCell>,
   status: Status,
+  // Whether the pawns may step diagonally to an empty square.
+  diagonal_steps: bool,
   moves: Vec<HexapawnMove>,
+  hash: u64,
 }
 
 impl HexapawnState {
-  fn new(width: u32, height: u32) -> HexapawnState {
+  /// Creates the starting position with `rows` rows of pawns per side.
+  pub(super) fn new(
+    width: u32,
+    height: u32,
+    rows: u32,
+    diagonal_steps: bool,
+  ) -> HexapawnState {
+    assert!(2 * rows < height);
     let mut state = HexapawnState {
       board: Board::new_empty(width, height),
       status: Status::new(),
+      diagonal_steps,
       moves: Vec::new(),
+      hash: 0,
     };
-    for x in 0..width {
-      state.board.set_xy(x, 0, HexapawnCell::White);
-      state.board.set_xy(x, height - 1, HexapawnCell::Black);
+    for y in 0..rows {
+      for x in 0..width {
+        let white = state.board.xy_to_point(x, y);
+        let black = state.board.xy_to_point(x, height - 1 - y);
+        state.board.set(white, HexapawnCell::White);
+        state.board.set(black, HexapawnCell::Black);
+        state.hash ^= zobrist::point_key(white, true);
+        state.hash ^= zobrist::point_key(black, false);
+      }
     }
     state.gen_moves();
     state
@@ -117,25 +149,29 @@ impl HexapawnState {
           self.board
         );
         let (x, _) = self.board.point_to_xy(point);
-        if x != 0 && self.board.get(next_row - 1).unwrap().is_player(!player) {
-          self
-            .moves
-            .push(HexapawnMove::new(point, next_row - 1, width));
+        if x != 0 {
+          self.add_diagonal_move(point, next_row - 1);
         }
         if self.board.get(next_row).unwrap().is_empty() {
           self.moves.push(HexapawnMove::new(point, next_row, width));
         }
-        if x != width - 1
-          && self.board.get(next_row + 1).unwrap().is_player(!player)
-        {
-          self
-            .moves
-            .push(HexapawnMove::new(point, next_row + 1, width));
+        if x != width - 1 {
+          self.add_diagonal_move(point, next_row + 1);
         }
       }
     }
   }
 
+  fn add_diagonal_move(&mut self, from: usize, to: usize) {
+    let width = self.board.width;
+    let cell = self.board.get(to).unwrap();
+    if cell.is_player(!self.player()) {
+      self.moves.push(HexapawnMove::with_take(from, to, width, true));
+    } else if cell.is_empty() && self.diagonal_steps {
+      self.moves.push(HexapawnMove::with_take(from, to, width, false));
+    }
+  }
+
   fn check_move(&self, m: HexapawnMove) -> Result<(), &'static str> {
     let player = self.player();
     if !self.board.get(m.from as usize).unwrap().is_player(player) {
@@ -149,10 +185,24 @@ impl HexapawnState {
       if !self.board.get(m.to as usize).unwrap().is_empty() {
         return Err("Target square is not empty.");
       }
+      if m.is_diagonal() && !self.diagonal_steps {
+        return Err("Diagonal moves must be takes.");
+      }
     }
 
     Ok(())
   }
+
+  // Updates the hash for a given move. Since XOR is its own inverse, this is
+  // used both in `play()` and `undo()`.
+  fn update_hash(&mut self, m: HexapawnMove, player: bool) {
+    self.hash ^= zobrist::point_key(m.from as usize, player)
+      ^ zobrist::point_key(m.to as usize, player)
+      ^ zobrist::player_key();
+    if m.is_take() {
+      self.hash ^= zobrist::point_key(m.to as usize, !player);
+    }
+  }
 }
 
 impl State for HexapawnState {
@@ -187,6 +237,7 @@ impl State for HexapawnState {
     self.check_move(m)?;
     self.board.set(m.from as usize, HexapawnCell::Empty);
     self.board.set(m.to as usize, HexapawnCell::player(player));
+    self.update_hash(m, player);
 
     self.status.switch_player();
 
@@ -223,6 +274,7 @@ impl State for HexapawnState {
         HexapawnCell::Empty
       },
     );
+    self.update_hash(m, player);
     self.status.undo_terminal();
     self.status.switch_player();
 
@@ -241,14 +293,19 @@ impl State for HexapawnState {
       .board
       .parse_point(&caps[3])
       .ok_or("Error parsing Hexapawn move.")?;
-    let m = HexapawnMove::new(from, to, self.board.width);
+    let m =
+      HexapawnMove::with_take(from, to, self.board.width, &caps[2] == "x");
     self.check_move(m)?;
-    if m.is_take() == (&caps[2] == "x") {
+    if self.moves.contains(&m) {
       Ok(m)
     } else {
-      Err("Incorrect `-` vs `x`")
+      Err("Illegal move.")
     }
   }
+
+  fn hash(&self) -> Option<u64> {
+    Some(self.hash)
+  }
 }
 
 impl fmt::Display for HexapawnState {
@@ -258,14 +315,14 @@ impl fmt::Display for HexapawnState {
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-enum HexapawnCell {
+pub(super) enum HexapawnCell {
   Empty,
   White,
   Black,
 }
 
 impl HexapawnCell {
-  fn is_player(self, player: bool) -> bool {
+  pub(super) fn is_player(self, player: bool) -> bool {
     if player {
       self == HexapawnCell::White
     } else {
@@ -504,6 +561,35 @@ mod test {
     assert!(state.is_terminal());
   }
 
+  #[test]
+  fn hash() {
+    let game = Hexapawn::new(3, 3);
+    let mut state1 = game.new_game();
+    let mut state2 = game.new_game();
+    let start_hash = state1.hash();
+
+    for m in &["a1-a2", "b3-b2", "c1-c2"] {
+      let m = state1.parse_move(m).unwrap();
+      state1.play(m).unwrap();
+    }
+    for m in &["c1-c2", "b3-b2", "a1-a2"] {
+      let m = state2.parse_move(m).unwrap();
+      state2.play(m).unwrap();
+    }
+    assert_eq!(state1.hash(), state2.hash());
+    assert_ne!(start_hash, state1.hash());
+
+    let mut state = game.new_game();
+    let m = state.parse_move("a1-a2").unwrap();
+    state.play(m).unwrap();
+    let hash = state.hash();
+    let m = state.parse_move("b3xa2").unwrap();
+    state.play(m).unwrap();
+    assert_ne!(hash, state.hash());
+    state.undo(m).unwrap();
+    assert_eq!(hash, state.hash());
+  }
+
   #[test]
   fn extractor() {
     let mut state = Hexapawn::default(3, 3).new_game();
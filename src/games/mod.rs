@@ -1,21 +1,115 @@
+mod breakthrough;
+mod chess;
+pub mod connect_four;
+mod draughts;
+mod go;
 mod gomoku;
+mod hex;
 mod hexapawn;
+mod nim;
+mod othello;
+mod pig;
+mod poker;
+mod quarto;
 pub mod subtractor;
 
-pub use self::gomoku::{Gomoku, GomokuLineFeatureExtractor};
+pub use self::breakthrough::{Breakthrough, BreakthroughFeatureExtractor};
+pub use self::chess::{Chess, ChessFeatureExtractor};
+pub use self::connect_four::{ConnectFour, ConnectFourThreatExtractor};
+pub use self::draughts::{Draughts, DraughtsFeatureExtractor};
+pub use self::go::Go;
+pub use self::gomoku::{Connect6, Connect6LineFeatureExtractor, Gomoku,
+                       GomokuLineFeatureExtractor, GomokuProximityPolicy,
+                       GomokuSwap2};
+pub use self::hex::Hex;
 pub use self::hexapawn::{Hexapawn, HexapawnCompleteExtractor, HexapawnNumberOfPawnsExtractor};
+pub use self::nim::Nim;
+pub use self::othello::{Othello, OthelloFeatureExtractor};
+pub use self::pig::Pig;
+pub use self::poker::Poker;
+pub use self::quarto::{Quarto, QuartoFeatureExtractor};
 pub use self::subtractor::{Subtractor, SubtractorFeatureExtractor};
 
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+
+/// Returns the game instance with the given parameters from the cache,
+/// creating it on the first use. The instances are leaked, so that the
+/// configurations without a preset instance can also be shared as
+/// `&'static`.
+pub(crate) fn cached_instance<K, G, F>(
+  cache: &Mutex<HashMap<K, &'static G>>,
+  key: K,
+  create: F,
+) -> &'static G
+where
+  K: Eq + Hash,
+  G: 'static,
+  F: FnOnce() -> G,
+{
+  let mut cache = cache.lock().unwrap();
+  if let Some(&game) = cache.get(&key) {
+    return game;
+  }
+  let game: &'static G = Box::leak(Box::new(create()));
+  cache.insert(key, game);
+  game
+}
+
 #[macro_export]
 macro_rules! call_with_game {
   ($func:expr, $game_spec:expr, $( $arg:expr ),* ) => {
     match $game_spec {
-      &$crate::spec::GameSpec::Gomoku => {
-        $func($crate::games::Gomoku::default(), $( $arg ),*)
+      &$crate::spec::GameSpec::Breakthrough(width, height) => {
+        $func($crate::games::Breakthrough::default(width, height), $( $arg ),*)
+      },
+      &$crate::spec::GameSpec::Chess => {
+        $func($crate::games::Chess::default(), $( $arg ),*)
+      },
+      &$crate::spec::GameSpec::ConnectFour(width, height) => {
+        $func($crate::games::ConnectFour::default(width, height), $( $arg ),*)
+      },
+      &$crate::spec::GameSpec::Connect6 => {
+        $func($crate::games::Connect6::default(), $( $arg ),*)
+      },
+      &$crate::spec::GameSpec::Draughts(russian) => {
+        if russian {
+          $func($crate::games::Draughts::russian(), $( $arg ),*)
+        } else {
+          $func($crate::games::Draughts::english(), $( $arg ),*)
+        }
+      },
+      &$crate::spec::GameSpec::Go(size) => {
+        $func($crate::games::Go::default(size), $( $arg ),*)
+      },
+      &$crate::spec::GameSpec::Gomoku(size, win_len) => {
+        $func($crate::games::Gomoku::default_sized(size, win_len), $( $arg ),*)
+      },
+      &$crate::spec::GameSpec::Renju => {
+        $func($crate::games::Gomoku::renju(), $( $arg ),*)
+      },
+      &$crate::spec::GameSpec::GomokuSwap2 => {
+        $func($crate::games::GomokuSwap2::default(), $( $arg ),*)
+      },
+      &$crate::spec::GameSpec::Nim(players) => {
+        $func($crate::games::Nim::default(players), $( $arg ),*)
+      },
+      &$crate::spec::GameSpec::Othello(size) => {
+        $func($crate::games::Othello::default(size), $( $arg ),*)
+      },
+      &$crate::spec::GameSpec::Hex(size, swap) => {
+        $func($crate::games::Hex::default(size, swap), $( $arg ),*)
       },
       &$crate::spec::GameSpec::Hexapawn(width, height) => {
         $func($crate::games::Hexapawn::default(width, height), $( $arg ),*)
       },
+      &$crate::spec::GameSpec::Pig(goal) => {
+        $func($crate::games::Pig::default(goal), $( $arg ),*)
+      },
+      &$crate::spec::GameSpec::Quarto => {
+        $func($crate::games::Quarto::default(), $( $arg ),*)
+      },
       &$crate::spec::GameSpec::Subtractor(start, max_sub) => {
         $func($crate::games::Subtractor::default(start, max_sub), $( $arg ),*)
       },
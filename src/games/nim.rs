@@ -0,0 +1,271 @@
+//! Nim for two or more players. The players take turns removing any number of
+//! objects from a single heap, and the player, who takes the last object,
+//! wins. All the other players lose.
+
+use lazy_static::lazy_static;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+use crate::def::{Game, State};
+use crate::games::cached_instance;
+
+const MAX_HEAPS: usize = 7;
+const MAX_HEAP_SIZE: u32 = 255;
+
+lazy_static! {
+  static ref INSTANCE_2: Nim = Nim::new(2, &[3, 4, 5]);
+  static ref INSTANCE_3: Nim = Nim::new(3, &[3, 4, 5]);
+  static ref INSTANCES: Mutex<HashMap<usize, &'static Nim>> =
+    Mutex::new(HashMap::new());
+}
+
+pub struct Nim {
+  players: usize,
+  heaps: Vec<u32>,
+}
+
+impl Nim {
+  pub fn new(players: usize, heaps: &[u32]) -> Nim {
+    assert!(players >= 2);
+    assert!(!heaps.is_empty() && heaps.len() <= MAX_HEAPS);
+    assert!(heaps.iter().all(|&heap| heap <= MAX_HEAP_SIZE));
+    Nim {
+      players,
+      heaps: heaps.to_vec(),
+    }
+  }
+
+  pub fn default(players: usize) -> &'static Nim {
+    match players {
+      2 => &*INSTANCE_2,
+      3 => &*INSTANCE_3,
+      _ => cached_instance(&INSTANCES, players, || {
+        Nim::new(players, &[3, 4, 5])
+      }),
+    }
+  }
+}
+
+impl Game for Nim {
+  type State = NimState;
+
+  fn new_game(&self) -> NimState {
+    NimState {
+      heaps: self.heaps.clone(),
+      players: self.players,
+      player: 0,
+      winner: None,
+    }
+  }
+}
+
+/// Takes `count` objects from the heap. Written as the heap letter followed by
+/// the count, e.g. "b3".
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NimMove {
+  heap: u8,
+  count: u32,
+}
+
+impl fmt::Display for NimMove {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}{}", (b'a' + self.heap) as char, self.count)
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct NimState {
+  heaps: Vec<u32>,
+  players: usize,
+  // Index of the player to move.
+  player: usize,
+  winner: Option<usize>,
+}
+
+impl State for NimState {
+  type Move = NimMove;
+
+  fn player(&self) -> bool {
+    self.player == 0
+  }
+
+  fn num_players(&self) -> usize {
+    self.players
+  }
+
+  fn player_index(&self) -> usize {
+    self.player
+  }
+
+  fn is_terminal(&self) -> bool {
+    self.winner.is_some()
+  }
+
+  fn payoff(&self) -> Option<f32> {
+    self.payoffs().map(|payoffs| payoffs[0])
+  }
+
+  fn payoffs(&self) -> Option<Vec<f32>> {
+    let winner = self.winner?;
+    Some(
+      (0..self.players)
+        .map(|i| if i == winner { 1.0 } else { -1.0 })
+        .collect(),
+    )
+  }
+
+  fn iter_moves<'s>(&'s self) -> Box<Iterator<Item = NimMove> + 's> {
+    Box::new(self.heaps.iter().enumerate().flat_map(|(heap, &size)| {
+      (1..=size).map(move |count| NimMove {
+        heap: heap as u8,
+        count,
+      })
+    }))
+  }
+
+  fn get_random_move<R: Rng>(&self, rng: &mut R) -> Option<NimMove> {
+    let moves: Vec<_> = self.iter_moves().collect();
+    moves.choose(rng).cloned()
+  }
+
+  fn play(&mut self, m: NimMove) -> Result<(), &'static str> {
+    if self.is_terminal() {
+      return Err("Trying to make a move in a terminal state.");
+    }
+    match self.heaps.get_mut(m.heap as usize) {
+      Some(size) if m.count > 0 && m.count <= *size => *size -= m.count,
+      _ => return Err("Illegal move."),
+    }
+    if self.heaps.iter().all(|&size| size == 0) {
+      self.winner = Some(self.player);
+    }
+    self.player = (self.player + 1) % self.players;
+    Ok(())
+  }
+
+  fn undo(&mut self, m: NimMove) -> Result<(), &'static str> {
+    match self.heaps.get_mut(m.heap as usize) {
+      Some(size) if *size + m.count <= MAX_HEAP_SIZE => *size += m.count,
+      _ => return Err("Can't undo move"),
+    }
+    self.winner = None;
+    self.player = (self.player + self.players - 1) % self.players;
+    Ok(())
+  }
+
+  fn parse_move(&self, move_str: &str) -> Result<NimMove, &'static str> {
+    let s = move_str.to_lowercase();
+    let heap = match s.bytes().next() {
+      Some(c) if c >= b'a' && c < b'a' + self.heaps.len() as u8 => c - b'a',
+      _ => return Err("Error parsing Nim move."),
+    };
+    let count = s[1..].parse().map_err(|_| "Error parsing Nim move.")?;
+    let m = NimMove { heap, count };
+    if count > 0 && count <= self.heaps[heap as usize] {
+      Ok(m)
+    } else {
+      Err("Illegal move.")
+    }
+  }
+
+  fn hash(&self) -> Option<u64> {
+    let heaps = self
+      .heaps
+      .iter()
+      .fold(0, |hash, &size| hash << 8 | size as u64);
+    Some(heaps << 8 | self.player as u64)
+  }
+}
+
+impl fmt::Display for NimState {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Heaps:")?;
+    for (i, size) in self.heaps.iter().enumerate() {
+      write!(f, " {}={}", (b'a' + i as u8) as char, size)?;
+    }
+    match self.winner {
+      Some(winner) => writeln!(f, ", player {} won", winner + 1),
+      None => writeln!(f, ", player {} to move", self.player + 1),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use rand::FromEntropy;
+  use rand::rngs::SmallRng;
+
+  use super::*;
+
+  fn play(state: &mut NimState, moves: &str) {
+    for m in moves.split_whitespace() {
+      let m = state.parse_move(m).unwrap();
+      state.play(m).unwrap();
+    }
+  }
+
+  #[test]
+  fn three_players() {
+    let mut state = Nim::new(3, &[1, 2]).new_game();
+    assert_eq!(3, state.num_players());
+    assert_eq!(3, state.iter_moves().count());
+    assert!(state.parse_move("a2").is_err());
+    assert!(state.parse_move("c1").is_err());
+    play(&mut state, "b1");
+    assert_eq!(1, state.player_index());
+    assert!(!state.player());
+    play(&mut state, "a1");
+    assert_eq!(2, state.player_index());
+    assert_eq!(None, state.payoffs());
+    play(&mut state, "b1");
+    assert_eq!(Some(vec![-1.0, -1.0, 1.0]), state.payoffs());
+    assert_eq!(Some(-1.0), state.payoff());
+    assert_eq!(0, state.player_index());
+
+    state.undo(NimMove { heap: 1, count: 1 }).unwrap();
+    assert!(!state.is_terminal());
+    assert_eq!(2, state.player_index());
+  }
+
+  #[test]
+  fn two_players() {
+    let mut state = Nim::new(2, &[2]).new_game();
+    play(&mut state, "a2");
+    assert_eq!(Some(vec![1.0, -1.0]), state.payoffs());
+    assert_eq!(Some(1.0), state.payoff());
+  }
+
+  #[test]
+  fn random_games() {
+    let mut rng = SmallRng::from_entropy();
+    let game = Nim::new(4, &[3, 4, 5]);
+    for _ in 0..10 {
+      let mut state = game.new_game();
+      let mut moves = Vec::new();
+      while let Some(m) = state.get_random_move(&mut rng) {
+        state.play(m).unwrap();
+        moves.push(m);
+      }
+      let payoffs = state.payoffs().unwrap();
+      assert_eq!(1, payoffs.iter().filter(|&&p| p == 1.0).count());
+      for &m in moves.iter().rev() {
+        state.undo(m).unwrap();
+      }
+      assert_eq!(game.new_game().hash(), state.hash());
+    }
+  }
+
+  #[test]
+  fn default_players() {
+    let game = Nim::default(4);
+    assert!(std::ptr::eq(game, Nim::default(4)));
+    let mut state = game.new_game();
+    play(&mut state, "a1 b1 c1");
+    assert!(!state.is_terminal());
+    assert_eq!(3, state.player_index());
+  }
+
+}  // mod test
@@ -0,0 +1,524 @@
+use lazy_static::lazy_static;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+use crate::board::{col_letter, Board, Cell};
+use crate::def::{FeatureExtractor, Game, Regression, State};
+use crate::games::cached_instance;
+use crate::spec::FeatureExtractorSpec;
+use crate::status::Status;
+use crate::zobrist;
+
+// All eight directions, in which the discs can be flipped.
+const DIRECTIONS: [(i32, i32); 8] = [
+  (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1),
+];
+
+lazy_static! {
+  static ref INSTANCE_6: Othello = Othello::new(6);
+  static ref INSTANCE_8: Othello = Othello::new(8);
+  static ref INSTANCES: Mutex<HashMap<u32, &'static Othello>> =
+    Mutex::new(HashMap::new());
+}
+
+pub struct Othello {
+  size: u32,
+}
+
+impl Othello {
+  pub fn new(size: u32) -> Othello {
+    assert!(size >= 4 && size % 2 == 0, "Othello board size must be even.");
+    Othello { size }
+  }
+
+  pub fn default(size: u32) -> &'static Othello {
+    match size {
+      6 => &*INSTANCE_6,
+      8 => &*INSTANCE_8,
+      _ => cached_instance(&INSTANCES, size, || Othello::new(size)),
+    }
+  }
+}
+
+impl Game for Othello {
+  type State = OthelloState;
+
+  fn new_game(&self) -> OthelloState {
+    OthelloState::new(self.size)
+  }
+}
+
+/// A disc is placed at (column, row). A player, who has no legal placement,
+/// has to pass. The game ends after two consecutive passes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OthelloMove {
+  Place(u32, u32),
+  Pass,
+}
+
+#[derive(Clone, Debug)]
+pub struct OthelloState {
+  board: Board<OthelloCell>,
+  status: Status,
+  // The discs flipped by every move played so far. Passes flip nothing.
+  history: Vec<Vec<usize>>,
+  hash: u64,
+}
+
+impl OthelloState {
+  fn new(size: u32) -> OthelloState {
+    let mut state = OthelloState {
+      board: Board::new_empty(size, size),
+      status: Status::new(),
+      history: Vec::new(),
+      hash: 0,
+    };
+    let c = size / 2;
+    for &(x, y, player) in [
+      (c - 1, c - 1, false),
+      (c, c, false),
+      (c - 1, c, true),
+      (c, c - 1, true),
+    ]
+    .iter()
+    {
+      state.board.set_xy(x, y, OthelloCell::player(player));
+      state.hash ^= zobrist::point_key(state.board.xy_to_point(x, y), player);
+    }
+    state
+  }
+
+  fn cell(&self, x: i32, y: i32) -> Option<OthelloCell> {
+    if x < 0 || y < 0 {
+      return None;
+    }
+    let (x, y) = (x as u32, y as u32);
+    if x >= self.board.width || y >= self.board.height {
+      return None;
+    }
+    self.board.get_xy(x, y)
+  }
+
+  // The discs of the opponent, that are flipped by placing a disc of `player`
+  // at `point`. The placement is legal iff it flips at least one disc.
+  fn flips(&self, point: usize, player: bool) -> Vec<usize> {
+    let mut flipped = Vec::new();
+    if self.board.get(point) != Some(OthelloCell::Empty) {
+      return flipped;
+    }
+    let own = OthelloCell::player(player);
+    let other = OthelloCell::player(!player);
+    let (x, y) = self.board.point_to_xy(point);
+    for &(dx, dy) in DIRECTIONS.iter() {
+      let (mut cx, mut cy) = (x as i32 + dx, y as i32 + dy);
+      let mut line = Vec::new();
+      while self.cell(cx, cy) == Some(other) {
+        line.push(self.board.xy_to_point(cx as u32, cy as u32));
+        cx += dx;
+        cy += dy;
+      }
+      if !line.is_empty() && self.cell(cx, cy) == Some(own) {
+        flipped.extend(line);
+      }
+    }
+    flipped
+  }
+
+  // All the legal placements of `player`, regardless of whose turn it is.
+  fn placements(&self, player: bool) -> Vec<usize> {
+    (0..self.board.len())
+      .filter(|&p| !self.flips(p, player).is_empty())
+      .collect()
+  }
+
+  // Number of the passes at the end of the game record.
+  fn trailing_passes(&self) -> usize {
+    self.history.iter().rev().take_while(|f| f.is_empty()).count()
+  }
+
+  fn count_discs(&self) -> (u32, u32) {
+    self.board.iter().fold((0, 0), |(t, f), &c| match c {
+      OthelloCell::Black => (t + 1, f),
+      OthelloCell::White => (t, f + 1),
+      OthelloCell::Empty => (t, f),
+    })
+  }
+
+  fn flip(&mut self, point: usize, player: bool) {
+    self.board.set(point, OthelloCell::player(player));
+    self.hash ^=
+      zobrist::point_key(point, player) ^ zobrist::point_key(point, !player);
+  }
+}
+
+impl State for OthelloState {
+  type Move = OthelloMove;
+
+  fn player(&self) -> bool {
+    self.status.player()
+  }
+
+  fn is_terminal(&self) -> bool {
+    self.status.is_terminal()
+  }
+
+  fn payoff(&self) -> Option<f32> {
+    self.status.payoff()
+  }
+
+  fn iter_moves<'s>(&'s self) -> Box<Iterator<Item = OthelloMove> + 's> {
+    if self.is_terminal() {
+      return Box::new(None.into_iter());
+    }
+    let placements = self.placements(self.player());
+    if placements.is_empty() {
+      Box::new(Some(OthelloMove::Pass).into_iter())
+    } else {
+      Box::new(placements.into_iter().map(move |p| {
+        let (x, y) = self.board.point_to_xy(p);
+        OthelloMove::Place(x, y)
+      }))
+    }
+  }
+
+  fn get_random_move<R: Rng>(&self, rng: &mut R) -> Option<OthelloMove> {
+    let moves: Vec<_> = self.iter_moves().collect();
+    moves.choose(rng).cloned()
+  }
+
+  fn play(&mut self, m: OthelloMove) -> Result<(), &'static str> {
+    if self.is_terminal() {
+      return Err("Trying to make a move in a terminal state.");
+    }
+    let player = self.player();
+    match m {
+      OthelloMove::Pass => {
+        if !self.placements(player).is_empty() {
+          return Err("Can't pass, when there are legal moves.");
+        }
+        self.history.push(Vec::new());
+        if self.trailing_passes() >= 2 {
+          let (black, white) = self.count_discs();
+          if black == white {
+            self.status.set_draw();
+          } else {
+            self.status.set_winner(black > white);
+          }
+        }
+      }
+      OthelloMove::Place(x, y) => {
+        if x >= self.board.width || y >= self.board.height {
+          return Err("Move is outside of the board.");
+        }
+        let point = self.board.xy_to_point(x, y);
+        let flipped = self.flips(point, player);
+        if flipped.is_empty() {
+          return Err("Illegal move.");
+        }
+        self.board.set(point, OthelloCell::player(player));
+        self.hash ^= zobrist::point_key(point, player);
+        for &p in flipped.iter() {
+          self.flip(p, player);
+        }
+        self.history.push(flipped);
+      }
+    }
+    self.hash ^= zobrist::player_key();
+    self.status.switch_player();
+
+    Ok(())
+  }
+
+  fn undo(&mut self, m: OthelloMove) -> Result<(), &'static str> {
+    let player = !self.player();
+    match (m, self.history.last()) {
+      (OthelloMove::Pass, Some(flipped)) if flipped.is_empty() => {}
+      (OthelloMove::Place(x, y), Some(flipped))
+        if !flipped.is_empty()
+          && self.board.get_xy(x, y) == Some(OthelloCell::player(player)) => {}
+      _ => return Err("Can't undo move"),
+    }
+
+    let flipped = self.history.pop().unwrap();
+    if let OthelloMove::Place(x, y) = m {
+      let point = self.board.xy_to_point(x, y);
+      self.board.set(point, OthelloCell::Empty);
+      self.hash ^= zobrist::point_key(point, player);
+      for &p in flipped.iter() {
+        self.flip(p, !player);
+      }
+    }
+    self.hash ^= zobrist::player_key();
+    self.status.undo_terminal();
+    self.status.switch_player();
+
+    Ok(())
+  }
+
+  fn parse_move(&self, move_str: &str) -> Result<OthelloMove, &'static str> {
+    let m = if move_str.to_lowercase() == "pass" {
+      OthelloMove::Pass
+    } else {
+      let point = self
+        .board
+        .parse_point(move_str)
+        .ok_or("Error parsing Othello move.")?;
+      let (x, y) = self.board.point_to_xy(point);
+      OthelloMove::Place(x, y)
+    };
+    if self.iter_moves().any(|legal| legal == m) {
+      Ok(m)
+    } else {
+      Err("Illegal move.")
+    }
+  }
+
+  fn hash(&self) -> Option<u64> {
+    Some(self.hash)
+  }
+}
+
+impl fmt::Display for OthelloState {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.board.format(false))
+  }
+}
+
+impl fmt::Display for OthelloMove {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      OthelloMove::Place(x, y) => write!(f, "{}{}", col_letter(*x), y + 1),
+      OthelloMove::Pass => write!(f, "pass"),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum OthelloCell {
+  Empty,
+  Black,
+  White,
+}
+
+impl OthelloCell {
+  fn player(player: bool) -> OthelloCell {
+    if player {
+      OthelloCell::Black
+    } else {
+      OthelloCell::White
+    }
+  }
+}
+
+impl Cell for OthelloCell {
+  fn empty() -> OthelloCell {
+    OthelloCell::Empty
+  }
+
+  fn is_empty(self) -> bool {
+    self == OthelloCell::Empty
+  }
+
+  fn ascii(self) -> char {
+    match self {
+      OthelloCell::Empty => '.',
+      OthelloCell::Black => 'X',
+      OthelloCell::White => 'O',
+    }
+  }
+
+  fn unicode(self) -> char {
+    match self {
+      OthelloCell::Empty => '·',
+      OthelloCell::Black => '●',
+      OthelloCell::White => '○',
+    }
+  }
+}
+
+/// Features from the point of view of the acting player: bias, numbers of
+/// the player's and the opponent's discs, numbers of their legal placements
+/// (mobility) and numbers of the occupied corners.
+#[derive(Clone)]
+pub struct OthelloFeatureExtractor {}
+
+impl OthelloFeatureExtractor {
+  pub fn new() -> Self {
+    OthelloFeatureExtractor {}
+  }
+}
+
+impl FeatureExtractor<OthelloState> for OthelloFeatureExtractor {
+  fn nfeatures(&self) -> usize {
+    7
+  }
+
+  fn extract(&self, state: &OthelloState) -> Vec<f32> {
+    let player = state.player();
+    let own = OthelloCell::player(player);
+    let other = OthelloCell::player(!player);
+    let last = state.board.width - 1;
+    let corners = [(0, 0), (0, last), (last, 0), (last, last)];
+    let count_corners = |cell| {
+      corners
+        .iter()
+        .filter(|&&(x, y)| state.board.get_xy(x, y) == Some(cell))
+        .count() as f32
+    };
+    let count_discs =
+      |cell| state.board.iter().filter(|&&c| c == cell).count() as f32;
+
+    vec![
+      1.0,
+      count_discs(own),
+      count_discs(other),
+      state.placements(player).len() as f32,
+      state.placements(!player).len() as f32,
+      count_corners(own),
+      count_corners(other),
+    ]
+  }
+
+  fn spec(&self) -> FeatureExtractorSpec {
+    FeatureExtractorSpec::Othello
+  }
+
+  fn report<R: Regression>(&self, regression: &R) {
+    let b = regression.params();
+    println!("bias: {:>6.3}", b[0]);
+    println!("discs: self {:>6.3}, other {:>6.3}", b[1], b[2]);
+    println!("mobility: self {:>6.3}, other {:>6.3}", b[3], b[4]);
+    println!("corners: self {:>6.3}, other {:>6.3}\n", b[5], b[6]);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use rand::FromEntropy;
+  use rand::rngs::SmallRng;
+  use std::cmp::Ordering;
+
+  use super::*;
+
+  fn play(state: &mut OthelloState, moves: &str) {
+    for m in moves.split_whitespace() {
+      let m = state.parse_move(m).unwrap();
+      state.play(m).unwrap();
+    }
+  }
+
+  // A 4x4 board with the given discs and nothing else.
+  fn custom_state(discs: &[(&str, bool)]) -> OthelloState {
+    let mut state = Othello::new(4).new_game();
+    state.board = Board::new_empty(4, 4);
+    for &(a, player) in discs {
+      let point = state.board.parse_point(a).unwrap();
+      state.board.set(point, OthelloCell::player(player));
+    }
+    state
+  }
+
+  #[test]
+  fn opening() {
+    let mut state = Othello::new(8).new_game();
+    let mut moves: Vec<String> =
+      state.iter_moves().map(|m| m.to_string()).collect();
+    moves.sort();
+    assert_eq!(vec!["c4", "d3", "e6", "f5"], moves);
+
+    let hash = state.hash();
+    play(&mut state, "d3");
+    assert_eq!(Some(OthelloCell::Black), state.board.get_a("d4"));
+    assert_eq!((4, 1), state.count_discs());
+    assert!(state.parse_move("d3").is_err());
+
+    state.undo(OthelloMove::Place(3, 2)).unwrap();
+    assert_eq!(Some(OthelloCell::White), state.board.get_a("d4"));
+    assert_eq!(hash, state.hash());
+    assert!(state.player());
+  }
+
+  #[test]
+  fn default_sizes() {
+    let game = Othello::default(10);
+    assert!(std::ptr::eq(game, Othello::default(10)));
+    assert_eq!(4, game.new_game().iter_moves().count());
+  }
+
+  #[test]
+  fn pass() {
+    // Black has no discs to flank the white one with, white can play c1.
+    let mut state = custom_state(&[("a1", false), ("b1", true)]);
+    let moves: Vec<_> = state.iter_moves().collect();
+    assert_eq!(vec![OthelloMove::Pass], moves);
+    assert!(state.parse_move("c1").is_err());
+    play(&mut state, "pass");
+    assert!(!state.is_terminal());
+    assert!(state.parse_move("pass").is_err());
+    play(&mut state, "c1");
+    assert_eq!((0, 3), state.count_discs());
+  }
+
+  #[test]
+  fn double_pass() {
+    let mut state = custom_state(&[("a1", false), ("b1", false)]);
+    play(&mut state, "pass");
+    assert!(!state.is_terminal());
+    play(&mut state, "pass");
+    assert!(state.is_terminal());
+    assert_eq!(Some(-1.0), state.payoff());
+    assert_eq!(0, state.iter_moves().count());
+
+    state.undo(OthelloMove::Pass).unwrap();
+    assert!(!state.is_terminal());
+    assert!(!state.player());
+    assert!(state.undo(OthelloMove::Place(0, 0)).is_err());
+  }
+
+  #[test]
+  fn random_games() {
+    let mut rng = SmallRng::from_entropy();
+    let game = Othello::new(6);
+    for _ in 0..10 {
+      let mut state = game.new_game();
+      let mut moves = Vec::new();
+      while let Some(m) = state.get_random_move(&mut rng) {
+        state.play(m).unwrap();
+        moves.push(m);
+      }
+      let (black, white) = state.count_discs();
+      let payoff = match black.cmp(&white) {
+        Ordering::Greater => 1.0,
+        Ordering::Less => -1.0,
+        Ordering::Equal => 0.0,
+      };
+      assert_eq!(Some(payoff), state.payoff());
+
+      for &m in moves.iter().rev() {
+        state.undo(m).unwrap();
+      }
+      assert_eq!(game.new_game().hash(), state.hash());
+      assert_eq!((2, 2), state.count_discs());
+    }
+  }
+
+  #[test]
+  fn features() {
+    let mut state = Othello::new(8).new_game();
+    let extractor = OthelloFeatureExtractor::new();
+    assert_eq!(
+      vec![1.0, 2.0, 2.0, 4.0, 4.0, 0.0, 0.0],
+      extractor.extract(&state)
+    );
+    // White to move: c3, e3 and c5 are legal.
+    play(&mut state, "d3");
+    assert_eq!(
+      vec![1.0, 1.0, 4.0, 3.0, 3.0, 0.0, 0.0],
+      extractor.extract(&state)
+    );
+  }
+
+}  // mod test
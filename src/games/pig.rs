@@ -0,0 +1,318 @@
+//! Pig, the dice game. In a turn the player rolls a die until they either
+//! hold, adding the rolled points to their score, or roll 1, losing the points
+//! of the turn. The first player reaching the goal wins.
+//!
+//! The die rolls are the moves of chance nodes.
+
+use lazy_static::lazy_static;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+use crate::def::{Game, State};
+use crate::games::cached_instance;
+use crate::status::Status;
+
+lazy_static! {
+  static ref INSTANCE_100: Pig = Pig::new(100);
+  static ref INSTANCES: Mutex<HashMap<u32, &'static Pig>> =
+    Mutex::new(HashMap::new());
+}
+
+pub struct Pig {
+  goal: u32,
+}
+
+impl Pig {
+  pub fn new(goal: u32) -> Pig {
+    assert!(goal > 0 && goal < 1000);
+    Pig { goal }
+  }
+
+  pub fn default(goal: u32) -> &'static Pig {
+    match goal {
+      100 => &*INSTANCE_100,
+      _ => cached_instance(&INSTANCES, goal, || Pig::new(goal)),
+    }
+  }
+}
+
+impl Game for Pig {
+  type State = PigState;
+
+  fn new_game(&self) -> PigState {
+    PigState {
+      goal: self.goal,
+      scores: [0, 0],
+      turn_total: 0,
+      rolling: false,
+      status: Status::new(),
+      history: Vec::new(),
+    }
+  }
+}
+
+/// A player either rolls or holds. The player must roll at least once per
+/// turn. `Die` is the outcome of a roll.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PigMove {
+  Roll,
+  Hold,
+  Die(u8),
+}
+
+impl fmt::Display for PigMove {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PigMove::Roll => write!(f, "roll"),
+      PigMove::Hold => write!(f, "hold"),
+      PigMove::Die(points) => write!(f, "{}", points),
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct PigState {
+  goal: u32,
+  // The scores of the first and the second player.
+  scores: [u32; 2],
+  // The points rolled in the current turn.
+  turn_total: u32,
+  // Whether the die is rolled, i.e. it's a chance node.
+  rolling: bool,
+  status: Status,
+  // The moves played with the turn totals before them.
+  history: Vec<(PigMove, u32)>,
+}
+
+impl State for PigState {
+  type Move = PigMove;
+
+  fn player(&self) -> bool {
+    self.status.player()
+  }
+
+  fn is_terminal(&self) -> bool {
+    self.status.is_terminal()
+  }
+
+  fn payoff(&self) -> Option<f32> {
+    self.status.payoff()
+  }
+
+  fn is_chance(&self) -> bool {
+    self.rolling
+  }
+
+  fn chances(&self) -> Vec<(PigMove, f32)> {
+    if !self.rolling {
+      return Vec::new();
+    }
+    (1..7).map(|points| (PigMove::Die(points), 1.0 / 6.0)).collect()
+  }
+
+  fn iter_moves<'s>(&'s self) -> Box<Iterator<Item = PigMove> + 's> {
+    if self.is_terminal() {
+      Box::new(None.into_iter())
+    } else if self.rolling {
+      Box::new((1..7).map(PigMove::Die))
+    } else if self.turn_total == 0 {
+      Box::new(Some(PigMove::Roll).into_iter())
+    } else {
+      Box::new(vec![PigMove::Roll, PigMove::Hold].into_iter())
+    }
+  }
+
+  fn get_random_move<R: Rng>(&self, rng: &mut R) -> Option<PigMove> {
+    let moves: Vec<_> = self.iter_moves().collect();
+    moves.choose(rng).cloned()
+  }
+
+  fn play(&mut self, m: PigMove) -> Result<(), &'static str> {
+    if self.is_terminal() {
+      return Err("Trying to make a move in a terminal state.");
+    }
+    let turn_total = self.turn_total;
+    match m {
+      PigMove::Die(points) if self.rolling && (1..=6).contains(&points) => {
+        self.rolling = false;
+        if points == 1 {
+          self.turn_total = 0;
+          self.status.switch_player();
+        } else {
+          self.turn_total += points as u32;
+        }
+      }
+      PigMove::Roll if !self.rolling => self.rolling = true,
+      PigMove::Hold if !self.rolling && self.turn_total > 0 => {
+        let index = self.player_index();
+        self.scores[index] += self.turn_total;
+        self.turn_total = 0;
+        if self.scores[index] >= self.goal {
+          self.status.set_winner(self.player());
+        }
+        self.status.switch_player();
+      }
+      _ => return Err("Illegal move."),
+    }
+    self.history.push((m, turn_total));
+    Ok(())
+  }
+
+  fn undo(&mut self, m: PigMove) -> Result<(), &'static str> {
+    match self.history.last() {
+      Some(&(last, _)) if last == m => {}
+      _ => return Err("Can't undo move"),
+    }
+    let (_, turn_total) = self.history.pop().unwrap();
+    match m {
+      PigMove::Die(points) => {
+        if points == 1 {
+          self.status.switch_player();
+        }
+        self.rolling = true;
+      }
+      PigMove::Roll => self.rolling = false,
+      PigMove::Hold => {
+        self.status.undo_terminal();
+        self.status.switch_player();
+        let index = self.player_index();
+        self.scores[index] -= turn_total;
+      }
+    }
+    self.turn_total = turn_total;
+    Ok(())
+  }
+
+  fn parse_move(&self, move_str: &str) -> Result<PigMove, &'static str> {
+    let m = match move_str.to_lowercase().as_str() {
+      "roll" => PigMove::Roll,
+      "hold" => PigMove::Hold,
+      s => PigMove::Die(s.parse().map_err(|_| "Error parsing Pig move.")?),
+    };
+    if self.iter_moves().any(|legal| legal == m) {
+      Ok(m)
+    } else {
+      Err("Illegal move.")
+    }
+  }
+
+  fn hash(&self) -> Option<u64> {
+    Some(
+      self.scores[0] as u64
+        | (self.scores[1] as u64) << 16
+        | (self.turn_total as u64) << 32
+        | (self.rolling as u64) << 48
+        | (self.player() as u64) << 49,
+    )
+  }
+}
+
+impl fmt::Display for PigState {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(
+      f,
+      "Scores: {} - {}, goal {}",
+      self.scores[0], self.scores[1], self.goal
+    )?;
+    let player = if self.player() { 1 } else { 2 };
+    if self.rolling {
+      writeln!(f, "Player {} rolls, turn total {}", player, self.turn_total)
+    } else {
+      writeln!(f, "Player {} to move, turn total {}", player, self.turn_total)
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use rand::FromEntropy;
+  use rand::rngs::SmallRng;
+
+  use super::*;
+
+  fn play(state: &mut PigState, moves: &str) {
+    for m in moves.split_whitespace() {
+      let m = state.parse_move(m).unwrap();
+      state.play(m).unwrap();
+    }
+  }
+
+  #[test]
+  fn turns() {
+    let mut state = Pig::new(10).new_game();
+    assert!(state.parse_move("hold").is_err());
+    assert!(state.parse_move("3").is_err());
+    assert!(state.chances().is_empty());
+    play(&mut state, "roll");
+    assert!(state.is_chance());
+    assert_eq!(6, state.chances().len());
+    assert!(state.parse_move("roll").is_err());
+    assert!(state.parse_move("7").is_err());
+
+    play(&mut state, "4 roll 5 hold");
+    assert_eq!([9, 0], state.scores);
+    assert!(!state.player());
+    play(&mut state, "roll 6 roll 1");
+    assert_eq!([9, 0], state.scores);
+    assert!(state.player());
+    play(&mut state, "roll 2");
+    assert!(!state.is_terminal());
+    play(&mut state, "hold");
+    assert_eq!(Some(1.0), state.payoff());
+    assert_eq!(0, state.iter_moves().count());
+  }
+
+  #[test]
+  fn undo() {
+    let mut state = Pig::new(10).new_game();
+    play(&mut state, "roll 4 roll 6");
+    let hash = state.hash();
+    play(&mut state, "hold");
+    assert!(state.is_terminal());
+    state.undo(PigMove::Hold).unwrap();
+    assert_eq!(hash, state.hash());
+    assert!(!state.is_terminal());
+    assert!(state.undo(PigMove::Die(4)).is_err());
+
+    play(&mut state, "roll 1");
+    assert!(!state.player());
+    state.undo(PigMove::Die(1)).unwrap();
+    assert!(state.player());
+    assert!(state.is_chance());
+    assert_eq!(10, state.turn_total);
+  }
+
+  #[test]
+  fn random_games() {
+    let mut rng = SmallRng::from_entropy();
+    let game = Pig::new(30);
+    for _ in 0..10 {
+      let mut state = game.new_game();
+      let mut moves = Vec::new();
+      while let Some(m) = state.get_random_move(&mut rng) {
+        state.play(m).unwrap();
+        moves.push(m);
+      }
+      assert!(state.scores.iter().any(|&score| score >= 30));
+      for &m in moves.iter().rev() {
+        state.undo(m).unwrap();
+      }
+      assert_eq!(game.new_game().hash(), state.hash());
+    }
+  }
+
+  #[test]
+  fn default_goals() {
+    let game = Pig::default(20);
+    assert!(std::ptr::eq(game, Pig::default(20)));
+    let mut state = game.new_game();
+    play(&mut state, "roll 6 roll 6 roll 6 hold roll 1 roll 2");
+    assert!(!state.is_terminal());
+    play(&mut state, "hold");
+    assert_eq!(Some(1.0), state.payoff());
+  }
+
+}  // mod test
@@ -0,0 +1,488 @@
+//! Kuhn and Leduc poker, the small poker games used as benchmarks for the
+//! algorithms for games with imperfect information.
+//!
+//! Both players ante one chip and get a private card. In Kuhn poker the deck
+//! is J, Q, K, and there is a single betting round, where a player may bet one
+//! chip. In Leduc poker the deck has two cards of every rank, and after the
+//! first betting round a public card is dealt. The bets are 2 and 4 chips in
+//! the two rounds, and there are at most two bets (a bet and a raise) in a
+//! round. A player, who pairs the public card, wins at the showdown, otherwise
+//! the higher card wins.
+//!
+//! The cards are dealt in chance nodes. The first player starts every betting
+//! round.
+//!
+//! These games are only meant for the CFR solver in `crate::cfr`. The states
+//! expose the hidden cards of both players, and the payoffs are counted in
+//! chips rather than lying in [-1, 1], so the minimax and MCTS agents can't
+//! play them. That's why poker has no `GameSpec`.
+
+use lazy_static::lazy_static;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::fmt;
+
+use crate::def::{Game, ImperfectInfoState, State};
+
+const RANKS: [char; 3] = ['J', 'Q', 'K'];
+const ANTE: u32 = 1;
+
+lazy_static! {
+  static ref KUHN: Poker = Poker::new_kuhn();
+  static ref LEDUC: Poker = Poker::new_leduc();
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Rules {
+  // Number of the cards of every rank in the deck.
+  copies: u8,
+  rounds: usize,
+  bets: [u32; 2],
+  max_bets: u8,
+}
+
+pub struct Poker {
+  rules: Rules,
+}
+
+impl Poker {
+  pub fn new_kuhn() -> Poker {
+    Poker {
+      rules: Rules {
+        copies: 1,
+        rounds: 1,
+        bets: [1, 1],
+        max_bets: 1,
+      },
+    }
+  }
+
+  pub fn new_leduc() -> Poker {
+    Poker {
+      rules: Rules {
+        copies: 2,
+        rounds: 2,
+        bets: [2, 4],
+        max_bets: 2,
+      },
+    }
+  }
+
+  pub fn kuhn() -> &'static Poker {
+    &*KUHN
+  }
+
+  pub fn leduc() -> &'static Poker {
+    &*LEDUC
+  }
+}
+
+impl Game for Poker {
+  type State = PokerState;
+
+  fn new_game(&self) -> PokerState {
+    PokerState::new(self.rules)
+  }
+}
+
+/// `Call` is a check when there is no bet to call, and `Raise` is a bet when
+/// there is no bet yet. `Deal` is a card dealt by chance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PokerMove {
+  Deal(u8),
+  Fold,
+  Call,
+  Raise,
+}
+
+impl fmt::Display for PokerMove {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PokerMove::Deal(rank) => write!(f, "{}", RANKS[*rank as usize]),
+      PokerMove::Fold => write!(f, "fold"),
+      PokerMove::Call => write!(f, "call"),
+      PokerMove::Raise => write!(f, "raise"),
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct PokerState {
+  rules: Rules,
+  // The private cards of the first and the second player.
+  cards: [Option<u8>; 2],
+  public: Option<u8>,
+  round: usize,
+  // The chips put in the pot by the first and the second player.
+  chips: [u32; 2],
+  // Number of the bets and raises in the current round.
+  bets: u8,
+  // Number of the actions in the current round.
+  actions: u8,
+  player: bool,
+  // The player, who has folded.
+  folded: Option<bool>,
+  showdown: bool,
+  history: Vec<PokerMove>,
+}
+
+impl PokerState {
+  fn new(rules: Rules) -> PokerState {
+    PokerState {
+      rules,
+      cards: [None, None],
+      public: None,
+      round: 0,
+      chips: [ANTE, ANTE],
+      bets: 0,
+      actions: 0,
+      player: true,
+      folded: None,
+      showdown: false,
+      history: Vec::new(),
+    }
+  }
+
+  fn index(player: bool) -> usize {
+    if player { 0 } else { 1 }
+  }
+
+  // Whether there is a bet to call.
+  fn facing_bet(&self) -> bool {
+    self.chips[0] != self.chips[1]
+  }
+
+  // The numbers of the cards of every rank left in the deck.
+  fn deck(&self) -> Vec<u8> {
+    let mut deck = vec![self.rules.copies; RANKS.len()];
+    for rank in self.cards.iter().chain(Some(&self.public)).flatten() {
+      deck[*rank as usize] -= 1;
+    }
+    deck
+  }
+
+  fn end_round(&mut self) {
+    self.round += 1;
+    self.bets = 0;
+    self.actions = 0;
+    self.player = true;
+    if self.round == self.rules.rounds {
+      self.showdown = true;
+    }
+  }
+
+  // The strength of the player's hand at the showdown.
+  fn strength(&self, player: bool) -> u8 {
+    let card = self.cards[Self::index(player)].unwrap();
+    if Some(card) == self.public {
+      RANKS.len() as u8 + card
+    } else {
+      card
+    }
+  }
+
+  /// The chips won by the first player, when the game is over.
+  fn winnings(&self) -> Option<f32> {
+    let winner = match self.folded {
+      Some(player) => Some(!player),
+      None if self.showdown => {
+        let (first, second) = (self.strength(true), self.strength(false));
+        if first == second {
+          None
+        } else {
+          Some(first > second)
+        }
+      }
+      None => return None,
+    };
+    Some(match winner {
+      Some(true) => self.chips[1] as f32,
+      Some(false) => -(self.chips[0] as f32),
+      None => 0.0,
+    })
+  }
+}
+
+impl State for PokerState {
+  type Move = PokerMove;
+
+  fn player(&self) -> bool {
+    self.player
+  }
+
+  fn is_terminal(&self) -> bool {
+    self.folded.is_some() || self.showdown
+  }
+
+  /// The payoff is the number of chips won by the first player, up to 13 in
+  /// Leduc poker. Unlike the other games, it isn't limited to [-1, 1].
+  fn payoff(&self) -> Option<f32> {
+    self.winnings()
+  }
+
+  fn is_chance(&self) -> bool {
+    !self.is_terminal()
+      && (self.cards[1].is_none()
+        || (self.round > 0 && self.public.is_none()))
+  }
+
+  fn chances(&self) -> Vec<(PokerMove, f32)> {
+    if !self.is_chance() {
+      return Vec::new();
+    }
+    let deck = self.deck();
+    let total: u8 = deck.iter().sum();
+    deck
+      .iter()
+      .enumerate()
+      .filter(|&(_, &count)| count > 0)
+      .map(|(rank, &count)| {
+        (PokerMove::Deal(rank as u8), count as f32 / total as f32)
+      })
+      .collect()
+  }
+
+  fn iter_moves<'s>(&'s self) -> Box<Iterator<Item = PokerMove> + 's> {
+    if self.is_terminal() {
+      return Box::new(None.into_iter());
+    }
+    if self.is_chance() {
+      return Box::new(self.chances().into_iter().map(|(m, _)| m));
+    }
+    let mut moves = Vec::with_capacity(3);
+    if self.facing_bet() {
+      moves.push(PokerMove::Fold);
+    }
+    moves.push(PokerMove::Call);
+    if self.bets < self.rules.max_bets {
+      moves.push(PokerMove::Raise);
+    }
+    Box::new(moves.into_iter())
+  }
+
+  fn get_random_move<R: Rng>(&self, rng: &mut R) -> Option<PokerMove> {
+    if self.is_chance() {
+      let chances = self.chances();
+      return chances.choose_weighted(rng, |c| c.1).ok().map(|c| c.0);
+    }
+    let moves: Vec<_> = self.iter_moves().collect();
+    moves.choose(rng).cloned()
+  }
+
+  fn play(&mut self, m: PokerMove) -> Result<(), &'static str> {
+    if self.is_terminal() {
+      return Err("Trying to make a move in a terminal state.");
+    }
+    if !self.iter_moves().any(|legal| legal == m) {
+      return Err("Illegal move.");
+    }
+    let (own, other) = if self.player { (0, 1) } else { (1, 0) };
+    match m {
+      PokerMove::Deal(rank) => {
+        if self.cards[0].is_none() {
+          self.cards[0] = Some(rank);
+        } else if self.cards[1].is_none() {
+          self.cards[1] = Some(rank);
+        } else {
+          self.public = Some(rank);
+        }
+      }
+      PokerMove::Fold => self.folded = Some(self.player),
+      PokerMove::Call => {
+        let facing_bet = self.facing_bet();
+        self.chips[own] = self.chips[other];
+        self.actions += 1;
+        self.player = !self.player;
+        // A call ends the round, as do two checks.
+        if facing_bet || self.actions == 2 {
+          self.end_round();
+        }
+      }
+      PokerMove::Raise => {
+        self.chips[own] = self.chips[other] + self.rules.bets[self.round];
+        self.bets += 1;
+        self.actions += 1;
+        self.player = !self.player;
+      }
+    }
+    self.history.push(m);
+    Ok(())
+  }
+
+  /// Undoes the move by replaying the game without it.
+  fn undo(&mut self, m: PokerMove) -> Result<(), &'static str> {
+    if self.history.last() != Some(&m) {
+      return Err("Can't undo move");
+    }
+    let mut history = std::mem::take(&mut self.history);
+    history.pop();
+    *self = PokerState::new(self.rules);
+    for m in history {
+      self.play(m).unwrap();
+    }
+    Ok(())
+  }
+
+  fn parse_move(&self, move_str: &str) -> Result<PokerMove, &'static str> {
+    let s = move_str.to_lowercase();
+    let m = match s.as_str() {
+      "fold" => PokerMove::Fold,
+      "call" | "check" => PokerMove::Call,
+      "raise" | "bet" => PokerMove::Raise,
+      _ => {
+        let rank = RANKS
+          .iter()
+          .position(|r| r.to_lowercase().to_string() == s)
+          .ok_or("Error parsing poker move.")?;
+        PokerMove::Deal(rank as u8)
+      }
+    };
+    if self.iter_moves().any(|legal| legal == m) {
+      Ok(m)
+    } else {
+      Err("Illegal move.")
+    }
+  }
+}
+
+impl ImperfectInfoState for PokerState {
+  /// The private card of the player, the public card, if it is dealt, and
+  /// the actions of the players, e.g. "K:rc" or "JQ:rc/cr".
+  type InfoSet = String;
+
+  fn info_set(&self) -> String {
+    let mut info = String::new();
+    let cards = [self.cards[Self::index(self.player)], self.public];
+    for rank in cards.iter().filter_map(|&c| c) {
+      info.push(RANKS[rank as usize]);
+    }
+    info.push(':');
+    let mut deals = 0;
+    for m in self.history.iter() {
+      match m {
+        PokerMove::Deal(_) => {
+          deals += 1;
+          // The public card.
+          if deals > 2 {
+            info.push('/');
+          }
+        }
+        PokerMove::Fold => info.push('f'),
+        PokerMove::Call => info.push('c'),
+        PokerMove::Raise => info.push('r'),
+      }
+    }
+    info
+  }
+}
+
+impl fmt::Display for PokerState {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let card = |c: Option<u8>| c.map_or('?', |rank| RANKS[rank as usize]);
+    write!(
+      f,
+      "Cards: {} {}, public: {}, chips: {} {}, moves:",
+      card(self.cards[0]),
+      card(self.cards[1]),
+      card(self.public),
+      self.chips[0],
+      self.chips[1]
+    )?;
+    for m in self.history.iter() {
+      write!(f, " {}", m)?;
+    }
+    writeln!(f)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use rand::FromEntropy;
+  use rand::rngs::SmallRng;
+
+  use super::*;
+
+  fn play(state: &mut PokerState, moves: &str) {
+    for m in moves.split_whitespace() {
+      let m = state.parse_move(m).unwrap();
+      state.play(m).unwrap();
+    }
+  }
+
+  #[test]
+  fn kuhn() {
+    let mut state = Poker::kuhn().new_game();
+    assert!(state.is_chance());
+    assert_eq!(3, state.chances().len());
+    play(&mut state, "q");
+    let chances = state.chances();
+    assert_eq!(
+      vec![(PokerMove::Deal(0), 0.5), (PokerMove::Deal(2), 0.5)],
+      chances
+    );
+    play(&mut state, "k");
+    assert!(!state.is_chance());
+    assert_eq!("Q:", state.info_set());
+    play(&mut state, "check");
+    assert_eq!("K:c", state.info_set());
+    assert!(state.parse_move("fold").is_err());
+    play(&mut state, "bet");
+    assert!(state.parse_move("raise").is_err());
+    play(&mut state, "call");
+    assert_eq!(Some(-2.0), state.payoff());
+
+    state.undo(PokerMove::Call).unwrap();
+    play(&mut state, "fold");
+    assert_eq!(Some(-1.0), state.payoff());
+    let mut state = Poker::kuhn().new_game();
+    play(&mut state, "q j check check");
+    assert_eq!(Some(1.0), state.payoff());
+  }
+
+  #[test]
+  fn leduc() {
+    let mut state = Poker::leduc().new_game();
+    play(&mut state, "j");
+    assert_eq!(
+      vec![
+        (PokerMove::Deal(0), 0.2),
+        (PokerMove::Deal(1), 0.4),
+        (PokerMove::Deal(2), 0.4),
+      ],
+      state.chances()
+    );
+    play(&mut state, "k bet raise");
+    assert!(state.parse_move("raise").is_err());
+    play(&mut state, "call");
+    assert_eq!([5, 5], state.chips);
+    assert!(state.is_chance());
+    play(&mut state, "j");
+    assert_eq!("JJ:rrc/", state.info_set());
+    play(&mut state, "check bet");
+    assert_eq!("JJ:rrc/cr", state.info_set());
+    play(&mut state, "call");
+    // The pair of jacks beats the king.
+    assert_eq!(Some(9.0), state.payoff());
+  }
+
+  #[test]
+  fn random_games() {
+    let mut rng = SmallRng::from_entropy();
+    for &game in [Poker::kuhn(), Poker::leduc()].iter() {
+      for _ in 0..20 {
+        let mut state = game.new_game();
+        let mut moves = Vec::new();
+        while let Some(m) = state.get_random_move(&mut rng) {
+          state.play(m).unwrap();
+          moves.push(m);
+        }
+        assert!(state.payoff().is_some());
+        for &m in moves.iter().rev() {
+          state.undo(m).unwrap();
+        }
+        assert!(state.history.is_empty());
+      }
+    }
+  }
+
+}  // mod test
@@ -0,0 +1,524 @@
+//! Quarto on the 4x4 board with the 16 pieces.
+//!
+//! Every piece has four binary attributes (tall or short, dark or light,
+//! square or round, hollow or solid), so the pieces are numbered 0-15 by the
+//! bits of the attributes. A player wins by completing a line of four pieces,
+//! that share at least one attribute. The pieces are common, but a player
+//! places the piece chosen by the opponent, so a turn consists of placing the
+//! received piece and choosing the piece for the opponent. The very first
+//! turn is only the choice of a piece.
+
+use lazy_static::lazy_static;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::fmt;
+
+use crate::board::{point_to_a, Board, Cell};
+use crate::def::{FeatureExtractor, Game, Regression, State};
+use crate::spec::FeatureExtractorSpec;
+use crate::status::Status;
+use crate::zobrist;
+
+const SIZE: u32 = 4;
+const NPIECES: u8 = 16;
+const ALL_ATTRIBUTES: u8 = 0b1111;
+
+// Rows, columns and the two diagonals.
+const LINES: [[usize; 4]; 10] = [
+  [0, 1, 2, 3],
+  [4, 5, 6, 7],
+  [8, 9, 10, 11],
+  [12, 13, 14, 15],
+  [0, 4, 8, 12],
+  [1, 5, 9, 13],
+  [2, 6, 10, 14],
+  [3, 7, 11, 15],
+  [0, 5, 10, 15],
+  [3, 6, 9, 12],
+];
+
+lazy_static! {
+  static ref INSTANCE: Quarto = Quarto::new();
+}
+
+pub struct Quarto {}
+
+impl Quarto {
+  pub fn new() -> Quarto {
+    Quarto {}
+  }
+
+  pub fn default() -> &'static Quarto {
+    &*INSTANCE
+  }
+}
+
+impl Game for Quarto {
+  type State = QuartoState;
+
+  fn new_game(&self) -> QuartoState {
+    QuartoState::new()
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum QuartoMove {
+  /// The first turn of the game: the piece for the opponent.
+  Give(u8),
+  /// Places the received piece at the point and gives the next piece to the
+  /// opponent. No piece is given, if the placement ends the game.
+  Place(u8, Option<u8>),
+}
+
+fn format_piece(piece: u8) -> String {
+  format!("{:04b}", piece)
+}
+
+fn parse_piece(s: &str) -> Result<u8, &'static str> {
+  if s.len() != 4 {
+    return Err("Error parsing Quarto piece.");
+  }
+  u8::from_str_radix(s, 2).map_err(|_| "Error parsing Quarto piece.")
+}
+
+impl fmt::Display for QuartoMove {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      QuartoMove::Give(piece) => write!(f, "give {}", format_piece(piece)),
+      QuartoMove::Place(point, next) => {
+        write!(f, "{}", point_to_a(point as usize, SIZE))?;
+        if let Some(piece) = next {
+          write!(f, " {}", format_piece(piece))?;
+        }
+        Ok(())
+      }
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct QuartoCell(Option<u8>);
+
+impl Cell for QuartoCell {
+  fn empty() -> QuartoCell {
+    QuartoCell(None)
+  }
+
+  fn is_empty(self) -> bool {
+    self.0.is_none()
+  }
+
+  fn ascii(self) -> char {
+    match self.0 {
+      Some(piece) => std::char::from_digit(piece as u32, 16).unwrap(),
+      None => '.',
+    }
+  }
+
+  fn unicode(self) -> char {
+    match self.0 {
+      Some(piece) => std::char::from_digit(piece as u32, 16).unwrap(),
+      None => '·',
+    }
+  }
+}
+
+// Whether the pieces share at least one attribute.
+fn share_attribute(pieces: &[u8]) -> bool {
+  let ones = pieces.iter().fold(ALL_ATTRIBUTES, |a, &p| a & p);
+  let zeros = pieces.iter().fold(ALL_ATTRIBUTES, |a, &p| a & !p);
+  ones != 0 || zeros != 0
+}
+
+fn piece_key(point: usize, piece: u8) -> u64 {
+  zobrist::point_key(point * NPIECES as usize + piece as usize, true)
+}
+
+// The piece to be placed is hashed as if it stood on an extra point.
+fn given_key(piece: u8) -> u64 {
+  piece_key((SIZE * SIZE) as usize, piece)
+}
+
+#[derive(Clone, Debug)]
+pub struct QuartoState {
+  board: Board<QuartoCell>,
+  // Bit mask of the pieces, that are neither placed nor given.
+  available: u16,
+  // The piece to be placed by the acting player.
+  given: Option<u8>,
+  status: Status,
+  hash: u64,
+}
+
+impl QuartoState {
+  fn new() -> QuartoState {
+    QuartoState {
+      board: Board::new_empty(SIZE, SIZE),
+      available: 0xffff,
+      given: None,
+      status: Status::new(),
+      hash: 0,
+    }
+  }
+
+  fn available_pieces(&self) -> Vec<u8> {
+    (0..NPIECES).filter(|&p| self.available & (1 << p) != 0).collect()
+  }
+
+  fn piece(&self, point: usize) -> Option<u8> {
+    self.board.get(point).unwrap().0
+  }
+
+  // The pieces in the line, which are placed already.
+  fn line_pieces(&self, line: &[usize]) -> Vec<u8> {
+    line.iter().filter_map(|&p| self.piece(p)).collect()
+  }
+
+  // Whether placing the piece at the empty point completes a line.
+  fn completes_line(&self, point: usize, piece: u8) -> bool {
+    LINES.iter().filter(|line| line.contains(&point)).any(|line| {
+      let mut pieces = self.line_pieces(line);
+      pieces.push(piece);
+      pieces.len() == 4 && share_attribute(&pieces)
+    })
+  }
+
+  fn empty_points(&self) -> Vec<usize> {
+    (0..self.board.len()).filter(|&p| self.piece(p).is_none()).collect()
+  }
+
+  fn legal_moves(&self) -> Vec<QuartoMove> {
+    let given = match self.given {
+      Some(piece) => piece,
+      None => {
+        return self
+          .available_pieces()
+          .into_iter()
+          .map(QuartoMove::Give)
+          .collect();
+      }
+    };
+    let available = self.available_pieces();
+    let mut moves = Vec::new();
+    for point in self.empty_points() {
+      if self.completes_line(point, given) || available.is_empty() {
+        moves.push(QuartoMove::Place(point as u8, None));
+      } else {
+        moves.extend(
+          available
+            .iter()
+            .map(|&next| QuartoMove::Place(point as u8, Some(next))),
+        );
+      }
+    }
+    moves
+  }
+
+  fn give(&mut self, piece: u8) {
+    self.available &= !(1 << piece);
+    self.given = Some(piece);
+    self.hash ^= given_key(piece);
+  }
+
+  fn take_back(&mut self, piece: u8) {
+    self.available |= 1 << piece;
+    self.given = None;
+    self.hash ^= given_key(piece);
+  }
+}
+
+impl State for QuartoState {
+  type Move = QuartoMove;
+
+  fn player(&self) -> bool {
+    self.status.player()
+  }
+
+  fn is_terminal(&self) -> bool {
+    self.status.is_terminal()
+  }
+
+  fn payoff(&self) -> Option<f32> {
+    self.status.payoff()
+  }
+
+  fn iter_moves<'s>(&'s self) -> Box<Iterator<Item = QuartoMove> + 's> {
+    if self.is_terminal() {
+      return Box::new(None.into_iter());
+    }
+    Box::new(self.legal_moves().into_iter())
+  }
+
+  fn get_random_move<R: Rng>(&self, rng: &mut R) -> Option<QuartoMove> {
+    let moves: Vec<_> = self.iter_moves().collect();
+    moves.choose(rng).cloned()
+  }
+
+  fn play(&mut self, m: QuartoMove) -> Result<(), &'static str> {
+    if self.is_terminal() {
+      return Err("Trying to make a move in a terminal state.");
+    }
+    if !self.legal_moves().contains(&m) {
+      return Err("Illegal move.");
+    }
+    match m {
+      QuartoMove::Give(piece) => self.give(piece),
+      QuartoMove::Place(point, next) => {
+        let point = point as usize;
+        let piece = self.given.unwrap();
+        let won = self.completes_line(point, piece);
+        self.hash ^= given_key(piece) ^ piece_key(point, piece);
+        self.given = None;
+        self.board.set(point, QuartoCell(Some(piece)));
+        match next {
+          Some(next) => self.give(next),
+          None if won => self.status.set_winner(self.player()),
+          None => self.status.set_draw(),
+        }
+      }
+    }
+    self.hash ^= zobrist::player_key();
+    self.status.switch_player();
+
+    Ok(())
+  }
+
+  fn undo(&mut self, m: QuartoMove) -> Result<(), &'static str> {
+    match m {
+      QuartoMove::Give(piece) => {
+        if self.given != Some(piece) || self.board.iter().any(|c| c.0.is_some())
+        {
+          return Err("Can't undo move");
+        }
+        self.take_back(piece);
+      }
+      QuartoMove::Place(point, next) => {
+        let point = point as usize;
+        let piece = match self.piece(point) {
+          Some(piece) if self.given == next => piece,
+          _ => return Err("Can't undo move"),
+        };
+        if let Some(next) = next {
+          self.take_back(next);
+        }
+        self.board.set(point, QuartoCell(None));
+        self.given = Some(piece);
+        self.hash ^= given_key(piece) ^ piece_key(point, piece);
+      }
+    }
+    self.hash ^= zobrist::player_key();
+    self.status.undo_terminal();
+    self.status.switch_player();
+
+    Ok(())
+  }
+
+  fn parse_move(&self, move_str: &str) -> Result<QuartoMove, &'static str> {
+    let tokens: Vec<&str> = move_str.split_whitespace().collect();
+    let m = match tokens.as_slice() {
+      ["give", piece] => QuartoMove::Give(parse_piece(piece)?),
+      [point] | [point, _] => {
+        let point = self
+          .board
+          .parse_point(point)
+          .ok_or("Error parsing Quarto move.")?;
+        let next = match tokens.get(1) {
+          Some(piece) => Some(parse_piece(piece)?),
+          None => None,
+        };
+        QuartoMove::Place(point as u8, next)
+      }
+      _ => return Err("Error parsing Quarto move."),
+    };
+    if self.iter_moves().any(|legal| legal == m) {
+      Ok(m)
+    } else {
+      Err("Illegal move.")
+    }
+  }
+
+  fn hash(&self) -> Option<u64> {
+    Some(self.hash)
+  }
+}
+
+impl fmt::Display for QuartoState {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.board.format(true))?;
+    match self.given {
+      Some(piece) => writeln!(f, "Piece to place: {}", format_piece(piece)),
+      None => Ok(()),
+    }
+  }
+}
+
+/// Features of the lines with the pieces, that share an attribute: bias,
+/// numbers of such lines with two and with three pieces, number of the
+/// available pieces, that would complete a line with three pieces, whether
+/// the piece to be placed completes a line, and the number of empty points.
+#[derive(Clone)]
+pub struct QuartoFeatureExtractor {}
+
+impl QuartoFeatureExtractor {
+  pub fn new() -> Self {
+    QuartoFeatureExtractor {}
+  }
+}
+
+impl FeatureExtractor<QuartoState> for QuartoFeatureExtractor {
+  fn nfeatures(&self) -> usize {
+    6
+  }
+
+  fn extract(&self, state: &QuartoState) -> Vec<f32> {
+    let shared_lines = |n| {
+      LINES
+        .iter()
+        .map(|line| state.line_pieces(line))
+        .filter(|pieces| pieces.len() == n && share_attribute(pieces))
+        .count() as f32
+    };
+    let empty = state.empty_points();
+    let completes =
+      |piece| empty.iter().any(|&p| state.completes_line(p, piece));
+    let unsafe_pieces = state
+      .available_pieces()
+      .into_iter()
+      .filter(|&piece| completes(piece))
+      .count() as f32;
+    let given_completes = match state.given {
+      Some(piece) if completes(piece) => 1.0,
+      _ => 0.0,
+    };
+
+    vec![
+      1.0,
+      shared_lines(2),
+      shared_lines(3),
+      unsafe_pieces,
+      given_completes,
+      empty.len() as f32,
+    ]
+  }
+
+  fn spec(&self) -> FeatureExtractorSpec {
+    FeatureExtractorSpec::Quarto
+  }
+
+  fn report<R: Regression>(&self, regression: &R) {
+    let b = regression.params();
+    println!("bias: {:>6.3}", b[0]);
+    println!("shared lines: two {:>6.3}, three {:>6.3}", b[1], b[2]);
+    println!("unsafe pieces: {:>6.3}", b[3]);
+    println!("piece to place completes: {:>6.3}", b[4]);
+    println!("empty points: {:>6.3}\n", b[5]);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use rand::FromEntropy;
+  use rand::rngs::SmallRng;
+
+  use super::*;
+
+  fn play(state: &mut QuartoState, moves: &[&str]) -> Vec<QuartoMove> {
+    moves
+      .iter()
+      .map(|m| {
+        let m = state.parse_move(m).unwrap();
+        state.play(m).unwrap();
+        m
+      })
+      .collect()
+  }
+
+  #[test]
+  fn turns() {
+    let mut state = Quarto::new().new_game();
+    assert_eq!(16, state.iter_moves().count());
+    assert!(state.parse_move("a1 0000").is_err());
+    play(&mut state, &["give 0101"]);
+    assert!(!state.player());
+    assert_eq!(16 * 15, state.iter_moves().count());
+    assert!(state.parse_move("a1 0101").is_err());
+    assert!(state.parse_move("a1").is_err());
+
+    play(&mut state, &["a1 1111"]);
+    assert!(state.player());
+    assert_eq!(Some(QuartoCell(Some(5))), state.board.get_a("a1"));
+    assert_eq!(Some(15), state.given);
+    assert_eq!(15 * 14, state.iter_moves().count());
+    assert_eq!("b1 0000", state.parse_move("b1 0000").unwrap().to_string());
+  }
+
+  #[test]
+  fn win() {
+    let mut state = Quarto::new().new_game();
+    play(&mut state, &["give 0010", "a1 0011", "b1 0110", "c1 1010"]);
+    // All the pieces in the first row have the second bit set.
+    assert_eq!(Some(10), state.given);
+    let moves: Vec<_> = state
+      .iter_moves()
+      .filter(|&m| m == QuartoMove::Place(3, None)
+                   || m == QuartoMove::Place(3, Some(0)))
+      .collect();
+    assert_eq!(vec![QuartoMove::Place(3, None)], moves);
+    play(&mut state, &["d1"]);
+    assert!(state.is_terminal());
+    assert_eq!(Some(1.0), state.payoff());
+  }
+
+  #[test]
+  fn undo() {
+    let mut state = Quarto::new().new_game();
+    let hash = state.hash();
+    let moves = play(
+      &mut state,
+      &["give 0010", "a1 0011", "b1 0110", "c1 1010", "d1"],
+    );
+    assert!(state.undo(QuartoMove::Give(2)).is_err());
+    for &m in moves.iter().rev() {
+      state.undo(m).unwrap();
+    }
+    assert_eq!(hash, state.hash());
+    assert_eq!(16, state.iter_moves().count());
+  }
+
+  #[test]
+  fn random_games() {
+    let mut rng = SmallRng::from_entropy();
+    let game = Quarto::new();
+    for _ in 0..50 {
+      let mut state = game.new_game();
+      let mut moves = Vec::new();
+      while let Some(m) = state.get_random_move(&mut rng) {
+        state.play(m).unwrap();
+        moves.push(m);
+      }
+      assert!(state.payoff().is_some());
+      if state.payoff() == Some(0.0) {
+        assert_eq!(17, moves.len());
+      }
+      for &m in moves.iter().rev() {
+        state.undo(m).unwrap();
+      }
+      assert_eq!(game.new_game().hash(), state.hash());
+      assert_eq!(0xffff, state.available);
+    }
+  }
+
+  #[test]
+  fn features() {
+    let mut state = Quarto::new().new_game();
+    let extractor = QuartoFeatureExtractor::new();
+    assert_eq!(vec![1.0, 0.0, 0.0, 0.0, 0.0, 16.0], extractor.extract(&state));
+    play(&mut state, &["give 0010", "a1 0011", "b1 0110"]);
+    assert_eq!(vec![1.0, 1.0, 0.0, 0.0, 0.0, 14.0], extractor.extract(&state));
+    // The first row 0010 0011 0110 is completed by the pieces with the second
+    // bit set or the fourth bit clear. 1000 has neither.
+    play(&mut state, &["c1 1000"]);
+    assert_eq!(vec![1.0, 0.0, 1.0, 9.0, 0.0, 13.0], extractor.extract(&state));
+  }
+
+}  // mod test
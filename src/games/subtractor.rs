@@ -112,6 +112,10 @@ impl State for SubtractorState {
       .parse()
       .map_err(|_| "Error parsing Subtractor move.")
   }
+
+  fn hash(&self) -> Option<u64> {
+    Some(2 * self.number as u64 + if self.player { 0 } else { 1 })
+  }
 }
 
 impl fmt::Display for SubtractorState {
@@ -212,6 +216,23 @@ mod test {
     assert_eq!(Some(-1.0), state.payoff());
   }
 
+  #[test]
+  fn hash() {
+    let game = Subtractor::new(10, 4);
+    let mut state1 = game.new_game();
+    let mut state2 = game.new_game();
+    state1.play(3).unwrap();
+    state2.play(1).unwrap();
+    assert_ne!(state1.hash(), state2.hash());
+    state1.play(1).unwrap();
+    state2.play(3).unwrap();
+    assert_eq!(state1.hash(), state2.hash());
+    state2.undo(3).unwrap();
+    state2.play(2).unwrap();
+    state2.play(1).unwrap();
+    assert_ne!(state1.hash(), state2.hash());
+  }
+
   #[test]
   fn feature_extractor() {
     let game = Subtractor::new(10, 4);
@@ -29,19 +29,40 @@ enum Job {
   Play(Participant, Participant),
 }
 
+const TWO_PLAYERS_ONLY: &str =
+  "Only the games with two players can be played between agents.";
+
+/// Plays a game between two agents and returns the payoff of the first one.
+/// The games with more than two players are rejected, since there is no
+/// agent for the other seats, as well as the agent specs that don't fit the
+/// game.
 pub fn play_game<G: Game>(
   game: &'static G,
   player1: &AgentSpec,
   player2: &AgentSpec,
   output: bool,
-) -> f32 {
-  let agent1 = create_agent(game, player1);
-  let agent2 = create_agent(game, player2);
+) -> Result<f32, &'static str> {
   let mut state = game.new_game();
+  if state.num_players() > 2 {
+    return Err(TWO_PLAYERS_ONLY);
+  }
+  let agent1 = create_agent(game, player1)?;
+  let agent2 = create_agent(game, player2)?;
+  let mut rng = SmallRng::from_entropy();
   while !state.is_terminal() {
     if output {
       println!("{}", state);
     }
+    if state.is_chance() {
+      let m = state.get_random_move(&mut rng).unwrap();
+      if output {
+        println!("Chance: {}", m);
+      }
+      state.play(m).unwrap();
+      agent1.observe_move(m);
+      agent2.observe_move(m);
+      continue;
+    }
     let report = if state.player() {
       agent1.select_move(&state)
     } else {
@@ -51,13 +72,16 @@ pub fn play_game<G: Game>(
     if output {
       println!("{}", report);
     }
-    state.play(report.get_move()).unwrap();
+    let m = report.get_move();
+    state.play(m).unwrap();
+    agent1.observe_move(m);
+    agent2.observe_move(m);
   }
   let payoff = state.payoff().unwrap();
   if output {
     println!("{}\nPayoff: {}", state, payoff);
   }
-  payoff
+  Ok(payoff)
 }
 
 struct Worker<G: Game> {
@@ -89,7 +113,7 @@ impl<G: Game> Worker<G> {
       &player1.agent_spec,
       &player2.agent_spec,
       false,
-    );
+    ).unwrap();
 
     GameResult {
       player1_id: player1.id,
@@ -143,7 +167,15 @@ pub struct Ladder {
 }
 
 impl Ladder {
-  pub fn new<G: Game>(game: &'static G, nthreads: usize) -> Self {
+  /// Starts `nthreads` workers playing the games. Like `play_game`, rejects
+  /// the games with more than two players.
+  pub fn new<G: Game>(
+    game: &'static G,
+    nthreads: usize,
+  ) -> Result<Self, &'static str> {
+    if game.new_game().num_players() > 2 {
+      return Err(TWO_PLAYERS_ONLY);
+    }
     let (jobs_sender, jobs_receiver) = channel();
     let jobs_receiver = Arc::new(Mutex::new(jobs_receiver));
     let (results_sender, results_receiver) = channel();
@@ -157,14 +189,14 @@ impl Ladder {
       ));
     }
 
-    Ladder {
+    Ok(Ladder {
       participants: Vec::new(),
       results: Vec::new(),
       ratings: Ratings::new(true),
       threads,
       jobs_sender,
       results_receiver,
-    }
+    })
   }
 
   /// Adds a new agent to the ladder and returns an id that it was assigned.
@@ -281,6 +313,10 @@ impl Ladder {
         depth: _,
         time_per_move: _,
         evaluator: _,
+        table_size: _,
+        ordering: _,
+        multiplayer: _,
+        threads: _,
         ref name,
       } => name,
       AgentSpec::Mcts {
@@ -288,6 +324,10 @@ impl Ladder {
         time_per_move: _,
         evaluator: _,
         policy: _,
+        selection: _,
+        exploration: _,
+        fpu: _,
+        threads: _,
         ref name,
       } => name,
     }
@@ -310,8 +350,8 @@ impl Drop for Ladder {
 mod test {
   use std::sync::mpsc::channel;
 
-  use crate::games::{Hexapawn, Subtractor};
-  use crate::spec::{AgentSpec, EvaluatorSpec};
+  use crate::games::{Hexapawn, Nim, Pig, Subtractor};
+  use crate::spec::{AgentSpec, EvaluatorSpec, MoveOrdering, Multiplayer};
 
   use super::*;
 
@@ -322,20 +362,55 @@ mod test {
       depth: 3,
       time_per_move: 0.0,
       evaluator: EvaluatorSpec::Terminal,
+      table_size: 0,
+      ordering: MoveOrdering::Random,
+      multiplayer: Multiplayer::MaxN,
+      threads: 0,
       name: "1".to_string(),
     };
     let agent2_spec = AgentSpec::Minimax {
       depth: 10,
       time_per_move: 0.0,
       evaluator: EvaluatorSpec::Terminal,
+      table_size: 0,
+      ordering: MoveOrdering::Random,
+      multiplayer: Multiplayer::MaxN,
+      threads: 0,
       name: "2".to_string(),
     };
     assert_eq!(
       -1.0,
-      play_game(game, &agent1_spec, &agent2_spec, false)
+      play_game(game, &agent1_spec, &agent2_spec, false).unwrap()
     );
   }
 
+  #[test]
+  fn play_pig() {
+    // The die rolls are made by the game loop, not by the agents.
+    let agent_spec = AgentSpec::Minimax {
+      depth: 2,
+      time_per_move: 0.0,
+      evaluator: EvaluatorSpec::Terminal,
+      table_size: 0,
+      ordering: MoveOrdering::Random,
+      multiplayer: Multiplayer::MaxN,
+      threads: 0,
+      name: String::new(),
+    };
+    let payoff =
+      play_game(Pig::default(100), &agent_spec, &AgentSpec::Random, false)
+        .unwrap();
+    assert!(payoff == 1.0 || payoff == -1.0);
+  }
+
+  #[test]
+  fn reject_three_players() {
+    let game = Nim::default(3);
+    let agent_spec = AgentSpec::Random;
+    assert!(play_game(game, &agent_spec, &agent_spec, false).is_err());
+    assert!(Ladder::new(game, 1).is_err());
+  }
+
   #[test]
   fn subtractor_worker() {
     let game = Subtractor::default(21, 4);
@@ -353,6 +428,10 @@ mod test {
         depth: 5,
         time_per_move: 0.0,
         evaluator: EvaluatorSpec::Terminal,
+        table_size: 0,
+        ordering: MoveOrdering::Random,
+        multiplayer: Multiplayer::MaxN,
+        threads: 0,
         name: "2".to_string(),
       },
     };
@@ -374,12 +453,16 @@ mod test {
 
   #[test]
   fn subtractor_ladder() {
-    let mut ladder = Ladder::new(Subtractor::default(21, 4), 2);
+    let mut ladder = Ladder::new(Subtractor::default(21, 4), 2).unwrap();
     let random_id = ladder.add_participant(&AgentSpec::Random);
     let minimax_id = ladder.add_participant(&AgentSpec::Minimax {
       depth: 6,
       time_per_move: 0.0,
       evaluator: EvaluatorSpec::Terminal,
+      table_size: 0,
+      ordering: MoveOrdering::Random,
+      multiplayer: Multiplayer::MaxN,
+      threads: 0,
       name: "2".to_string(),
     });
 
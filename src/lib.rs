@@ -1,8 +1,10 @@
 pub mod agents;
 mod board;
+pub mod cfr;
 pub mod def;
 mod equal_policy;
 pub mod evaluators;
+mod feature_policy;
 #[macro_use]
 pub mod games;
 pub mod ladder;
@@ -11,5 +13,6 @@ mod ratings;
 pub mod registry;
 pub mod spec;
 mod status;
+mod zobrist;
 
 pub use self::evaluators::train_subtractor_eval;
@@ -9,7 +9,7 @@ use gamer::def::Game;
 use gamer::ladder::{play_game, Ladder};
 use gamer::registry::create_training;
 use gamer::spec::{agent_spec_to_json, load_agent_spec, load_training_spec,
-                  AgentSpec, GameSpec};
+                  GameSpec};
 
 fn args_definition() -> clap::App<'static, 'static> {
   App::new("gamer")
@@ -21,7 +21,12 @@ fn args_definition() -> clap::App<'static, 'static> {
         .long("game")
         .value_name("GAME")
         .takes_value(true)
-        .possible_values(&["gomoku", "hexapawn", "subtractor"])
+        .possible_values(&["breakthrough", "breakthrough_6", "chess",
+                           "connect6", "connect_four", "draughts", "go",
+                           "go_13", "go_9", "gomoku", "gomoku_15", "gomoku_9",
+                           "gomoku_swap2", "hex", "hex_noswap", "hexapawn",
+                           "nim", "nim_3", "othello", "pig", "quarto", "renju",
+                           "russian_draughts", "subtractor", "tictactoe"])
         .help("The game to be played."),
     )
     .subcommand(
@@ -164,7 +169,9 @@ fn run_play<G: Game>(game: &'static G, args: &ArgMatches) {
     load_agent_spec(args.value_of("player2").unwrap(), t).unwrap();
   println!("Player 2: {:?}\n", player2_spec);
 
-  play_game(game, &player1_spec, &player2_spec, true);
+  if let Err(e) = play_game(game, &player1_spec, &player2_spec, true) {
+    println!("Error: {}", e);
+  }
 }
 
 fn run_train<G: Game>(game: &'static G, args: &ArgMatches) {
@@ -178,12 +185,7 @@ fn run_train<G: Game>(game: &'static G, args: &ArgMatches) {
 
   let mut trainer = create_training(game, &training_spec);
   trainer.train(steps, t);
-  let agent_spec = AgentSpec::Minimax {
-    depth: 1000,
-    time_per_move: 0.0,
-    evaluator: trainer.build_evaluator().spec(),
-    name: String::new(),
-  };
+  let agent_spec = trainer.build_agent_spec();
 
   let agent_json = agent_spec_to_json(&agent_spec);
   match args.value_of("output") {
@@ -212,7 +214,13 @@ fn run_tournament<G: Game>(game: &'static G, args: &ArgMatches) {
     .unwrap()
     .map(|a| load_agent_spec(a, t).unwrap())
     .collect();
-  let mut ladder = Ladder::new(game, threads);
+  let mut ladder = match Ladder::new(game, threads) {
+    Ok(ladder) => ladder,
+    Err(e) => {
+      println!("Error: {}", e);
+      return;
+    }
+  };
   for agent in agents.iter() {
     let id = ladder.add_participant(agent);
     println!("{}  {:?}", id, agent);
@@ -2,24 +2,32 @@ use std::any::Any;
 use std::mem::transmute;
 use std::time::Duration;
 
-use crate::def::{Agent, Evaluator, FeatureExtractor, Game, Regression, State, Trainer};
+use crate::def::{Agent, Evaluator, FeatureExtractor, Game, Policy, Regression, State,
+                 Trainer};
 use crate::evaluators::{AnnealingTrainer, FeatureEvaluator, LadderAnnealingTrainer,
-                 LinearRegressionTanh, ReinforceTrainer, SamplerEvaluator,
-                 TerminalEvaluator};
-use crate::games::{Gomoku, GomokuLineFeatureExtractor, Hexapawn,
-            HexapawnCompleteExtractor, HexapawnNumberOfPawnsExtractor,
-            Subtractor, SubtractorFeatureExtractor};
+                 LinearRegressionTanh, PolicyTrainer, ReinforceTrainer,
+                 SamplerEvaluator, TerminalEvaluator};
+use crate::games::{Breakthrough, BreakthroughFeatureExtractor, Chess,
+            ChessFeatureExtractor, Connect6,
+            Connect6LineFeatureExtractor, ConnectFour,
+            ConnectFourThreatExtractor, Draughts, DraughtsFeatureExtractor,
+            Gomoku, GomokuLineFeatureExtractor, GomokuProximityPolicy,
+            Hexapawn, HexapawnCompleteExtractor,
+            HexapawnNumberOfPawnsExtractor, Othello, OthelloFeatureExtractor,
+            Quarto, QuartoFeatureExtractor, Subtractor,
+            SubtractorFeatureExtractor};
 use crate::agents::{HumanAgent, MctsAgent, MinimaxAgent, RandomAgent};
 use crate::spec::{AgentSpec, EvaluatorSpec, FeatureExtractorSpec, PolicySpec, RegressionSpec,
            TrainerSpec, TrainingSpec};
 use crate::equal_policy::EqualPolicy;
+use crate::feature_policy::FeaturePolicy;
 
 pub fn create_agent<G: Game>(
   game: &'static G,
   spec: &AgentSpec,
-) -> Box<Agent<G::State>> {
-  match spec {
-    &AgentSpec::Random => Box::new(RandomAgent::new()),
+) -> Result<Box<Agent<G::State>>, &'static str> {
+  let agent = match spec {
+    &AgentSpec::Random => Box::new(RandomAgent::new()) as Box<Agent<G::State>>,
 
     &AgentSpec::Human => Box::new(HumanAgent {}),
 
@@ -27,15 +35,29 @@ pub fn create_agent<G: Game>(
       depth,
       time_per_move,
       evaluator: ref evaluator_spec,
+      table_size,
+      ordering,
+      multiplayer,
+      threads,
       name: _,
     } => {
       let evaluator = create_evaluator(game, evaluator_spec);
       let duration = convert_duration(time_per_move);
-      Box::new(MinimaxAgent::new(
+      let mut agent = MinimaxAgent::new(
         evaluator,
         depth,
         duration,
-      ))
+        table_size,
+        ordering,
+      );
+      agent.set_multiplayer(multiplayer);
+      if threads > 1 {
+        let evaluator_spec = evaluator_spec.clone();
+        agent.set_threads(threads, move || {
+          create_evaluator(game, &evaluator_spec)
+        });
+      }
+      Box::new(agent)
     },
 
     &AgentSpec::Mcts {
@@ -43,20 +65,37 @@ pub fn create_agent<G: Game>(
       time_per_move,
       evaluator: ref evaluator_spec,
       policy: ref policy_spec,
+      selection,
+      exploration,
+      fpu,
+      threads,
       name: _,
     } => {
       let evaluator = create_evaluator(game, evaluator_spec);
-      let policy = create_policy(game, policy_spec);
+      let policy = create_policy(game, policy_spec)?;
       let duration = convert_duration(time_per_move);
-      Box::new(MctsAgent::new(
+      let mut agent = MctsAgent::new(
         policy,
         evaluator,
         if samples == 0 { None } else { Some(samples) },
         duration,
-      ))
+      );
+      agent.set_selection(selection, exploration, fpu);
+      if threads > 1 {
+        let policy_spec = policy_spec.clone();
+        let evaluator_spec = evaluator_spec.clone();
+        agent.set_threads(threads, move || {
+          (
+            create_policy(game, &policy_spec).unwrap(),
+            create_evaluator(game, &evaluator_spec),
+          )
+        });
+      }
+      Box::new(agent)
     }
 
-  }
+  };
+  Ok(agent)
 }
 
 fn create_regression<S: State, FE: FeatureExtractor<S>>(
@@ -70,8 +109,181 @@ fn create_regression<S: State, FE: FeatureExtractor<S>>(
   }
 }
 
-pub fn create_policy<G: Game>(_game: &'static G, _spec: &PolicySpec) -> EqualPolicy {
-  EqualPolicy::new()
+fn create_feature_policy<G: Game, FE: FeatureExtractor<G::State> + 'static>(
+  game: &'static G,
+  extractor: FE,
+  params: &[f32],
+) -> FeaturePolicy<G, FE> {
+  if params.is_empty() {
+    FeaturePolicy::zeros(game, extractor)
+  } else {
+    FeaturePolicy::new(game, extractor, params)
+  }
+}
+
+const WRONG_POLICY_GAME: &str = "The policy is not defined for this game.";
+
+/// Creates the policy from its spec, or returns an error if the spec refers to
+/// a different game, e.g. a Gomoku policy for Connect6.
+pub fn create_policy<G: Game>(
+  game: &'static G,
+  spec: &PolicySpec,
+) -> Result<Box<Policy<G::State>>, &'static str> {
+  let policy = match spec {
+    &PolicySpec::Equal => Box::new(EqualPolicy::new()) as Box<Policy<G::State>>,
+
+    &PolicySpec::Features {
+      extractor: ref extractor_spec,
+      ref params,
+    } => match extractor_spec {
+      &FeatureExtractorSpec::Subtractor(nfeatures) => {
+        let extractor = SubtractorFeatureExtractor::new(nfeatures);
+        let subtractor: &Subtractor =
+          (game as &Any).downcast_ref().ok_or(WRONG_POLICY_GAME)?;
+        let policy = create_feature_policy(subtractor, extractor, params);
+        unsafe {
+          transmute::<
+            Box<Policy<<Subtractor as Game>::State>>,
+            Box<Policy<G::State>>,
+          >(Box::new(policy))
+        }
+      }
+      &FeatureExtractorSpec::GomokuLines(min_len) => {
+        let gomoku: &Gomoku =
+          (game as &Any).downcast_ref().ok_or(WRONG_POLICY_GAME)?;
+        let extractor = GomokuLineFeatureExtractor::new_with_win_len(
+          min_len as usize, gomoku.size(), gomoku.win_len() as usize);
+        let policy = create_feature_policy(gomoku, extractor, params);
+        unsafe {
+          transmute::<
+            Box<Policy<<Gomoku as Game>::State>>,
+            Box<Policy<G::State>>,
+          >(Box::new(policy))
+        }
+      }
+      &FeatureExtractorSpec::Connect6Lines(min_len) => {
+        let extractor = Connect6LineFeatureExtractor::new(min_len as usize);
+        let connect6: &Connect6 =
+          (game as &Any).downcast_ref().ok_or(WRONG_POLICY_GAME)?;
+        let policy = create_feature_policy(connect6, extractor, params);
+        unsafe {
+          transmute::<
+            Box<Policy<<Connect6 as Game>::State>>,
+            Box<Policy<G::State>>,
+          >(Box::new(policy))
+        }
+      }
+      &FeatureExtractorSpec::HexapawnNumberOfPawns => {
+        let extractor = HexapawnNumberOfPawnsExtractor::new();
+        let hexapawn: &Hexapawn =
+          (game as &Any).downcast_ref().ok_or(WRONG_POLICY_GAME)?;
+        let policy = create_feature_policy(hexapawn, extractor, params);
+        unsafe {
+          transmute::<
+            Box<Policy<<Hexapawn as Game>::State>>,
+            Box<Policy<G::State>>,
+          >(Box::new(policy))
+        }
+      }
+      &FeatureExtractorSpec::HexapawnComplete => {
+        let hexapawn: &Hexapawn =
+          (game as &Any).downcast_ref().ok_or(WRONG_POLICY_GAME)?;
+        let extractor = HexapawnCompleteExtractor::new(hexapawn);
+        let policy = create_feature_policy(hexapawn, extractor, params);
+        unsafe {
+          transmute::<
+            Box<Policy<<Hexapawn as Game>::State>>,
+            Box<Policy<G::State>>,
+          >(Box::new(policy))
+        }
+      }
+      &FeatureExtractorSpec::ConnectFourThreats => {
+        let extractor = ConnectFourThreatExtractor::new();
+        let connect_four: &ConnectFour =
+          (game as &Any).downcast_ref().ok_or(WRONG_POLICY_GAME)?;
+        let policy = create_feature_policy(connect_four, extractor, params);
+        unsafe {
+          transmute::<
+            Box<Policy<<ConnectFour as Game>::State>>,
+            Box<Policy<G::State>>,
+          >(Box::new(policy))
+        }
+      }
+      &FeatureExtractorSpec::Othello => {
+        let extractor = OthelloFeatureExtractor::new();
+        let othello: &Othello =
+          (game as &Any).downcast_ref().ok_or(WRONG_POLICY_GAME)?;
+        let policy = create_feature_policy(othello, extractor, params);
+        unsafe {
+          transmute::<
+            Box<Policy<<Othello as Game>::State>>,
+            Box<Policy<G::State>>,
+          >(Box::new(policy))
+        }
+      }
+      &FeatureExtractorSpec::Draughts => {
+        let extractor = DraughtsFeatureExtractor::new();
+        let draughts: &Draughts =
+          (game as &Any).downcast_ref().ok_or(WRONG_POLICY_GAME)?;
+        let policy = create_feature_policy(draughts, extractor, params);
+        unsafe {
+          transmute::<
+            Box<Policy<<Draughts as Game>::State>>,
+            Box<Policy<G::State>>,
+          >(Box::new(policy))
+        }
+      }
+      &FeatureExtractorSpec::Quarto => {
+        let extractor = QuartoFeatureExtractor::new();
+        let quarto: &Quarto =
+          (game as &Any).downcast_ref().ok_or(WRONG_POLICY_GAME)?;
+        let policy = create_feature_policy(quarto, extractor, params);
+        unsafe {
+          transmute::<
+            Box<Policy<<Quarto as Game>::State>>,
+            Box<Policy<G::State>>,
+          >(Box::new(policy))
+        }
+      }
+      &FeatureExtractorSpec::Chess => {
+        let extractor = ChessFeatureExtractor::new();
+        let chess: &Chess =
+          (game as &Any).downcast_ref().ok_or(WRONG_POLICY_GAME)?;
+        let policy = create_feature_policy(chess, extractor, params);
+        unsafe {
+          transmute::<
+            Box<Policy<<Chess as Game>::State>>,
+            Box<Policy<G::State>>,
+          >(Box::new(policy))
+        }
+      }
+      &FeatureExtractorSpec::Breakthrough => {
+        let breakthrough: &Breakthrough =
+          (game as &Any).downcast_ref().ok_or(WRONG_POLICY_GAME)?;
+        let extractor = BreakthroughFeatureExtractor::new(breakthrough);
+        let policy = create_feature_policy(breakthrough, extractor, params);
+        unsafe {
+          transmute::<
+            Box<Policy<<Breakthrough as Game>::State>>,
+            Box<Policy<G::State>>,
+          >(Box::new(policy))
+        }
+      }
+    },
+
+    &PolicySpec::GomokuProximity { ref weights } => {
+      let _gomoku: &Gomoku =
+        (game as &Any).downcast_ref().ok_or(WRONG_POLICY_GAME)?;
+      let policy = GomokuProximityPolicy::new(weights);
+      unsafe {
+        transmute::<
+          Box<Policy<<Gomoku as Game>::State>>,
+          Box<Policy<G::State>>,
+        >(Box::new(policy))
+      }
+    }
+  };
+  Ok(policy)
 }
 
 pub fn create_evaluator<G: Game>(
@@ -99,9 +311,10 @@ pub fn create_evaluator<G: Game>(
         }
       }
       &FeatureExtractorSpec::GomokuLines(min_len) => {
-        let extractor = GomokuLineFeatureExtractor::new(min_len as usize);
-        let regression = create_regression(regression_spec, &extractor);
         let gomoku: &Gomoku = (game as &Any).downcast_ref().unwrap();
+        let extractor = GomokuLineFeatureExtractor::new_with_win_len(
+          min_len as usize, gomoku.size(), gomoku.win_len() as usize);
+        let regression = create_regression(regression_spec, &extractor);
         let evaluator = FeatureEvaluator::new(gomoku, extractor, regression);
         unsafe {
           transmute::<
@@ -110,6 +323,18 @@ pub fn create_evaluator<G: Game>(
           >(Box::new(evaluator))
         }
       }
+      &FeatureExtractorSpec::Connect6Lines(min_len) => {
+        let extractor = Connect6LineFeatureExtractor::new(min_len as usize);
+        let regression = create_regression(regression_spec, &extractor);
+        let connect6: &Connect6 = (game as &Any).downcast_ref().unwrap();
+        let evaluator = FeatureEvaluator::new(connect6, extractor, regression);
+        unsafe {
+          transmute::<
+            Box<Evaluator<<Connect6 as Game>::State>>,
+            Box<Evaluator<G::State>>,
+          >(Box::new(evaluator))
+        }
+      }
       &FeatureExtractorSpec::HexapawnNumberOfPawns => {
         let extractor = HexapawnNumberOfPawnsExtractor::new();
         let regression = create_regression(regression_spec, &extractor);
@@ -134,6 +359,81 @@ pub fn create_evaluator<G: Game>(
           >(Box::new(evaluator))
         }
       }
+      &FeatureExtractorSpec::ConnectFourThreats => {
+        let extractor = ConnectFourThreatExtractor::new();
+        let regression = create_regression(regression_spec, &extractor);
+        let connect_four: &ConnectFour = (game as &Any).downcast_ref().unwrap();
+        let evaluator =
+          FeatureEvaluator::new(connect_four, extractor, regression);
+        unsafe {
+          transmute::<
+            Box<Evaluator<<ConnectFour as Game>::State>>,
+            Box<Evaluator<G::State>>,
+          >(Box::new(evaluator))
+        }
+      }
+      &FeatureExtractorSpec::Othello => {
+        let extractor = OthelloFeatureExtractor::new();
+        let regression = create_regression(regression_spec, &extractor);
+        let othello: &Othello = (game as &Any).downcast_ref().unwrap();
+        let evaluator = FeatureEvaluator::new(othello, extractor, regression);
+        unsafe {
+          transmute::<
+            Box<Evaluator<<Othello as Game>::State>>,
+            Box<Evaluator<G::State>>,
+          >(Box::new(evaluator))
+        }
+      }
+      &FeatureExtractorSpec::Draughts => {
+        let extractor = DraughtsFeatureExtractor::new();
+        let regression = create_regression(regression_spec, &extractor);
+        let draughts: &Draughts = (game as &Any).downcast_ref().unwrap();
+        let evaluator = FeatureEvaluator::new(draughts, extractor, regression);
+        unsafe {
+          transmute::<
+            Box<Evaluator<<Draughts as Game>::State>>,
+            Box<Evaluator<G::State>>,
+          >(Box::new(evaluator))
+        }
+      }
+      &FeatureExtractorSpec::Quarto => {
+        let extractor = QuartoFeatureExtractor::new();
+        let regression = create_regression(regression_spec, &extractor);
+        let quarto: &Quarto = (game as &Any).downcast_ref().unwrap();
+        let evaluator = FeatureEvaluator::new(quarto, extractor, regression);
+        unsafe {
+          transmute::<
+            Box<Evaluator<<Quarto as Game>::State>>,
+            Box<Evaluator<G::State>>,
+          >(Box::new(evaluator))
+        }
+      }
+      &FeatureExtractorSpec::Chess => {
+        let extractor = ChessFeatureExtractor::new();
+        let regression = create_regression(regression_spec, &extractor);
+        let chess: &Chess = (game as &Any).downcast_ref().unwrap();
+        let evaluator = FeatureEvaluator::new(chess, extractor, regression);
+        unsafe {
+          transmute::<
+            Box<Evaluator<<Chess as Game>::State>>,
+            Box<Evaluator<G::State>>,
+          >(Box::new(evaluator))
+        }
+      }
+      &FeatureExtractorSpec::Breakthrough => {
+        let breakthrough: &Breakthrough =
+          (game as &Any).downcast_ref().unwrap();
+        let extractor = BreakthroughFeatureExtractor::new(breakthrough);
+        let regression = create_regression(regression_spec, &extractor);
+        let evaluator =
+          FeatureEvaluator::new(breakthrough, extractor, regression);
+        unsafe {
+          transmute::<
+            Box<Evaluator<<Breakthrough as Game>::State>>,
+            Box<Evaluator<G::State>>,
+          >(Box::new(evaluator))
+        }
+      }
     },
 
     &EvaluatorSpec::Sampler {
@@ -157,13 +457,22 @@ pub fn create_training<G: Game>(
       unsafe { transmute::<Box<Trainer<Subtractor>>, Box<Trainer<G>>>(trainer) }
     }
     &FeatureExtractorSpec::GomokuLines(min_len) => {
-      let extractor = GomokuLineFeatureExtractor::new(min_len as usize);
-      let regression = create_regression(&spec.regression, &extractor);
       let gomoku: &Gomoku = (game as &Any).downcast_ref().unwrap();
+      let extractor = GomokuLineFeatureExtractor::new_with_win_len(
+        min_len as usize, gomoku.size(), gomoku.win_len() as usize);
+      let regression = create_regression(&spec.regression, &extractor);
       let trainer =
         create_trainer(gomoku, extractor, regression, &spec.trainer);
       unsafe { transmute::<Box<Trainer<Gomoku>>, Box<Trainer<G>>>(trainer) }
     }
+    &FeatureExtractorSpec::Connect6Lines(min_len) => {
+      let extractor = Connect6LineFeatureExtractor::new(min_len as usize);
+      let regression = create_regression(&spec.regression, &extractor);
+      let connect6: &Connect6 = (game as &Any).downcast_ref().unwrap();
+      let trainer =
+        create_trainer(connect6, extractor, regression, &spec.trainer);
+      unsafe { transmute::<Box<Trainer<Connect6>>, Box<Trainer<G>>>(trainer) }
+    }
     &FeatureExtractorSpec::HexapawnNumberOfPawns => {
       let extractor = HexapawnNumberOfPawnsExtractor::new();
       let regression = create_regression(&spec.regression, &extractor);
@@ -180,6 +489,64 @@ pub fn create_training<G: Game>(
         create_trainer(hexapawn, extractor, regression, &spec.trainer);
       unsafe { transmute::<Box<Trainer<Hexapawn>>, Box<Trainer<G>>>(trainer) }
     }
+    &FeatureExtractorSpec::ConnectFourThreats => {
+      let extractor = ConnectFourThreatExtractor::new();
+      let regression = create_regression(&spec.regression, &extractor);
+      let connect_four: &ConnectFour = (game as &Any).downcast_ref().unwrap();
+      let trainer =
+        create_trainer(connect_four, extractor, regression, &spec.trainer);
+      unsafe {
+        transmute::<Box<Trainer<ConnectFour>>, Box<Trainer<G>>>(trainer)
+      }
+    }
+    &FeatureExtractorSpec::Othello => {
+      let extractor = OthelloFeatureExtractor::new();
+      let regression = create_regression(&spec.regression, &extractor);
+      let othello: &Othello = (game as &Any).downcast_ref().unwrap();
+      let trainer =
+        create_trainer(othello, extractor, regression, &spec.trainer);
+      unsafe { transmute::<Box<Trainer<Othello>>, Box<Trainer<G>>>(trainer) }
+    }
+    &FeatureExtractorSpec::Draughts => {
+      let extractor = DraughtsFeatureExtractor::new();
+      let regression = create_regression(&spec.regression, &extractor);
+      let draughts: &Draughts = (game as &Any).downcast_ref().unwrap();
+      let trainer =
+        create_trainer(draughts, extractor, regression, &spec.trainer);
+      unsafe {
+        transmute::<Box<Trainer<Draughts>>, Box<Trainer<G>>>(trainer)
+      }
+    }
+    &FeatureExtractorSpec::Quarto => {
+      let extractor = QuartoFeatureExtractor::new();
+      let regression = create_regression(&spec.regression, &extractor);
+      let quarto: &Quarto = (game as &Any).downcast_ref().unwrap();
+      let trainer =
+        create_trainer(quarto, extractor, regression, &spec.trainer);
+      unsafe {
+        transmute::<Box<Trainer<Quarto>>, Box<Trainer<G>>>(trainer)
+      }
+    }
+    &FeatureExtractorSpec::Chess => {
+      let extractor = ChessFeatureExtractor::new();
+      let regression = create_regression(&spec.regression, &extractor);
+      let chess: &Chess = (game as &Any).downcast_ref().unwrap();
+      let trainer =
+        create_trainer(chess, extractor, regression, &spec.trainer);
+      unsafe {
+        transmute::<Box<Trainer<Chess>>, Box<Trainer<G>>>(trainer)
+      }
+    }
+    &FeatureExtractorSpec::Breakthrough => {
+      let breakthrough: &Breakthrough = (game as &Any).downcast_ref().unwrap();
+      let extractor = BreakthroughFeatureExtractor::new(breakthrough);
+      let regression = create_regression(&spec.regression, &extractor);
+      let trainer =
+        create_trainer(breakthrough, extractor, regression, &spec.trainer);
+      unsafe {
+        transmute::<Box<Trainer<Breakthrough>>, Box<Trainer<G>>>(trainer)
+      }
+    }
   }
 }
 
@@ -234,6 +601,18 @@ fn create_trainer<
       temperature,
       ngames,
     )),
+    &TrainerSpec::Policy {
+      mcts_samples,
+      evaluator: ref evaluator_spec,
+      alpha,
+    } => Box::new(PolicyTrainer::new(
+      game,
+      FeaturePolicy::new(game, extractor, regression.params()),
+      evaluator_spec.clone(),
+      mcts_samples,
+      regression.spec().regularization,
+      alpha,
+    )),
   }
 }
 
@@ -251,7 +630,7 @@ fn convert_duration(seconds: f64) -> Option<Duration> {
 #[cfg(test)]
 mod test {
   use crate::def::State;
-  use crate::games::{Gomoku, Hexapawn, Subtractor};
+  use crate::games::{Connect6, Gomoku, GomokuSwap2, Hexapawn, Subtractor};
   use crate::spec::*;
 
   use super::*;
@@ -260,7 +639,7 @@ mod test {
   fn hexapawn_random() {
     let game = Hexapawn::default(3, 3);
     let agent_spec = AgentSpec::Random;
-    let agent = create_agent(game, &agent_spec);
+    let agent = create_agent(game, &agent_spec).unwrap();
     let mut state = game.new_game();
     let report = agent.select_move(&state).unwrap();
     assert!(state.play(report.get_move()).is_ok())
@@ -273,9 +652,13 @@ mod test {
       depth: 10,
       time_per_move: 0.0,
       evaluator: EvaluatorSpec::Terminal,
+      table_size: 0,
+      ordering: MoveOrdering::Random,
+      multiplayer: Multiplayer::MaxN,
+      threads: 0,
       name: String::new(),
     };
-    let agent = create_agent(game, &agent_spec);
+    let agent = create_agent(game, &agent_spec).unwrap();
     let mut state = game.new_game();
     let report = agent.select_move(&state).unwrap();
     assert!(state.play(report.get_move()).is_ok());
@@ -295,11 +678,15 @@ mod test {
           params: vec![0.1, 0.2, 0.3],
         },
       },
+      table_size: 0,
+      ordering: MoveOrdering::Random,
+      multiplayer: Multiplayer::MaxN,
+      threads: 0,
       name: String::new(),
     };
 
     let game = Subtractor::default(21, 4);
-    let agent = create_agent(game, &agent_spec);
+    let agent = create_agent(game, &agent_spec).unwrap();
     let mut state = game.new_game();
     let report = agent.select_move(&state).unwrap();
     assert!(state.play(report.get_move()).is_ok())
@@ -317,16 +704,49 @@ mod test {
           regularization: 0.001,
         },
       },
+      table_size: 0,
+      ordering: MoveOrdering::Random,
+      multiplayer: Multiplayer::MaxN,
+      threads: 0,
       name: String::new(),
     };
 
     let game = Gomoku::default();
-    let agent = create_agent(game, &agent_spec);
+    let agent = create_agent(game, &agent_spec).unwrap();
     let mut state = game.new_game();
     let report = agent.select_move(&state).unwrap();
     assert!(state.play(report.get_move()).is_ok())
   }
 
+  #[test]
+  fn gomoku_features_win_len() {
+    let evaluator_spec = EvaluatorSpec::Features {
+      extractor: FeatureExtractorSpec::GomokuLines(1),
+      regression: RegressionSpec {
+        params: vec![],
+        regularization: 0.001,
+      },
+    };
+
+    // An open five doesn't win if six in a row are needed.
+    let game = Gomoku::default_sized(19, 6);
+    let evaluator = create_evaluator(game, &evaluator_spec);
+    let mut state = game.new_game();
+    for m in "c3 c4 d3 d4 e3 e4 f3 f4 g3 g4".split(' ') {
+      state.play(m.parse().unwrap()).unwrap();
+    }
+    assert!(!state.is_terminal());
+    evaluator.evaluate(&state);
+
+    let game = Gomoku::default_sized(9, 4);
+    let evaluator = create_evaluator(game, &evaluator_spec);
+    let mut state = game.new_game();
+    for m in "c3 c4 d3 d4 e3 e4".split(' ') {
+      state.play(m.parse().unwrap()).unwrap();
+    }
+    evaluator.evaluate(&state);
+  }
+
   #[test]
   fn hexapawn_features() {
     let agent_spec = AgentSpec::Minimax {
@@ -339,11 +759,15 @@ mod test {
           regularization: 0.001,
         },
       },
+      table_size: 0,
+      ordering: MoveOrdering::Random,
+      multiplayer: Multiplayer::MaxN,
+      threads: 0,
       name: String::new(),
     };
 
     let game = Hexapawn::default(3, 3);
-    let agent = create_agent(game, &agent_spec);
+    let agent = create_agent(game, &agent_spec).unwrap();
     let mut state = game.new_game();
     let report = agent.select_move(&state).unwrap();
     assert!(state.play(report.get_move()).is_ok())
@@ -372,4 +796,55 @@ mod test {
     trainer.build_evaluator();
   }
 
+  #[test]
+  fn gomoku_proximity_mcts() {
+    let game = Gomoku::default();
+    let agent_spec: AgentSpec = serde_json::from_str(
+      r#"{"type": "Mcts", "samples": 100,
+          "policy": {"type": "GomokuProximity", "weights": [2.0, 1.0]},
+          "evaluator": {"type": "Sampler", "samples": 1, "discount": 0.999}}"#,
+    ).unwrap();
+    let agent = create_agent(game, &agent_spec).unwrap();
+    let mut state = game.new_game();
+    state.play(state.parse_move("K10").unwrap()).unwrap();
+    let report = agent.select_move(&state).unwrap();
+    // Within two points from K10.
+    let m = report.get_move().to_string();
+    let (col, row) = m.split_at(1);
+    assert!("HJKLM".contains(col), "{}", m);
+    assert!((8..13).contains(&row.parse::<u32>().unwrap()), "{}", m);
+  }
+
+  #[test]
+  fn gomoku_proximity_other_games() {
+    let policy_spec = PolicySpec::GomokuProximity { weights: vec![2.0, 1.0] };
+    assert!(create_policy(Connect6::default(), &policy_spec).is_err());
+    assert!(create_policy(GomokuSwap2::default(), &policy_spec).is_err());
+    let agent_spec = AgentSpec::Mcts {
+      policy: policy_spec,
+      evaluator: EvaluatorSpec::Terminal,
+      samples: 100,
+      time_per_move: 0.0,
+      selection: Selection::Puct,
+      exploration: 1.0,
+      fpu: None,
+      threads: 0,
+      name: String::new(),
+    };
+    assert!(create_agent(Connect6::default(), &agent_spec).is_err());
+  }
+
+  #[test]
+  fn subtractor_feature_policy() {
+    let game = Subtractor::default(21, 4);
+    let policy_spec: PolicySpec = serde_json::from_str(
+      r#"{"type": "Features", "extractor": {"type": "Subtractor", "content": 4},
+          "params": [0.0, 0.0, 0.0, 5.0]}"#,
+    ).unwrap();
+    let policy = create_policy(game, &policy_spec).unwrap();
+    let moves = policy.get_moves(&game.new_game());
+    assert_eq!(3, moves.len());
+    assert!(moves.iter().any(|&(m, w)| m == 1 && w > 0.9));
+  }
+
 } // mod test
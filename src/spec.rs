@@ -6,17 +6,54 @@ use std::time::Duration;
 
 #[derive(Debug)]
 pub enum GameSpec {
-  Gomoku,
+  Breakthrough(u32, u32),
+  Chess,
+  Connect6,
+  ConnectFour(u32, u32),
+  // Russian draughts if set, English otherwise.
+  Draughts(bool),
+  Go(u32),
+  Gomoku(u32, u32),
+  GomokuSwap2,
+  Hex(u32, bool),
   Hexapawn(u32, u32),
+  // Number of the players.
+  Nim(usize),
+  Othello(u32),
+  Pig(u32),
+  Quarto,
+  Renju,
   Subtractor(u32, u32),
 }
 
 impl GameSpec {
   pub fn parse(s: &str) -> Option<GameSpec> {
     match s {
-      "gomoku" => Some(GameSpec::Gomoku),
+      "breakthrough" => Some(GameSpec::Breakthrough(8, 8)),
+      "breakthrough_6" => Some(GameSpec::Breakthrough(6, 6)),
+      "chess" => Some(GameSpec::Chess),
+      "connect6" => Some(GameSpec::Connect6),
+      "connect_four" => Some(GameSpec::ConnectFour(7, 6)),
+      "draughts" => Some(GameSpec::Draughts(false)),
+      "go" => Some(GameSpec::Go(19)),
+      "go_13" => Some(GameSpec::Go(13)),
+      "go_9" => Some(GameSpec::Go(9)),
+      "gomoku" => Some(GameSpec::Gomoku(19, 5)),
+      "gomoku_15" => Some(GameSpec::Gomoku(15, 5)),
+      "gomoku_9" => Some(GameSpec::Gomoku(9, 5)),
+      "gomoku_swap2" => Some(GameSpec::GomokuSwap2),
+      "hex" => Some(GameSpec::Hex(11, true)),
+      "hex_noswap" => Some(GameSpec::Hex(11, false)),
       "hexapawn" => Some(GameSpec::Hexapawn(8, 8)),
+      "nim" => Some(GameSpec::Nim(2)),
+      "nim_3" => Some(GameSpec::Nim(3)),
+      "othello" => Some(GameSpec::Othello(8)),
+      "pig" => Some(GameSpec::Pig(100)),
+      "quarto" => Some(GameSpec::Quarto),
+      "renju" => Some(GameSpec::Renju),
+      "russian_draughts" => Some(GameSpec::Draughts(true)),
       "subtractor" => Some(GameSpec::Subtractor(21, 4)),
+      "tictactoe" => Some(GameSpec::Gomoku(3, 3)),
       _ => None,
     }
   }
@@ -32,6 +69,19 @@ pub enum AgentSpec {
     #[serde(default)]
     time_per_move: f64,
     evaluator: EvaluatorSpec,
+    // Number of entries in the transposition table. 0 to disable the table.
+    #[serde(default)]
+    table_size: usize,
+    #[serde(default)]
+    ordering: MoveOrdering,
+    // The search in the games with more than two players.
+    #[serde(default)]
+    multiplayer: Multiplayer,
+    // Number of search threads. 0 or 1 for single-threaded search. The helper
+    // threads only share their results through the transposition table, so
+    // they are only started if `table_size` is positive.
+    #[serde(default)]
+    threads: usize,
     #[serde(default)]
     name: String,
   },
@@ -43,10 +93,66 @@ pub enum AgentSpec {
     #[serde(default)]
     time_per_move: f64,
     #[serde(default)]
+    selection: Selection,
+    // The exploration constant in the selection formula.
+    #[serde(default = "default_exploration")]
+    exploration: f32,
+    // First-play urgency: the value of the children that haven't been sampled
+    // yet. By default the unvisited children have score 0 under PUCT and are
+    // always sampled first under UCB1.
+    #[serde(default)]
+    fpu: Option<f32>,
+    // Number of search threads, each growing its own tree. 0 or 1 for
+    // single-threaded search.
+    #[serde(default)]
+    threads: usize,
+    #[serde(default)]
     name: String,
   },
 }
 
+/// The order in which minimax search tries the moves.
+#[derive(Clone, Copy, Serialize, Debug, Default, Deserialize, PartialEq)]
+pub enum MoveOrdering {
+  /// Random order. Only the best move from the transposition table, if any,
+  /// goes first.
+  #[default]
+  Random,
+  /// The move from the principal variation of the previous iteration first,
+  /// then the best move from the transposition table, then killer moves,
+  /// then the rest, sorted by the history heuristic.
+  Ordered,
+}
+
+/// How minimax search treats the opponents in the games with more than two
+/// players.
+#[derive(Clone, Copy, Serialize, Debug, Default, Deserialize, PartialEq)]
+pub enum Multiplayer {
+  /// Max-n: every player maximises its own score.
+  #[default]
+  MaxN,
+  /// Paranoid search: all the other players minimise the score of the player
+  /// at the root, which allows alpha-beta pruning.
+  Paranoid,
+}
+
+/// The formula, by which MCTS selects the child to sample. Here `N` is the
+/// number of samples of the parent, `n`, `q` and `p` are the number of
+/// samples, the average score and the policy weight of the child, and `c` is
+/// the exploration constant.
+#[derive(Clone, Copy, Serialize, Debug, Default, Deserialize, PartialEq)]
+pub enum Selection {
+  /// `q + c * p * sqrt(N) / (n + 1)`
+  #[default]
+  Puct,
+  /// `q + c * sqrt(ln(N) / n)`
+  Ucb1,
+}
+
+fn default_exploration() -> f32 {
+  1.0
+}
+
 #[derive(Clone, Serialize, Debug, Deserialize)]
 #[serde(tag = "type")]
 pub enum EvaluatorSpec {
@@ -65,6 +171,17 @@ pub enum EvaluatorSpec {
 #[serde(tag = "type")]
 pub enum PolicySpec {
   Equal,
+  /// Softmax over a linear function of the features of the position after
+  /// the move. Empty `params` mean all zeros.
+  Features {
+    extractor: FeatureExtractorSpec,
+    #[serde(default)]
+    params: Vec<f32>,
+  },
+  /// Gomoku moves near the existing stones, see `GomokuProximityPolicy`.
+  GomokuProximity {
+    weights: Vec<f32>,
+  },
 }
 
 #[derive(Clone, Serialize, Debug, Deserialize)]
@@ -72,8 +189,15 @@ pub enum PolicySpec {
 pub enum FeatureExtractorSpec {
   Subtractor(u32),
   GomokuLines(u32),
+  Connect6Lines(u32),
   HexapawnNumberOfPawns,
   HexapawnComplete,
+  ConnectFourThreats,
+  Othello,
+  Draughts,
+  Quarto,
+  Chess,
+  Breakthrough,
 }
 
 #[derive(Clone, Serialize, Debug, Deserialize)]
@@ -109,6 +233,13 @@ pub enum TrainerSpec {
     temperature: f32,
     ngames: usize,
   },
+  /// Trains a feature policy from MCTS self-play. The regression params are
+  /// the initial policy params.
+  Policy {
+    mcts_samples: u64,
+    evaluator: EvaluatorSpec,
+    alpha: f32,
+  },
 }
 
 /// First match the string with "random" or "human". If it doesn't, treat it
@@ -140,6 +271,10 @@ pub fn load_agent_spec(
           depth: _,
           time_per_move: ref mut t,
           evaluator: _,
+          table_size: _,
+          ordering: _,
+          multiplayer: _,
+          threads: _,
           ref mut name,
         } => {
           if time_per_move > 0.0 {
@@ -154,6 +289,10 @@ pub fn load_agent_spec(
           evaluator: _,
           samples: _,
           time_per_move: ref mut t,
+          selection: _,
+          exploration: _,
+          fpu: _,
+          threads: _,
           ref mut name,
         } => {
           if time_per_move > 0.0 {
@@ -214,6 +353,10 @@ mod test {
           regularization: 0.001,
         },
       },
+      table_size: 0,
+      ordering: MoveOrdering::Random,
+      multiplayer: Multiplayer::MaxN,
+      threads: 0,
       name: String::new(),
     };
 
@@ -225,12 +368,85 @@ mod test {
     assert_eq!(agent_json, agent_json2);
   }
 
+  #[test]
+  fn minimax_defaults() {
+    let agent_spec: AgentSpec = serde_json::from_str(
+      r#"{"type": "Minimax", "depth": 3, "evaluator": {"type": "Terminal"}}"#,
+    ).unwrap();
+    match agent_spec {
+      AgentSpec::Minimax {
+        depth,
+        time_per_move,
+        evaluator: _,
+        table_size,
+        ordering,
+        multiplayer,
+        threads,
+        name: _,
+      } => {
+        assert_eq!(3, depth);
+        assert_eq!(0.0, time_per_move);
+        assert_eq!(0, table_size);
+        assert_eq!(MoveOrdering::Random, ordering);
+        assert_eq!(Multiplayer::MaxN, multiplayer);
+        assert_eq!(0, threads);
+      }
+      _ => panic!(),
+    }
+
+    let agent_spec: AgentSpec = serde_json::from_str(
+      r#"{"type": "Minimax", "depth": 3, "evaluator": {"type": "Terminal"},
+          "ordering": "Ordered", "multiplayer": "Paranoid"}"#,
+    ).unwrap();
+    match agent_spec {
+      AgentSpec::Minimax { ordering, multiplayer, .. } => {
+        assert_eq!(MoveOrdering::Ordered, ordering);
+        assert_eq!(Multiplayer::Paranoid, multiplayer);
+      }
+      _ => panic!(),
+    }
+  }
+
+  #[test]
+  fn mcts_selection() {
+    let agent_spec: AgentSpec = serde_json::from_str(
+      r#"{"type": "Mcts", "policy": {"type": "Equal"},
+          "evaluator": {"type": "Terminal"}}"#,
+    ).unwrap();
+    match agent_spec {
+      AgentSpec::Mcts { selection, exploration, fpu, .. } => {
+        assert_eq!(Selection::Puct, selection);
+        assert_eq!(1.0, exploration);
+        assert_eq!(None, fpu);
+      }
+      _ => panic!(),
+    }
+
+    let agent_spec: AgentSpec = serde_json::from_str(
+      r#"{"type": "Mcts", "policy": {"type": "Equal"},
+          "evaluator": {"type": "Terminal"},
+          "selection": "Ucb1", "exploration": 1.4, "fpu": 0.5}"#,
+    ).unwrap();
+    match agent_spec {
+      AgentSpec::Mcts { selection, exploration, fpu, .. } => {
+        assert_eq!(Selection::Ucb1, selection);
+        assert_eq!(1.4, exploration);
+        assert_eq!(Some(0.5), fpu);
+      }
+      _ => panic!(),
+    }
+  }
+
   #[test]
   fn terminal_evaluator() {
     let agent_spec = AgentSpec::Minimax {
       depth: 3,
       time_per_move: 0.0,
       evaluator: EvaluatorSpec::Terminal,
+      table_size: 0,
+      ordering: MoveOrdering::Random,
+      multiplayer: Multiplayer::MaxN,
+      threads: 0,
       name: String::new(),
     };
 
@@ -0,0 +1,69 @@
+//! Pseudo-random keys for Zobrist hashing of board positions.
+//!
+//! The hash of a position is the XOR of the keys of all the stones on the
+//! board, and of `player_key()` if it's the second player's turn. This allows
+//! to update the hash incrementally in `play()` and `undo()`.
+
+use lazy_static::lazy_static;
+
+// Enough keys for the two players on the largest supported `Board`.
+const NPOINTS: usize = 25 * 25;
+
+// Number of the keys for the game-specific flags.
+const NFLAGS: usize = 4;
+
+lazy_static! {
+  static ref KEYS: Vec<u64> = gen_keys(2 * NPOINTS + 1 + NFLAGS);
+}
+
+/// The key of a stone of the given player on the given point.
+pub fn point_key(point: usize, player: bool) -> u64 {
+  debug_assert!(point < NPOINTS);
+  KEYS[2 * point + if player { 0 } else { 1 }]
+}
+
+/// The key that is included in the hash when it's the second player's turn.
+pub fn player_key() -> u64 {
+  KEYS[2 * NPOINTS]
+}
+
+/// The key of a game-specific flag of the position, that is not reflected in
+/// the stones on the board, e.g. a choice made in the opening.
+pub fn flag_key(flag: usize) -> u64 {
+  debug_assert!(flag < NFLAGS);
+  KEYS[2 * NPOINTS + 1 + flag]
+}
+
+// SplitMix64 generator with a fixed seed, so that hashes are stable between
+// runs.
+fn gen_keys(n: usize) -> Vec<u64> {
+  let mut x: u64 = 0x9E37_79B9_7F4A_7C15;
+  let mut keys = Vec::with_capacity(n);
+  for _ in 0..n {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = x;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    keys.push(z ^ (z >> 31));
+  }
+  keys
+}
+
+#[cfg(test)]
+mod test {
+
+use super::*;
+
+#[test]
+fn distinct_keys() {
+  let mut keys: Vec<u64> = (0..NPOINTS)
+    .flat_map(|p| vec![point_key(p, true), point_key(p, false)])
+    .collect();
+  keys.push(player_key());
+  keys.extend((0..NFLAGS).map(flag_key));
+  keys.sort();
+  keys.dedup();
+  assert_eq!(2 * NPOINTS + 1 + NFLAGS, keys.len());
+}
+
+}  // mod test